@@ -4,6 +4,7 @@ import { Construct } from "constructs";
 import * as lambda from "aws-cdk-lib/aws-lambda";
 import * as apigw from "aws-cdk-lib/aws-apigateway";
 import * as dynamodb from "aws-cdk-lib/aws-dynamodb";
+import * as secretsmanager from "aws-cdk-lib/aws-secretsmanager";
 
 export interface InfraStackProps extends cdk.StackProps {
   assetPath?: string;
@@ -20,6 +21,91 @@ export class InfraStack extends cdk.Stack {
       removalPolicy: cdk.RemovalPolicy.DESTROY, // NOT recommended for production code
     });
 
+    // Lets GET /wishlists?owner=... query by owner instead of scanning
+    wishlistTable.addGlobalSecondaryIndex({
+      indexName: "owner-index",
+      partitionKey: { name: "owner", type: dynamodb.AttributeType.STRING },
+      sortKey: { name: "id", type: dynamodb.AttributeType.STRING },
+      projectionType: dynamodb.ProjectionType.ALL,
+    });
+
+    // Who each wishlist is shared with, and in what role
+    const collaboratorTable = new dynamodb.Table(this, "CollaboratorTable", {
+      tableName: "collaborator_table",
+      partitionKey: { name: "wishlist_id", type: dynamodb.AttributeType.STRING },
+      sortKey: { name: "subject", type: dynamodb.AttributeType.STRING },
+      removalPolicy: cdk.RemovalPolicy.DESTROY, // NOT recommended for production code
+    });
+
+    // Lets "lists shared with me" query by collaborator instead of scanning
+    collaboratorTable.addGlobalSecondaryIndex({
+      indexName: "subject-index",
+      partitionKey: { name: "subject", type: dynamodb.AttributeType.STRING },
+      sortKey: { name: "wishlist_id", type: dynamodb.AttributeType.STRING },
+      projectionType: dynamodb.ProjectionType.ALL,
+    });
+
+    // Public share links, keyed on the token's hash, listed per wishlist
+    const shareTable = new dynamodb.Table(this, "ShareTable", {
+      tableName: "share_table",
+      partitionKey: { name: "id", type: dynamodb.AttributeType.STRING },
+      removalPolicy: cdk.RemovalPolicy.DESTROY, // NOT recommended for production code
+    });
+
+    shareTable.addGlobalSecondaryIndex({
+      indexName: "wishlist-index",
+      partitionKey: { name: "wishlist_id", type: dynamodb.AttributeType.STRING },
+      sortKey: { name: "id", type: dynamodb.AttributeType.STRING },
+      projectionType: dynamodb.ProjectionType.ALL,
+    });
+
+    // Gift reservations, one record per item holding every reserver's claim
+    const reservationTable = new dynamodb.Table(this, "ReservationTable", {
+      tableName: "reservation_table",
+      partitionKey: { name: "wishlist_id", type: dynamodb.AttributeType.STRING },
+      sortKey: { name: "item_id", type: dynamodb.AttributeType.STRING },
+      removalPolicy: cdk.RemovalPolicy.DESTROY, // NOT recommended for production code
+    });
+
+    // Group gift pledges, next to a funding total per item
+    const pledgeTable = new dynamodb.Table(this, "PledgeTable", {
+      tableName: "pledge_table",
+      partitionKey: { name: "wishlist_id", type: dynamodb.AttributeType.STRING },
+      sortKey: { name: "id", type: dynamodb.AttributeType.STRING },
+      removalPolicy: cdk.RemovalPolicy.DESTROY, // NOT recommended for production code
+    });
+
+    // Gift exchanges, with their participants and draw
+    const exchangeTable = new dynamodb.Table(this, "ExchangeTable", {
+      tableName: "exchange_table",
+      partitionKey: { name: "id", type: dynamodb.AttributeType.STRING },
+      removalPolicy: cdk.RemovalPolicy.DESTROY, // NOT recommended for production code
+    });
+
+    // API keys for scripts, hashed, listed per owner
+    const apiKeyTable = new dynamodb.Table(this, "ApiKeyTable", {
+      tableName: "api_key_table",
+      partitionKey: { name: "id", type: dynamodb.AttributeType.STRING },
+      removalPolicy: cdk.RemovalPolicy.DESTROY, // NOT recommended for production code
+    });
+
+    apiKeyTable.addGlobalSecondaryIndex({
+      indexName: "owner-index",
+      partitionKey: { name: "owner", type: dynamodb.AttributeType.STRING },
+      sortKey: { name: "id", type: dynamodb.AttributeType.STRING },
+      projectionType: dynamodb.ProjectionType.ALL,
+    });
+
+    // Key every instance signs pagination cursors with
+    const cursorSecret = new secretsmanager.Secret(this, "CursorSecret", {
+      description: "Signs wishlist pagination cursors",
+      generateSecretString: {
+        passwordLength: 64,
+        excludePunctuation: true,
+      },
+      removalPolicy: cdk.RemovalPolicy.DESTROY, // NOT recommended for production code
+    });
+
     // Lambda function
     const wishLambda = new lambda.Function(this, "WishHandler", {
       runtime: lambda.Runtime.PROVIDED_AL2,
@@ -30,11 +116,18 @@ export class InfraStack extends cdk.Stack {
       environment: {
         DUMMY_VAR: "1",
         TABLE_NAME: wishlistTable.tableName,
+        CURSOR_SECRET: cursorSecret.secretValue.unsafeUnwrap(),
       },
     });
 
     // Grant Lambda permissions to read/write from the DynamoDB table
     wishlistTable.grantReadWriteData(wishLambda);
+    collaboratorTable.grantReadWriteData(wishLambda);
+    shareTable.grantReadWriteData(wishLambda);
+    reservationTable.grantReadWriteData(wishLambda);
+    pledgeTable.grantReadWriteData(wishLambda);
+    exchangeTable.grantReadWriteData(wishLambda);
+    apiKeyTable.grantReadWriteData(wishLambda);
 
     // API Gateway
     new apigw.LambdaRestApi(this, "WishApi", {
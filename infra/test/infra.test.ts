@@ -1,6 +1,6 @@
 import * as cdk from "aws-cdk-lib";
 import { Construct } from "constructs";
-import { Template } from "aws-cdk-lib/assertions";
+import { Match, Template } from "aws-cdk-lib/assertions";
 import * as Infra from "../lib/infra-stack";
 
 class TestableInfraStack extends Infra.InfraStack {
@@ -56,4 +56,115 @@ describe("Infrastructure Tests", () => {
     });
     console.log("API Gateway test completed");
   });
+
+  test("Lambda Gets Cursor Secret", async () => {
+    const app = new cdk.App();
+    const stack = new TestableInfraStack(app, "TestStack");
+    const template = Template.fromStack(stack);
+
+    template.resourceCountIs("AWS::SecretsManager::Secret", 1);
+    template.hasResourceProperties("AWS::Lambda::Function", {
+      Environment: {
+        Variables: Match.objectLike({ CURSOR_SECRET: Match.anyValue() }),
+      },
+    });
+  });
+
+  test.each<[string, Record<string, unknown>]>([
+    [
+      "wishlist_table",
+      {
+        GlobalSecondaryIndexes: [
+          {
+            IndexName: "owner-index",
+            KeySchema: [
+              { AttributeName: "owner", KeyType: "HASH" },
+              { AttributeName: "id", KeyType: "RANGE" },
+            ],
+          },
+        ],
+      },
+    ],
+    [
+      "collaborator_table",
+      {
+        KeySchema: [
+          { AttributeName: "wishlist_id", KeyType: "HASH" },
+          { AttributeName: "subject", KeyType: "RANGE" },
+        ],
+        GlobalSecondaryIndexes: [
+          {
+            IndexName: "subject-index",
+            KeySchema: [
+              { AttributeName: "subject", KeyType: "HASH" },
+              { AttributeName: "wishlist_id", KeyType: "RANGE" },
+            ],
+          },
+        ],
+      },
+    ],
+    [
+      "share_table",
+      {
+        KeySchema: [{ AttributeName: "id", KeyType: "HASH" }],
+        GlobalSecondaryIndexes: [
+          {
+            IndexName: "wishlist-index",
+            KeySchema: [
+              { AttributeName: "wishlist_id", KeyType: "HASH" },
+              { AttributeName: "id", KeyType: "RANGE" },
+            ],
+          },
+        ],
+      },
+    ],
+    [
+      "reservation_table",
+      {
+        KeySchema: [
+          { AttributeName: "wishlist_id", KeyType: "HASH" },
+          { AttributeName: "item_id", KeyType: "RANGE" },
+        ],
+      },
+    ],
+    [
+      "pledge_table",
+      {
+        KeySchema: [
+          { AttributeName: "wishlist_id", KeyType: "HASH" },
+          { AttributeName: "id", KeyType: "RANGE" },
+        ],
+      },
+    ],
+    [
+      "exchange_table",
+      {
+        KeySchema: [{ AttributeName: "id", KeyType: "HASH" }],
+      },
+    ],
+    [
+      "api_key_table",
+      {
+        KeySchema: [{ AttributeName: "id", KeyType: "HASH" }],
+        GlobalSecondaryIndexes: [
+          {
+            IndexName: "owner-index",
+            KeySchema: [
+              { AttributeName: "owner", KeyType: "HASH" },
+              { AttributeName: "id", KeyType: "RANGE" },
+            ],
+          },
+        ],
+      },
+    ],
+  ])("Table %s Has Its Keys And Indexes", async (tableName, keys) => {
+    const app = new cdk.App();
+    const stack = new TestableInfraStack(app, "TestStack");
+    const template = Template.fromStack(stack);
+
+    template.hasResourceProperties("AWS::DynamoDB::Table", {
+      TableName: tableName,
+      ...keys,
+    });
+  });
 });
@@ -0,0 +1,12 @@
+CREATE TABLE wishlists (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    owner TEXT NOT NULL
+);
+
+CREATE TABLE wishlist_items (
+    wishlist_id TEXT NOT NULL REFERENCES wishlists (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (wishlist_id, position)
+);
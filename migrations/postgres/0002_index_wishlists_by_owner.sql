@@ -0,0 +1 @@
+CREATE INDEX wishlists_owner_idx ON wishlists (owner, id);
@@ -0,0 +1 @@
+ALTER TABLE wishlists ADD COLUMN version BIGINT NOT NULL DEFAULT 0;
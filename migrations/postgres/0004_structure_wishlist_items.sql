@@ -0,0 +1,14 @@
+-- Existing items keep their text as the name and get the position-derived IDs
+-- that legacy DynamoDB items are read with.
+ALTER TABLE wishlist_items RENAME COLUMN value TO name;
+ALTER TABLE wishlist_items
+    ADD COLUMN item_id TEXT,
+    ADD COLUMN url TEXT,
+    ADD COLUMN price_cents BIGINT,
+    ADD COLUMN currency TEXT,
+    ADD COLUMN quantity INTEGER NOT NULL DEFAULT 1,
+    ADD COLUMN priority TEXT,
+    ADD COLUMN notes TEXT;
+UPDATE wishlist_items SET item_id = 'item-' || position;
+ALTER TABLE wishlist_items ALTER COLUMN item_id SET NOT NULL;
+CREATE UNIQUE INDEX wishlist_items_item_id_idx ON wishlist_items (wishlist_id, item_id);
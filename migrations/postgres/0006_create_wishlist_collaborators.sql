@@ -0,0 +1,9 @@
+CREATE TABLE wishlist_collaborators (
+    wishlist_id TEXT NOT NULL REFERENCES wishlists (id) ON DELETE CASCADE,
+    subject TEXT NOT NULL,
+    role TEXT NOT NULL,
+    PRIMARY KEY (wishlist_id, subject)
+);
+
+-- Answers "lists shared with me" without scanning.
+CREATE INDEX wishlist_collaborators_subject_idx ON wishlist_collaborators (subject, wishlist_id);
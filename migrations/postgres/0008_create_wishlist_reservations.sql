@@ -0,0 +1,11 @@
+-- Not tied to `wishlist_items`, whose rows are rewritten on every update;
+-- reservations of removed items are ignored when read.
+CREATE TABLE wishlist_reservations (
+    wishlist_id TEXT NOT NULL REFERENCES wishlists (id) ON DELETE CASCADE,
+    item_id TEXT NOT NULL,
+    reserver TEXT NOT NULL,
+    quantity INTEGER NOT NULL,
+    purchased BOOLEAN NOT NULL DEFAULT FALSE,
+    reserved_at BIGINT NOT NULL,
+    PRIMARY KEY (wishlist_id, item_id, reserver)
+);
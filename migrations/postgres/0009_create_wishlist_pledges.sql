@@ -0,0 +1,13 @@
+-- Like reservations, pledges aren't tied to `wishlist_items` rows.
+CREATE TABLE wishlist_pledges (
+    id TEXT PRIMARY KEY,
+    wishlist_id TEXT NOT NULL REFERENCES wishlists (id) ON DELETE CASCADE,
+    item_id TEXT NOT NULL,
+    contributor TEXT NOT NULL,
+    amount_cents BIGINT NOT NULL,
+    currency TEXT NOT NULL,
+    message TEXT,
+    pledged_at BIGINT NOT NULL
+);
+
+CREATE INDEX wishlist_pledges_item_idx ON wishlist_pledges (wishlist_id, item_id, pledged_at);
@@ -0,0 +1,21 @@
+-- `previous_exchange_id` isn't a foreign key: last year's exchange may since
+-- have been deleted.
+CREATE TABLE exchanges (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    organizer TEXT NOT NULL,
+    previous_exchange_id TEXT,
+    created_at BIGINT NOT NULL,
+    drawn_at BIGINT
+);
+
+-- `receiver` stays NULL until the names are drawn.
+CREATE TABLE exchange_participants (
+    exchange_id TEXT NOT NULL REFERENCES exchanges (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    subject TEXT NOT NULL,
+    wishlist_id TEXT NOT NULL,
+    spouse TEXT,
+    receiver TEXT,
+    PRIMARY KEY (exchange_id, subject)
+);
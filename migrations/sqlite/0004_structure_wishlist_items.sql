@@ -0,0 +1,12 @@
+-- Existing items keep their text as the name and get the position-derived IDs
+-- that legacy DynamoDB items are read with.
+ALTER TABLE wishlist_items RENAME COLUMN value TO name;
+ALTER TABLE wishlist_items ADD COLUMN item_id TEXT;
+ALTER TABLE wishlist_items ADD COLUMN url TEXT;
+ALTER TABLE wishlist_items ADD COLUMN price_cents INTEGER;
+ALTER TABLE wishlist_items ADD COLUMN currency TEXT;
+ALTER TABLE wishlist_items ADD COLUMN quantity INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE wishlist_items ADD COLUMN priority TEXT;
+ALTER TABLE wishlist_items ADD COLUMN notes TEXT;
+UPDATE wishlist_items SET item_id = 'item-' || position;
+CREATE UNIQUE INDEX wishlist_items_item_id_idx ON wishlist_items (wishlist_id, item_id);
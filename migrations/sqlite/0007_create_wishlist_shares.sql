@@ -0,0 +1,12 @@
+-- `id` is the SHA-256 of the share token; the token itself isn't stored.
+CREATE TABLE wishlist_shares (
+    id TEXT PRIMARY KEY NOT NULL,
+    wishlist_id TEXT NOT NULL REFERENCES wishlists (id) ON DELETE CASCADE,
+    created_by TEXT,
+    created_at BIGINT NOT NULL,
+    expires_at BIGINT,
+    password_hash TEXT,
+    revoked_at BIGINT
+);
+
+CREATE INDEX wishlist_shares_wishlist_idx ON wishlist_shares (wishlist_id, created_at);
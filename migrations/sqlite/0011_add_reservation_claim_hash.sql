@@ -0,0 +1,2 @@
+-- SHA-256 of the claim token a share link guest got with their reservation.
+ALTER TABLE wishlist_reservations ADD COLUMN claim_hash TEXT;
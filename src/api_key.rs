@@ -0,0 +1,162 @@
+use lambda_http::http::header::AUTHORIZATION;
+use lambda_http::Request;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::auth::Principal;
+use crate::db::WishlistStore;
+use crate::error::AppError;
+use crate::secret::{constant_time_eq, random_token, sha256_hex};
+use crate::utils::now;
+
+/// Prefix of every API key, so leaked keys are easy to recognise in logs and
+/// secret scanners.
+pub const KEY_PREFIX: &str = "wl_";
+
+/// `last_used_at` is only rewritten once this many seconds have passed, so a
+/// busy script doesn't turn every read into a write.
+const LAST_USED_RESOLUTION: u64 = 60;
+
+/// What a key may do on its owner's behalf.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ApiKeyScope {
+    /// `GET` and `HEAD` only.
+    #[default]
+    ReadOnly,
+    ReadWrite,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiKeyScope::ReadOnly => "read-only",
+            ApiKeyScope::ReadWrite => "read-write",
+        }
+    }
+}
+
+impl FromStr for ApiKeyScope {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read-only" => Ok(ApiKeyScope::ReadOnly),
+            "read-write" => Ok(ApiKeyScope::ReadWrite),
+            other => Err(AppError::Generic(format!(
+                "Unknown API key scope: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// A stored API key. Only a hash of the secret is kept; the full key is shown
+/// once, when it is created.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiKey {
+    pub id: String,
+    pub owner: String,
+    /// Label chosen by the owner, e.g. the script using the key.
+    pub name: String,
+    pub scope: ApiKeyScope,
+    /// Hex-encoded SHA-256 of the secret part of the key.
+    #[serde(skip)]
+    pub secret_hash: String,
+    /// Timestamps are Unix times in seconds.
+    pub created_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<u64>,
+}
+
+impl ApiKey {
+    /// Makes a new key for `owner`, returning it with the full key to hand to
+    /// the client.
+    pub fn generate(owner: &str, name: &str, scope: ApiKeyScope) -> (ApiKey, String) {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let secret = random_token();
+        let token = format!("{}{}.{}", KEY_PREFIX, id, secret);
+        let key = ApiKey {
+            id,
+            owner: owner.to_string(),
+            name: name.to_string(),
+            scope,
+            secret_hash: sha256_hex(&secret),
+            created_at: now(),
+            last_used_at: None,
+            revoked_at: None,
+        };
+        (key, token)
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+
+    /// Whether `secret` is this key's secret, compared in constant time.
+    pub fn matches(&self, secret: &str) -> bool {
+        constant_time_eq(&sha256_hex(secret), &self.secret_hash)
+    }
+}
+
+/// Body of `POST /api-keys`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiKeyRequest {
+    pub name: String,
+    #[serde(default)]
+    pub scope: ApiKeyScope,
+}
+
+/// Response to `POST /api-keys`: the stored key plus the full key, which is
+/// never returned again.
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+/// The API key sent in `X-Api-Key` or `Authorization: ApiKey <key>`, if any.
+pub fn presented_key(event: &Request) -> Option<&str> {
+    if let Some(value) = event.headers().get("X-Api-Key") {
+        return value.to_str().ok().map(str::trim);
+    }
+    let value = event.headers().get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, key) = value.split_once(' ')?;
+    scheme.eq_ignore_ascii_case("apikey").then(|| key.trim())
+}
+
+/// Identifies the caller from an API key, acting as the key's owner with the
+/// key's scope and no roles.
+pub async fn authenticate(store: &dyn WishlistStore, token: &str) -> Result<Principal, AppError> {
+    let (id, secret) = token
+        .strip_prefix(KEY_PREFIX)
+        .and_then(|rest| rest.split_once('.'))
+        .ok_or_else(|| AppError::Unauthorized("Malformed API key".to_string()))?;
+    let key = match store.get_api_key(id.to_string()).await? {
+        Some(key) if key.matches(secret) => key,
+        _ => return Err(AppError::Unauthorized("Unknown API key".to_string())),
+    };
+    if key.is_revoked() {
+        return Err(AppError::Unauthorized(
+            "API key has been revoked".to_string(),
+        ));
+    }
+    let now = now();
+    if key
+        .last_used_at
+        .is_none_or(|used| now >= used + LAST_USED_RESOLUTION)
+    {
+        if let Err(e) = store.touch_api_key(key.id.clone(), now).await {
+            warn!("Could not record use of API key {}: {}", key.id, e);
+        }
+    }
+    Ok(Principal {
+        subject: key.owner,
+        roles: Vec::new(),
+        api_key: Some(key.scope),
+    })
+}
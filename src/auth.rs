@@ -0,0 +1,312 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Empty};
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Header, Validation};
+use lambda_http::http::header::AUTHORIZATION;
+use lambda_http::Request;
+use log::{info, warn};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+use crate::api_key::ApiKeyScope;
+use crate::error::AppError;
+
+/// The caller a request was authenticated as, by bearer token or API key.
+/// Put in the request's extensions by `handle_request` when authentication
+/// is enabled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    /// The token's `sub`; becomes the `owner` of wishlists the caller creates.
+    pub subject: String,
+    pub roles: Vec<String>,
+    /// Scope of the API key the caller used, or `None` for a bearer token.
+    pub api_key: Option<ApiKeyScope>,
+}
+
+impl Principal {
+    pub fn from_request(event: &Request) -> Option<&Principal> {
+        event.extensions().get::<Principal>()
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Claims {
+    sub: String,
+    #[serde(default)]
+    roles: Vec<String>,
+}
+
+/// Somewhere to find the key that verifies a token.
+#[async_trait]
+pub trait KeySource: Send + Sync {
+    /// The key for a token with this header, or `None` if this source has no
+    /// key for it, so another source may be tried.
+    async fn key(&self, header: &Header) -> Result<Option<DecodingKey>, AppError>;
+}
+
+/// HS256 with a shared secret.
+pub struct HmacSecret {
+    key: DecodingKey,
+}
+
+impl HmacSecret {
+    pub fn new(secret: &[u8]) -> Self {
+        HmacSecret {
+            key: DecodingKey::from_secret(secret),
+        }
+    }
+}
+
+#[async_trait]
+impl KeySource for HmacSecret {
+    async fn key(&self, header: &Header) -> Result<Option<DecodingKey>, AppError> {
+        Ok((header.alg == Algorithm::HS256).then(|| self.key.clone()))
+    }
+}
+
+/// RS256 and ES256 keys from a JWKS document loaded once.
+pub struct StaticJwks {
+    keys: JwkSet,
+}
+
+impl StaticJwks {
+    pub fn from_json(json: &str) -> Result<Self, AppError> {
+        let keys = serde_json::from_str(json)
+            .map_err(|e| AppError::Generic(format!("Invalid JWKS: {}", e)))?;
+        Ok(StaticJwks { keys })
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, AppError> {
+        StaticJwks::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+#[async_trait]
+impl KeySource for StaticJwks {
+    async fn key(&self, header: &Header) -> Result<Option<DecodingKey>, AppError> {
+        jwks_key(&self.keys, header)
+    }
+}
+
+/// How long fetched keys are trusted before the JWKS is fetched again.
+const JWKS_TTL: Duration = Duration::from_secs(600);
+/// Minimum gap between fetches triggered by an unknown `kid`, so tokens with
+/// made-up key IDs can't be used to hammer the endpoint.
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(30);
+
+/// RS256 and ES256 keys from a JWKS endpoint, such as a local identity
+/// provider. Only plain `http://` URLs are supported.
+pub struct RemoteJwks {
+    url: String,
+    cache: RwLock<Option<(JwkSet, Instant)>>,
+}
+
+impl RemoteJwks {
+    pub fn new(url: impl Into<String>) -> Self {
+        RemoteJwks {
+            url: url.into(),
+            cache: RwLock::new(None),
+        }
+    }
+
+    async fn fetch(&self) -> Result<JwkSet, AppError> {
+        let uri = self
+            .url
+            .parse()
+            .map_err(|e| AppError::Generic(format!("Invalid JWKS_URL: {}", e)))?;
+        let client = Client::builder(TokioExecutor::new()).build_http::<Empty<Bytes>>();
+        let response = client
+            .get(uri)
+            .await
+            .map_err(|e| AppError::Unavailable(format!("Fetching JWKS failed: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(AppError::Unavailable(format!(
+                "Fetching JWKS failed with status {}",
+                response.status()
+            )));
+        }
+        let body = response.into_body().collect().await?.to_bytes();
+        info!("Fetched JWKS from {}", self.url);
+        serde_json::from_slice(&body)
+            .map_err(|e| AppError::Unavailable(format!("Invalid JWKS from {}: {}", self.url, e)))
+    }
+}
+
+#[async_trait]
+impl KeySource for RemoteJwks {
+    async fn key(&self, header: &Header) -> Result<Option<DecodingKey>, AppError> {
+        if let Some((keys, fetched)) = self.cache.read().await.as_ref() {
+            if fetched.elapsed() < JWKS_TTL {
+                if let Some(key) = jwks_key(keys, header)? {
+                    return Ok(Some(key));
+                }
+            }
+            if fetched.elapsed() < JWKS_MIN_REFRESH {
+                return Ok(None);
+            }
+        }
+        let mut cache = self.cache.write().await;
+        // Another request may have refreshed while this one waited.
+        if let Some((keys, fetched)) = cache.as_ref() {
+            if fetched.elapsed() < JWKS_MIN_REFRESH {
+                return jwks_key(keys, header);
+            }
+        }
+        let keys = self.fetch().await?;
+        let key = jwks_key(&keys, header)?;
+        *cache = Some((keys, Instant::now()));
+        Ok(key)
+    }
+}
+
+/// Picks the key for `header` out of a JWKS: by `kid` when the token has one,
+/// otherwise the only key for its algorithm.
+fn jwks_key(keys: &JwkSet, header: &Header) -> Result<Option<DecodingKey>, AppError> {
+    if !matches!(header.alg, Algorithm::RS256 | Algorithm::ES256) {
+        return Ok(None);
+    }
+    let candidates: Vec<&Jwk> = match &header.kid {
+        Some(kid) => keys.find(kid).into_iter().collect(),
+        None => keys
+            .keys
+            .iter()
+            .filter(|jwk| jwk_algorithm(jwk) == Some(header.alg))
+            .collect(),
+    };
+    match candidates.as_slice() {
+        [jwk] if jwk_algorithm(jwk) == Some(header.alg) => DecodingKey::from_jwk(jwk)
+            .map(Some)
+            .map_err(|e| AppError::Generic(format!("Unusable JWK: {}", e))),
+        _ => Ok(None),
+    }
+}
+
+fn jwk_algorithm(jwk: &Jwk) -> Option<Algorithm> {
+    match &jwk.algorithm {
+        AlgorithmParameters::RSA(_) => Some(Algorithm::RS256),
+        AlgorithmParameters::EllipticCurve(params) if params.curve == EllipticCurve::P256 => {
+            Some(Algorithm::ES256)
+        }
+        _ => None,
+    }
+}
+
+/// Verifies bearer tokens against the configured key sources.
+pub struct Authenticator {
+    sources: Vec<Arc<dyn KeySource>>,
+    issuer: Option<String>,
+    audience: Option<String>,
+}
+
+impl Authenticator {
+    pub fn new(sources: Vec<Arc<dyn KeySource>>) -> Self {
+        Authenticator {
+            sources,
+            issuer: None,
+            audience: None,
+        }
+    }
+
+    /// Requires tokens to carry this `iss`.
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = Some(issuer.into());
+        self
+    }
+
+    /// Requires tokens to carry this `aud`.
+    pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+        self.audience = Some(audience.into());
+        self
+    }
+
+    /// Builds an authenticator from `JWT_HS256_SECRET`, `JWKS_PATH` and
+    /// `JWKS_URL`, any of which may be combined, plus optional `JWT_ISSUER`
+    /// and `JWT_AUDIENCE`. Returns `None`, leaving the API open, when no key
+    /// source is configured.
+    pub fn from_env() -> Result<Option<Self>, AppError> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let mut sources: Vec<Arc<dyn KeySource>> = Vec::new();
+        if let Some(secret) = var("JWT_HS256_SECRET") {
+            sources.push(Arc::new(HmacSecret::new(secret.as_bytes())));
+        }
+        if let Some(path) = var("JWKS_PATH") {
+            sources.push(Arc::new(StaticJwks::from_file(&path)?));
+        }
+        if let Some(url) = var("JWKS_URL") {
+            sources.push(Arc::new(RemoteJwks::new(url)));
+        }
+        if sources.is_empty() {
+            warn!("No JWT key source configured; authentication is disabled");
+            return Ok(None);
+        }
+        let mut authenticator = Authenticator::new(sources);
+        if let Some(issuer) = var("JWT_ISSUER") {
+            authenticator = authenticator.with_issuer(issuer);
+        }
+        if let Some(audience) = var("JWT_AUDIENCE") {
+            authenticator = authenticator.with_audience(audience);
+        }
+        Ok(Some(authenticator))
+    }
+
+    /// Identifies the caller from an `Authorization: Bearer` header.
+    pub async fn authenticate(&self, event: &Request) -> Result<Principal, AppError> {
+        let token = bearer_token(event)
+            .ok_or_else(|| AppError::Unauthorized("A bearer token is required".to_string()))?;
+        self.verify(token).await
+    }
+
+    pub async fn verify(&self, token: &str) -> Result<Principal, AppError> {
+        let header = decode_header(token)
+            .map_err(|e| AppError::Unauthorized(format!("Malformed token: {}", e)))?;
+        let mut key = None;
+        for source in &self.sources {
+            key = source.key(&header).await?;
+            if key.is_some() {
+                break;
+            }
+        }
+        let key = key.ok_or_else(|| {
+            AppError::Unauthorized(format!("No key to verify a {:?} token", header.alg))
+        })?;
+
+        let mut validation = Validation::new(header.alg);
+        match &self.issuer {
+            Some(issuer) => validation.set_issuer(&[issuer]),
+            None => validation.iss = None,
+        }
+        match &self.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+        let claims = decode::<Claims>(token, &key, &validation)
+            .map_err(|e| AppError::Unauthorized(format!("Invalid token: {}", e)))?
+            .claims;
+        if claims.sub.is_empty() {
+            return Err(AppError::Unauthorized("Token has no subject".to_string()));
+        }
+        Ok(Principal {
+            subject: claims.sub,
+            roles: claims.roles,
+            api_key: None,
+        })
+    }
+}
+
+fn bearer_token(event: &Request) -> Option<&str> {
+    let value = event.headers().get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim())
+        .filter(|token| !token.is_empty())
+}
@@ -0,0 +1,70 @@
+use log::warn;
+
+use crate::error::AppError;
+use crate::validation::Limits;
+
+/// Runtime settings read from the environment at startup.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Key used to sign pagination cursors. Every instance serving the same
+    /// table must share it, or cursors issued by one are rejected by another.
+    pub cursor_secret: Vec<u8>,
+    /// Whether `POST /wishlists` may keep an `id` sent by the client instead
+    /// of generating one. Off unless `ALLOW_CLIENT_IDS` is `true` or `1`.
+    pub allow_client_ids: bool,
+    /// Bounds enforced on request bodies before they are stored.
+    pub limits: Limits,
+}
+
+impl Config {
+    /// Fails when `CURSOR_SECRET` is unset on Lambda, where every instance
+    /// would otherwise sign cursors with a key of its own. Running locally a
+    /// random key is used instead.
+    pub fn from_env() -> Result<Self, AppError> {
+        let cursor_secret = match std::env::var("CURSOR_SECRET") {
+            Ok(secret) if !secret.is_empty() => secret.into_bytes(),
+            _ if on_lambda() => {
+                return Err(AppError::Generic(
+                    "CURSOR_SECRET must be set when running on Lambda".to_string(),
+                ))
+            }
+            _ => {
+                warn!(
+                    "CURSOR_SECRET not set, using a random key; cursors will not survive restarts"
+                );
+                random_secret()
+            }
+        };
+        let allow_client_ids = matches!(
+            std::env::var("ALLOW_CLIENT_IDS").as_deref(),
+            Ok("true") | Ok("1")
+        );
+        Ok(Config {
+            cursor_secret,
+            allow_client_ids,
+            limits: Limits::from_env(),
+        })
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            cursor_secret: random_secret(),
+            allow_client_ids: false,
+            limits: Limits::default(),
+        }
+    }
+}
+
+/// The Lambda runtime sets `AWS_LAMBDA_FUNCTION_NAME` in every function's
+/// environment; the local server never has it.
+fn on_lambda() -> bool {
+    std::env::var_os("AWS_LAMBDA_FUNCTION_NAME").is_some()
+}
+
+fn random_secret() -> Vec<u8> {
+    let mut secret = uuid::Uuid::new_v4().as_bytes().to_vec();
+    secret.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+    secret
+}
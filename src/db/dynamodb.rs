@@ -0,0 +1,2064 @@
+use crate::api_key::ApiKey;
+use crate::db::{Page, WishlistStore};
+use crate::error::AppError;
+use crate::exchange::{self, Exchange, Participant};
+use crate::pledge::{self, Pledge};
+use crate::reservation::{self, Reservation};
+use crate::share::ShareLink;
+use async_trait::async_trait;
+use aws_config::retry::RetryConfig;
+use aws_config::timeout::TimeoutConfig;
+use aws_sdk_dynamodb::error::SdkError;
+use aws_sdk_dynamodb::operation::delete_item::DeleteItemError;
+use aws_sdk_dynamodb::operation::transact_write_items::TransactWriteItemsError;
+use aws_sdk_dynamodb::operation::update_item::builders::UpdateItemFluentBuilder;
+use aws_sdk_dynamodb::operation::update_item::UpdateItemError;
+use aws_sdk_dynamodb::types::{
+    AttributeDefinition, AttributeValue, BillingMode, ConditionCheck, GlobalSecondaryIndex,
+    KeySchemaElement, KeyType, KeysAndAttributes, Projection, ProjectionType, Put, ReturnValue,
+    ReturnValuesOnConditionCheckFailure, ScalarAttributeType, TransactWriteItem,
+};
+use aws_sdk_dynamodb::Client as DynamoDbClient;
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub const TABLE_NAME: &str = "wishlist_table";
+/// Share links, keyed on `id`, the SHA-256 of the link's token.
+pub const SHARE_TABLE_NAME: &str = "share_table";
+/// Global secondary index of the share table keyed on `wishlist_id` (hash)
+/// and `id` (range).
+pub const WISHLIST_INDEX: &str = "wishlist-index";
+/// Reservations, one record per item keyed on `wishlist_id` and `item_id`,
+/// holding every reserver's reservation in a `reservations` map and a
+/// `revision` bumped on each write.
+pub const RESERVATION_TABLE_NAME: &str = "reservation_table";
+/// Pledges, keyed on `wishlist_id` and `id`. Each item funded so far has a
+/// `funding#<item_id>` record with its `pledged_cents` total, and each pledge
+/// is a `pledge#<item_id>#<pledge_id>` record.
+pub const PLEDGE_TABLE_NAME: &str = "pledge_table";
+/// Gift exchanges, keyed on `id`, each holding its participants and, once
+/// drawn, their receivers.
+pub const EXCHANGE_TABLE_NAME: &str = "exchange_table";
+/// Table of API keys, keyed on `id` like the wishlist table.
+pub const API_KEY_TABLE_NAME: &str = "api_key_table";
+/// Collaborators of each wishlist, keyed on `wishlist_id` (hash) and
+/// `subject` (range).
+pub const COLLABORATOR_TABLE_NAME: &str = "collaborator_table";
+/// Global secondary index keyed on `owner` (hash) and `id` (range), on the
+/// wishlist and API key tables.
+pub const OWNER_INDEX: &str = "owner-index";
+/// Global secondary index of the collaborator table keyed on `subject` (hash)
+/// and `wishlist_id` (range), for finding the lists shared with someone.
+pub const SUBJECT_INDEX: &str = "subject-index";
+/// Attempts at an item edit before giving up on a list that keeps changing
+/// underneath it.
+const ITEM_EDIT_ATTEMPTS: usize = 5;
+/// Rounds of `BatchGetItem` before giving up on keys DynamoDB keeps leaving
+/// unprocessed.
+const BATCH_GET_ATTEMPTS: usize = 5;
+/// Most keys one `BatchGetItem` call may ask for.
+const BATCH_GET_LIMIT: usize = 100;
+
+/// Retry and timeout policy for DynamoDB calls. The SDK retries throttling,
+/// timeouts and transient service errors with jittered exponential backoff;
+/// whatever still fails reaches the client as a 503.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetrySettings {
+    /// Attempts per call, the first included.
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Time allowed for a single attempt before it counts as a timeout.
+    pub attempt_timeout: Duration,
+}
+
+impl RetrySettings {
+    /// Reads `DYNAMODB_MAX_ATTEMPTS`, `DYNAMODB_INITIAL_BACKOFF_MS`,
+    /// `DYNAMODB_MAX_BACKOFF_MS` and `DYNAMODB_ATTEMPT_TIMEOUT_MS`, keeping
+    /// the default for any that is unset or invalid.
+    pub fn from_env() -> Self {
+        let defaults = RetrySettings::default();
+        let millis = |name: &str, default: Duration| {
+            Duration::from_millis(env_number(name, default.as_millis() as u64))
+        };
+        RetrySettings {
+            max_attempts: env_number("DYNAMODB_MAX_ATTEMPTS", defaults.max_attempts as u64) as u32,
+            initial_backoff: millis("DYNAMODB_INITIAL_BACKOFF_MS", defaults.initial_backoff),
+            max_backoff: millis("DYNAMODB_MAX_BACKOFF_MS", defaults.max_backoff),
+            attempt_timeout: millis("DYNAMODB_ATTEMPT_TIMEOUT_MS", defaults.attempt_timeout),
+        }
+    }
+
+    pub fn retry_config(&self) -> RetryConfig {
+        RetryConfig::standard()
+            .with_max_attempts(self.max_attempts)
+            .with_initial_backoff(self.initial_backoff)
+            .with_max_backoff(self.max_backoff)
+    }
+
+    pub fn timeout_config(&self) -> TimeoutConfig {
+        TimeoutConfig::builder()
+            .operation_attempt_timeout(self.attempt_timeout)
+            .build()
+    }
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        RetrySettings {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(2),
+            attempt_timeout: Duration::from_secs(3),
+        }
+    }
+}
+
+fn env_number(name: &str, default: u64) -> u64 {
+    match std::env::var(name) {
+        Ok(value) => match value.parse() {
+            Ok(number) if number > 0 => number,
+            _ => {
+                warn!("Ignoring invalid {}={:?}, using {}", name, value, default);
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+pub async fn get_db_client() -> DynamoDbClient {
+    let endpoint = std::env::var("DYNAMODB_ENDPOINT");
+    let retry = RetrySettings::from_env();
+    let config_builder = aws_config::from_env()
+        .retry_config(retry.retry_config())
+        .timeout_config(retry.timeout_config());
+    let config = if let Ok(endpoint_url) = endpoint {
+        config_builder
+            .endpoint_url(endpoint_url)
+            .behavior_version(aws_config::BehaviorVersion::latest())
+            .credentials_provider(aws_credential_types::Credentials::for_tests())
+            .region(aws_sdk_dynamodb::config::Region::new("us-east-1"))
+            .load()
+            .await
+    } else {
+        config_builder.load().await
+    };
+    DynamoDbClient::new(&config)
+}
+use crate::handlers::wishlist::{Collaborator, Wishlist, WishlistItem};
+use log::{error, info, warn};
+
+/// Creates the wishlist, collaborator, share, reservation, pledge, exchange
+/// and API key tables, each with its index, if they don't exist yet.
+/// Deployed environments get the tables from the CDK stack; this is for
+/// DynamoDB Local.
+pub async fn create_table(client: &DynamoDbClient) -> Result<(), AppError> {
+    create_keyed_table(
+        client,
+        TABLE_NAME,
+        ("id", None),
+        Some((OWNER_INDEX, ("owner", "id"))),
+    )
+    .await?;
+    create_keyed_table(
+        client,
+        COLLABORATOR_TABLE_NAME,
+        ("wishlist_id", Some("subject")),
+        Some((SUBJECT_INDEX, ("subject", "wishlist_id"))),
+    )
+    .await?;
+    create_keyed_table(
+        client,
+        SHARE_TABLE_NAME,
+        ("id", None),
+        Some((WISHLIST_INDEX, ("wishlist_id", "id"))),
+    )
+    .await?;
+    create_keyed_table(
+        client,
+        RESERVATION_TABLE_NAME,
+        ("wishlist_id", Some("item_id")),
+        None,
+    )
+    .await?;
+    create_keyed_table(client, PLEDGE_TABLE_NAME, ("wishlist_id", Some("id")), None).await?;
+    create_keyed_table(client, EXCHANGE_TABLE_NAME, ("id", None), None).await?;
+    create_keyed_table(
+        client,
+        API_KEY_TABLE_NAME,
+        ("id", None),
+        Some((OWNER_INDEX, ("owner", "id"))),
+    )
+    .await
+}
+
+/// Creates a table with string keys and, if given, one global secondary
+/// index projecting every attribute.
+async fn create_keyed_table(
+    client: &DynamoDbClient,
+    name: &str,
+    (hash, range): (&str, Option<&str>),
+    index: Option<(&str, (&str, &str))>,
+) -> Result<(), AppError> {
+    let key_schema = |name: &str, key_type: KeyType| {
+        KeySchemaElement::builder()
+            .attribute_name(name)
+            .key_type(key_type)
+            .build()
+    };
+    let attribute = |name: &str| {
+        AttributeDefinition::builder()
+            .attribute_name(name)
+            .attribute_type(ScalarAttributeType::S)
+            .build()
+    };
+    let mut request = client
+        .create_table()
+        .table_name(name)
+        .key_schema(key_schema(hash, KeyType::Hash)?)
+        .billing_mode(BillingMode::PayPerRequest);
+    if let Some(range) = range {
+        request = request.key_schema(key_schema(range, KeyType::Range)?);
+    }
+    let mut attributes = vec![hash];
+    attributes.extend(range);
+    if let Some((index_name, (index_hash, index_range))) = index {
+        let index = GlobalSecondaryIndex::builder()
+            .index_name(index_name)
+            .key_schema(key_schema(index_hash, KeyType::Hash)?)
+            .key_schema(key_schema(index_range, KeyType::Range)?)
+            .projection(
+                Projection::builder()
+                    .projection_type(ProjectionType::All)
+                    .build(),
+            )
+            .build()?;
+        request = request.global_secondary_indexes(index);
+        attributes.extend([index_hash, index_range]);
+    }
+    attributes.sort_unstable();
+    attributes.dedup();
+    for name in attributes {
+        request = request.attribute_definitions(attribute(name)?);
+    }
+    match request.send().await {
+        Ok(_) => {
+            info!("Created table '{}'", name);
+            Ok(())
+        }
+        Err(SdkError::ServiceError(e)) if e.err().is_resource_in_use_exception() => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn page_from(
+    items: Option<Vec<HashMap<String, AttributeValue>>>,
+    last_evaluated_key: Option<HashMap<String, AttributeValue>>,
+) -> Page<Wishlist> {
+    let last_key = last_evaluated_key
+        .as_ref()
+        .and_then(|key| key.get("id"))
+        .and_then(|id| id.as_s().ok())
+        .cloned();
+    let wishlists = items
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|item| Wishlist::try_from(item).ok())
+        .collect();
+    Page {
+        items: wishlists,
+        last_key,
+    }
+}
+
+/// Condition matching an existing wishlist, at `expected` version if given.
+/// Wishlists written before versioning have no `version` attribute and count
+/// as 0.
+fn version_condition(expected: Option<u64>) -> &'static str {
+    match expected {
+        None => "attribute_exists(id)",
+        Some(0) => {
+            "attribute_exists(id) AND (attribute_not_exists(#version) OR #version = :expected)"
+        }
+        Some(_) => "#version = :expected",
+    }
+}
+
+/// A failed `version_condition` means either the wishlist is gone or it is at
+/// another version; the item returned with the failure tells them apart.
+fn condition_failure(item_exists: bool) -> AppError {
+    if item_exists {
+        AppError::PreconditionFailed
+    } else {
+        AppError::NotFound
+    }
+}
+
+fn items_attribute(items: &[WishlistItem]) -> AttributeValue {
+    AttributeValue::L(items.iter().map(item_attribute).collect())
+}
+
+fn item_attribute(item: &WishlistItem) -> AttributeValue {
+    let mut fields = HashMap::from([
+        ("id".to_string(), AttributeValue::S(item.id.clone())),
+        ("name".to_string(), AttributeValue::S(item.name.clone())),
+        (
+            "quantity".to_string(),
+            AttributeValue::N(item.quantity.to_string()),
+        ),
+    ]);
+    let optional = [
+        ("url", item.url.clone().map(AttributeValue::S)),
+        (
+            "price_cents",
+            item.price_cents.map(|p| AttributeValue::N(p.to_string())),
+        ),
+        ("currency", item.currency.clone().map(AttributeValue::S)),
+        (
+            "priority",
+            item.priority
+                .map(|p| AttributeValue::S(p.as_str().to_string())),
+        ),
+        ("notes", item.notes.clone().map(AttributeValue::S)),
+    ];
+    for (key, value) in optional {
+        if let Some(value) = value {
+            fields.insert(key.to_string(), value);
+        }
+    }
+    AttributeValue::M(fields)
+}
+
+/// Sends an update and returns the new wishlist. A failed condition becomes
+/// `NotFound` or `PreconditionFailed`, as for `condition_failure`.
+async fn send_update(request: UpdateItemFluentBuilder) -> Result<Wishlist, AppError> {
+    let result = request
+        .return_values(ReturnValue::AllNew)
+        .return_values_on_condition_check_failure(ReturnValuesOnConditionCheckFailure::AllOld)
+        .send()
+        .await;
+    match result {
+        Ok(output) => {
+            let attributes = output.attributes.unwrap_or_default();
+            Wishlist::try_from(attributes).map_err(AppError::from)
+        }
+        Err(SdkError::ServiceError(e)) if e.err().is_conditional_check_failed_exception() => {
+            let stored = match e.into_err() {
+                UpdateItemError::ConditionalCheckFailedException(failure) => failure.item,
+                _ => None,
+            };
+            Err(condition_failure(stored.is_some()))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn collaborator_from_item(
+    item: &HashMap<String, AttributeValue>,
+) -> Result<Collaborator, AppError> {
+    let text = |name: &str| {
+        item.get(name)
+            .and_then(|v| v.as_s().ok())
+            .cloned()
+            .ok_or_else(|| AppError::Generic(format!("Collaborator is missing '{}'", name)))
+    };
+    Ok(Collaborator {
+        subject: text("subject")?,
+        role: text("role")?.parse()?,
+    })
+}
+
+fn share_attributes(link: &ShareLink) -> HashMap<String, AttributeValue> {
+    let mut attributes = HashMap::from([
+        ("id".to_string(), AttributeValue::S(link.id.clone())),
+        (
+            "wishlist_id".to_string(),
+            AttributeValue::S(link.wishlist_id.clone()),
+        ),
+        (
+            "created_at".to_string(),
+            AttributeValue::N(link.created_at.to_string()),
+        ),
+    ]);
+    let optional = [
+        ("created_by", link.created_by.clone().map(AttributeValue::S)),
+        (
+            "expires_at",
+            link.expires_at.map(|t| AttributeValue::N(t.to_string())),
+        ),
+        (
+            "password_hash",
+            link.password_hash.clone().map(AttributeValue::S),
+        ),
+        (
+            "revoked_at",
+            link.revoked_at.map(|t| AttributeValue::N(t.to_string())),
+        ),
+    ];
+    for (name, value) in optional {
+        if let Some(value) = value {
+            attributes.insert(name.to_string(), value);
+        }
+    }
+    attributes
+}
+
+impl TryFrom<HashMap<String, AttributeValue>> for ShareLink {
+    type Error = AppError;
+
+    fn try_from(item: HashMap<String, AttributeValue>) -> Result<Self, Self::Error> {
+        let text = |name: &str| item.get(name).and_then(|v| v.as_s().ok()).cloned();
+        let number = |name: &str| {
+            item.get(name)
+                .and_then(|v| v.as_n().ok())
+                .and_then(|n| n.parse::<u64>().ok())
+        };
+        let required = |name: &str| {
+            text(name).ok_or_else(|| AppError::Generic(format!("Share link is missing '{}'", name)))
+        };
+        Ok(ShareLink {
+            id: required("id")?,
+            wishlist_id: required("wishlist_id")?,
+            created_by: text("created_by"),
+            created_at: number("created_at").unwrap_or_default(),
+            expires_at: number("expires_at"),
+            password_hash: text("password_hash"),
+            revoked_at: number("revoked_at"),
+        })
+    }
+}
+
+fn reservation_attribute(reservation: &Reservation) -> AttributeValue {
+    let mut fields = HashMap::from([
+        (
+            "quantity".to_string(),
+            AttributeValue::N(reservation.quantity.to_string()),
+        ),
+        (
+            "purchased".to_string(),
+            AttributeValue::Bool(reservation.purchased),
+        ),
+        (
+            "reserved_at".to_string(),
+            AttributeValue::N(reservation.reserved_at.to_string()),
+        ),
+    ]);
+    if let Some(claim_hash) = &reservation.claim_hash {
+        fields.insert(
+            "claim_hash".to_string(),
+            AttributeValue::S(claim_hash.clone()),
+        );
+    }
+    AttributeValue::M(fields)
+}
+
+/// Reads the reservations out of one item's reservation record.
+fn reservations_from_item(
+    item: &HashMap<String, AttributeValue>,
+) -> Result<Vec<Reservation>, AppError> {
+    let item_id = item
+        .get("item_id")
+        .and_then(|v| v.as_s().ok())
+        .cloned()
+        .ok_or_else(|| AppError::Generic("Reservation is missing 'item_id'".to_string()))?;
+    let Some(Ok(entries)) = item.get("reservations").map(|v| v.as_m()) else {
+        return Ok(Vec::new());
+    };
+    let mut reservations = Vec::with_capacity(entries.len());
+    for (reserver, entry) in entries {
+        let fields = entry.as_m().map_err(|_| {
+            AppError::Generic(format!("Reservation of '{}' is not a map", reserver))
+        })?;
+        let number = |name: &str| {
+            fields
+                .get(name)
+                .and_then(|v| v.as_n().ok())
+                .and_then(|n| n.parse::<u64>().ok())
+                .unwrap_or_default()
+        };
+        reservations.push(Reservation {
+            item_id: item_id.clone(),
+            reserver: reserver.clone(),
+            quantity: number("quantity") as u32,
+            purchased: fields
+                .get("purchased")
+                .and_then(|v| v.as_bool().ok())
+                .copied()
+                .unwrap_or_default(),
+            reserved_at: number("reserved_at"),
+            claim_hash: fields
+                .get("claim_hash")
+                .and_then(|v| v.as_s().ok())
+                .cloned(),
+        });
+    }
+    reservations.sort_by(|a, b| a.reserver.cmp(&b.reserver));
+    Ok(reservations)
+}
+
+/// Sort key of the record totalling an item's pledges.
+fn funding_key(item_id: &str) -> String {
+    format!("funding#{}", item_id)
+}
+
+/// Prefix of the sort keys of an item's pledges.
+fn pledge_prefix(item_id: &str) -> String {
+    format!("pledge#{}#", item_id)
+}
+
+fn pledge_attributes(wishlist_id: &str, pledge: &Pledge) -> HashMap<String, AttributeValue> {
+    let mut attributes = HashMap::from([
+        (
+            "wishlist_id".to_string(),
+            AttributeValue::S(wishlist_id.to_string()),
+        ),
+        (
+            "id".to_string(),
+            AttributeValue::S(format!("{}{}", pledge_prefix(&pledge.item_id), pledge.id)),
+        ),
+        (
+            "pledge_id".to_string(),
+            AttributeValue::S(pledge.id.clone()),
+        ),
+        (
+            "item_id".to_string(),
+            AttributeValue::S(pledge.item_id.clone()),
+        ),
+        (
+            "contributor".to_string(),
+            AttributeValue::S(pledge.contributor.clone()),
+        ),
+        (
+            "amount_cents".to_string(),
+            AttributeValue::N(pledge.amount_cents.to_string()),
+        ),
+        (
+            "currency".to_string(),
+            AttributeValue::S(pledge.currency.clone()),
+        ),
+        (
+            "pledged_at".to_string(),
+            AttributeValue::N(pledge.pledged_at.to_string()),
+        ),
+    ]);
+    if let Some(message) = &pledge.message {
+        attributes.insert("message".to_string(), AttributeValue::S(message.clone()));
+    }
+    attributes
+}
+
+impl TryFrom<HashMap<String, AttributeValue>> for Pledge {
+    type Error = AppError;
+
+    fn try_from(item: HashMap<String, AttributeValue>) -> Result<Self, Self::Error> {
+        let text = |name: &str| item.get(name).and_then(|v| v.as_s().ok()).cloned();
+        let number = |name: &str| {
+            item.get(name)
+                .and_then(|v| v.as_n().ok())
+                .and_then(|n| n.parse::<u64>().ok())
+                .unwrap_or_default()
+        };
+        let required = |name: &str| {
+            text(name).ok_or_else(|| AppError::Generic(format!("Pledge is missing '{}'", name)))
+        };
+        Ok(Pledge {
+            id: required("pledge_id")?,
+            item_id: required("item_id")?,
+            contributor: required("contributor")?,
+            amount_cents: number("amount_cents"),
+            currency: required("currency")?,
+            message: text("message"),
+            pledged_at: number("pledged_at"),
+        })
+    }
+}
+
+fn participants_attribute(participants: &[Participant]) -> AttributeValue {
+    AttributeValue::L(
+        participants
+            .iter()
+            .map(|participant| {
+                let mut fields = HashMap::from([
+                    (
+                        "subject".to_string(),
+                        AttributeValue::S(participant.subject.clone()),
+                    ),
+                    (
+                        "wishlist_id".to_string(),
+                        AttributeValue::S(participant.wishlist_id.clone()),
+                    ),
+                ]);
+                let optional = [
+                    ("spouse", participant.spouse.clone()),
+                    ("receiver", participant.receiver.clone()),
+                ];
+                for (name, value) in optional {
+                    if let Some(value) = value {
+                        fields.insert(name.to_string(), AttributeValue::S(value));
+                    }
+                }
+                AttributeValue::M(fields)
+            })
+            .collect(),
+    )
+}
+
+fn exchange_attributes(exchange: &Exchange) -> HashMap<String, AttributeValue> {
+    let mut attributes = HashMap::from([
+        ("id".to_string(), AttributeValue::S(exchange.id.clone())),
+        ("name".to_string(), AttributeValue::S(exchange.name.clone())),
+        (
+            "organizer".to_string(),
+            AttributeValue::S(exchange.organizer.clone()),
+        ),
+        (
+            "participants".to_string(),
+            participants_attribute(&exchange.participants),
+        ),
+        (
+            "created_at".to_string(),
+            AttributeValue::N(exchange.created_at.to_string()),
+        ),
+    ]);
+    let optional = [
+        (
+            "previous_exchange_id",
+            exchange.previous_exchange_id.clone().map(AttributeValue::S),
+        ),
+        (
+            "drawn_at",
+            exchange.drawn_at.map(|t| AttributeValue::N(t.to_string())),
+        ),
+    ];
+    for (name, value) in optional {
+        if let Some(value) = value {
+            attributes.insert(name.to_string(), value);
+        }
+    }
+    attributes
+}
+
+impl TryFrom<HashMap<String, AttributeValue>> for Exchange {
+    type Error = AppError;
+
+    fn try_from(item: HashMap<String, AttributeValue>) -> Result<Self, Self::Error> {
+        let text = |name: &str| item.get(name).and_then(|v| v.as_s().ok()).cloned();
+        let number = |name: &str| {
+            item.get(name)
+                .and_then(|v| v.as_n().ok())
+                .and_then(|n| n.parse::<u64>().ok())
+        };
+        let required = |name: &str| {
+            text(name).ok_or_else(|| AppError::Generic(format!("Exchange is missing '{}'", name)))
+        };
+        let mut participants = Vec::new();
+        if let Some(Ok(entries)) = item.get("participants").map(|v| v.as_l()) {
+            for entry in entries {
+                let fields = entry.as_m().map_err(|_| {
+                    AppError::Generic("Exchange participant is not a map".to_string())
+                })?;
+                let text = |name: &str| fields.get(name).and_then(|v| v.as_s().ok()).cloned();
+                participants.push(Participant {
+                    subject: text("subject").unwrap_or_default(),
+                    wishlist_id: text("wishlist_id").unwrap_or_default(),
+                    spouse: text("spouse"),
+                    receiver: text("receiver"),
+                });
+            }
+        }
+        Ok(Exchange {
+            id: required("id")?,
+            name: required("name")?,
+            organizer: required("organizer")?,
+            participants,
+            previous_exchange_id: text("previous_exchange_id"),
+            created_at: number("created_at").unwrap_or_default(),
+            drawn_at: number("drawn_at"),
+        })
+    }
+}
+
+fn api_key_attributes(key: &ApiKey) -> HashMap<String, AttributeValue> {
+    let mut attributes = HashMap::from([
+        ("id".to_string(), AttributeValue::S(key.id.clone())),
+        ("owner".to_string(), AttributeValue::S(key.owner.clone())),
+        ("name".to_string(), AttributeValue::S(key.name.clone())),
+        (
+            "scope".to_string(),
+            AttributeValue::S(key.scope.as_str().to_string()),
+        ),
+        (
+            "secret_hash".to_string(),
+            AttributeValue::S(key.secret_hash.clone()),
+        ),
+        (
+            "created_at".to_string(),
+            AttributeValue::N(key.created_at.to_string()),
+        ),
+    ]);
+    let optional = [
+        ("last_used_at", key.last_used_at),
+        ("revoked_at", key.revoked_at),
+    ];
+    for (name, value) in optional {
+        if let Some(value) = value {
+            attributes.insert(name.to_string(), AttributeValue::N(value.to_string()));
+        }
+    }
+    attributes
+}
+
+impl TryFrom<HashMap<String, AttributeValue>> for ApiKey {
+    type Error = AppError;
+
+    fn try_from(item: HashMap<String, AttributeValue>) -> Result<Self, Self::Error> {
+        let text = |name: &str| {
+            item.get(name)
+                .and_then(|v| v.as_s().ok())
+                .cloned()
+                .ok_or_else(|| AppError::Generic(format!("API key is missing '{}'", name)))
+        };
+        let number = |name: &str| {
+            item.get(name)
+                .and_then(|v| v.as_n().ok())
+                .and_then(|n| n.parse::<u64>().ok())
+        };
+        Ok(ApiKey {
+            id: text("id")?,
+            owner: text("owner")?,
+            name: text("name")?,
+            scope: text("scope")?.parse()?,
+            secret_hash: text("secret_hash")?,
+            created_at: number("created_at").unwrap_or_default(),
+            last_used_at: number("last_used_at"),
+            revoked_at: number("revoked_at"),
+        })
+    }
+}
+
+/// The stored state an item edit is conditioned on.
+struct StoredItem {
+    wishlist: Wishlist,
+    position: usize,
+    /// The item exactly as stored, which may still be a legacy plain string.
+    raw: AttributeValue,
+    /// Whether later items are legacy strings, whose position-derived IDs
+    /// would shift if this item were removed from the list.
+    legacy_after: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct DynamoDbStore {
+    client: DynamoDbClient,
+}
+
+impl DynamoDbStore {
+    pub fn new(client: DynamoDbClient) -> Self {
+        DynamoDbStore { client }
+    }
+
+    pub fn client(&self) -> &DynamoDbClient {
+        &self.client
+    }
+
+    async fn stored_item(
+        &self,
+        wishlist_id: &str,
+        item_id: &str,
+        expected_version: Option<u64>,
+    ) -> Result<StoredItem, AppError> {
+        let output = self
+            .client
+            .get_item()
+            .table_name(TABLE_NAME)
+            .key("id", AttributeValue::S(wishlist_id.to_string()))
+            .consistent_read(true)
+            .send()
+            .await?;
+        let attributes = output.item.ok_or(AppError::NotFound)?;
+        let raw_items = attributes
+            .get("items")
+            .and_then(|v| v.as_l().ok())
+            .cloned()
+            .unwrap_or_default();
+        let wishlist = Wishlist::try_from(attributes)?;
+        if expected_version.is_some_and(|expected| expected != wishlist.version) {
+            return Err(AppError::PreconditionFailed);
+        }
+        let position = wishlist
+            .items
+            .iter()
+            .position(|item| item.id == item_id)
+            .ok_or(AppError::NotFound)?;
+        Ok(StoredItem {
+            legacy_after: raw_items[position + 1..].iter().any(|v| v.is_s()),
+            raw: raw_items[position].clone(),
+            position,
+            wishlist,
+        })
+    }
+
+    /// Fetches wishlists by ID, in the order given, skipping any that no
+    /// longer exist. IDs are asked for `BATCH_GET_LIMIT` at a time.
+    async fn batch_get_wishlists(&self, ids: &[String]) -> Result<Vec<Wishlist>, AppError> {
+        let mut found = HashMap::new();
+        for chunk in ids.chunks(BATCH_GET_LIMIT) {
+            self.batch_get_chunk(chunk, &mut found).await?;
+        }
+        Ok(ids.iter().filter_map(|id| found.remove(id)).collect())
+    }
+
+    /// Reads up to `BATCH_GET_LIMIT` wishlists into `found`, asking again for
+    /// any DynamoDB leaves unprocessed.
+    async fn batch_get_chunk(
+        &self,
+        ids: &[String],
+        found: &mut HashMap<String, Wishlist>,
+    ) -> Result<(), AppError> {
+        let mut keys: Vec<HashMap<String, AttributeValue>> = ids
+            .iter()
+            .map(|id| HashMap::from([("id".to_string(), AttributeValue::S(id.clone()))]))
+            .collect();
+        for _ in 0..BATCH_GET_ATTEMPTS {
+            if keys.is_empty() {
+                break;
+            }
+            let output = self
+                .client
+                .batch_get_item()
+                .request_items(
+                    TABLE_NAME,
+                    KeysAndAttributes::builder().set_keys(Some(keys)).build()?,
+                )
+                .send()
+                .await?;
+            for item in output
+                .responses
+                .and_then(|mut tables| tables.remove(TABLE_NAME))
+                .unwrap_or_default()
+            {
+                let wishlist = Wishlist::try_from(item)?;
+                found.insert(wishlist.id.clone(), wishlist);
+            }
+            // Throttled reads come back unprocessed and are asked for again.
+            keys = output
+                .unprocessed_keys
+                .and_then(|mut tables| tables.remove(TABLE_NAME))
+                .map(|unprocessed| unprocessed.keys)
+                .unwrap_or_default();
+        }
+        if !keys.is_empty() {
+            return Err(AppError::Throttled(
+                "Wishlists could not all be read".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Writes `put` only while `wishlist_id` exists, in one transaction. A
+    /// missing wishlist is `NotFound`; a failed condition on `put` itself is a
+    /// `Conflict` naming `what`.
+    async fn put_if_wishlist_exists(
+        &self,
+        wishlist_id: &str,
+        put: Put,
+        what: &str,
+    ) -> Result<(), AppError> {
+        let exists = ConditionCheck::builder()
+            .table_name(TABLE_NAME)
+            .key("id", AttributeValue::S(wishlist_id.to_string()))
+            .condition_expression("attribute_exists(id)")
+            .build()?;
+        let result = self
+            .client
+            .transact_write_items()
+            .transact_items(TransactWriteItem::builder().condition_check(exists).build())
+            .transact_items(TransactWriteItem::builder().put(put).build())
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(SdkError::ServiceError(e)) if e.err().is_transaction_canceled_exception() => {
+                let reasons = match e.into_err() {
+                    TransactWriteItemsError::TransactionCanceledException(canceled) => {
+                        canceled.cancellation_reasons.unwrap_or_default()
+                    }
+                    _ => Vec::new(),
+                };
+                // Reasons line up with the actions: the check, then the put.
+                let failed = |index: usize| {
+                    reasons.get(index).and_then(|reason| reason.code())
+                        == Some("ConditionalCheckFailed")
+                };
+                if failed(0) {
+                    Err(AppError::NotFound)
+                } else if failed(1) {
+                    Err(AppError::Conflict(format!("{} already exists", what)))
+                } else {
+                    Err(AppError::Conflict(
+                        "Wishlist is being modified concurrently; try again".to_string(),
+                    ))
+                }
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// An item's reservations and the revision of their record, or `None`
+    /// when nobody has reserved it yet.
+    async fn reservation_record(
+        &self,
+        wishlist_id: &str,
+        item_id: &str,
+    ) -> Result<(Vec<Reservation>, Option<u64>), AppError> {
+        let output = self
+            .client
+            .get_item()
+            .table_name(RESERVATION_TABLE_NAME)
+            .key("wishlist_id", AttributeValue::S(wishlist_id.to_string()))
+            .key("item_id", AttributeValue::S(item_id.to_string()))
+            .consistent_read(true)
+            .send()
+            .await?;
+        let Some(item) = output.item else {
+            return Ok((Vec::new(), None));
+        };
+        let revision = item
+            .get("revision")
+            .and_then(|v| v.as_n().ok())
+            .and_then(|n| n.parse().ok())
+            .unwrap_or_default();
+        Ok((reservations_from_item(&item)?, Some(revision)))
+    }
+
+    /// Removes the collaborators, share links, reservations and pledges kept
+    /// beside wishlist `id`, outside any transaction. Failures are only
+    /// logged, and each step can simply be run again: it runs when a wishlist
+    /// is deleted and again when one is created, so leftovers of a failed
+    /// cleanup never reach a later wishlist with the same ID.
+    async fn delete_dependents(&self, id: &str) {
+        self.delete_collaborators(id).await;
+        self.delete_shares(id).await;
+        self.delete_reservations(id).await;
+        self.delete_pledges(id).await;
+    }
+
+    /// Removes a deleted wishlist's reservations. Failures are only logged;
+    /// leftovers can't be reached once the wishlist is gone.
+    async fn delete_reservations(&self, wishlist_id: &str) {
+        let reservations = match self.list_reservations(wishlist_id.to_string()).await {
+            Ok(reservations) => reservations,
+            Err(e) => {
+                warn!("Could not list reservations of {}: {}", wishlist_id, e);
+                return;
+            }
+        };
+        let mut item_ids: Vec<String> = reservations.into_iter().map(|r| r.item_id).collect();
+        item_ids.dedup();
+        for item_id in item_ids {
+            let result = self
+                .client
+                .delete_item()
+                .table_name(RESERVATION_TABLE_NAME)
+                .key("wishlist_id", AttributeValue::S(wishlist_id.to_string()))
+                .key("item_id", AttributeValue::S(item_id.clone()))
+                .send()
+                .await;
+            if let Err(e) = result {
+                warn!(
+                    "Could not remove reservations of {} on {}: {}",
+                    item_id,
+                    wishlist_id,
+                    AppError::from(e)
+                );
+            }
+        }
+    }
+
+    /// Removes a deleted wishlist's pledges and funding totals. Failures are
+    /// only logged; leftovers can't be reached once the wishlist is gone.
+    async fn delete_pledges(&self, wishlist_id: &str) {
+        let mut start_key = None;
+        loop {
+            let output = match self
+                .client
+                .query()
+                .table_name(PLEDGE_TABLE_NAME)
+                .key_condition_expression("wishlist_id = :wishlist_id")
+                .expression_attribute_values(
+                    ":wishlist_id",
+                    AttributeValue::S(wishlist_id.to_string()),
+                )
+                .projection_expression("id")
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+            {
+                Ok(output) => output,
+                Err(e) => {
+                    warn!(
+                        "Could not list pledges of {}: {}",
+                        wishlist_id,
+                        AppError::from(e)
+                    );
+                    return;
+                }
+            };
+            for item in output.items.unwrap_or_default() {
+                let Some(id) = item.get("id").cloned() else {
+                    continue;
+                };
+                let result = self
+                    .client
+                    .delete_item()
+                    .table_name(PLEDGE_TABLE_NAME)
+                    .key("wishlist_id", AttributeValue::S(wishlist_id.to_string()))
+                    .key("id", id)
+                    .send()
+                    .await;
+                if let Err(e) = result {
+                    warn!(
+                        "Could not remove a pledge of {}: {}",
+                        wishlist_id,
+                        AppError::from(e)
+                    );
+                }
+            }
+            start_key = output.last_evaluated_key;
+            if start_key.is_none() {
+                return;
+            }
+        }
+    }
+
+    /// Removes a deleted wishlist's share links. Failures are only logged;
+    /// `GET /shared/{token}` finds no wishlist behind a leftover link.
+    async fn delete_shares(&self, wishlist_id: &str) {
+        let links = match self.list_shares(wishlist_id.to_string()).await {
+            Ok(links) => links,
+            Err(e) => {
+                warn!("Could not list share links of {}: {}", wishlist_id, e);
+                return;
+            }
+        };
+        for link in links {
+            let result = self
+                .client
+                .delete_item()
+                .table_name(SHARE_TABLE_NAME)
+                .key("id", AttributeValue::S(link.id.clone()))
+                .send()
+                .await;
+            if let Err(e) = result {
+                warn!(
+                    "Could not remove share link {} of {}: {}",
+                    link.id,
+                    wishlist_id,
+                    AppError::from(e)
+                );
+            }
+        }
+    }
+
+    /// Removes a deleted wishlist's collaborators. Failures are only logged:
+    /// leftovers are skipped by `query_shared_with` and can't grant anything
+    /// once the wishlist is gone.
+    async fn delete_collaborators(&self, wishlist_id: &str) {
+        let collaborators = match self.list_collaborators(wishlist_id.to_string()).await {
+            Ok(collaborators) => collaborators,
+            Err(e) => {
+                warn!("Could not list collaborators of {}: {}", wishlist_id, e);
+                return;
+            }
+        };
+        for collaborator in collaborators {
+            if let Err(e) = self
+                .delete_collaborator(wishlist_id.to_string(), collaborator.subject.clone())
+                .await
+            {
+                warn!(
+                    "Could not remove collaborator {} of {}: {}",
+                    collaborator.subject, wishlist_id, e
+                );
+            }
+        }
+    }
+
+    /// Starts an update of `wishlist_id` that also bumps its version. `:zero`,
+    /// `:one` and, when `expected_version` is given, `:expected` are bound.
+    fn item_edit(
+        &self,
+        wishlist_id: &str,
+        update_expression: String,
+        condition_expression: String,
+        expected_version: Option<u64>,
+    ) -> UpdateItemFluentBuilder {
+        let request = self
+            .client
+            .update_item()
+            .table_name(TABLE_NAME)
+            .key("id", AttributeValue::S(wishlist_id.to_string()))
+            .update_expression(format!(
+                "{} #version = if_not_exists(#version, :zero) + :one",
+                update_expression
+            ))
+            .condition_expression(condition_expression)
+            .expression_attribute_names("#items", "items")
+            .expression_attribute_names("#version", "version")
+            .expression_attribute_values(":zero", AttributeValue::N("0".to_string()))
+            .expression_attribute_values(":one", AttributeValue::N("1".to_string()));
+        match expected_version {
+            Some(expected) => request
+                .expression_attribute_values(":expected", AttributeValue::N(expected.to_string())),
+            None => request,
+        }
+    }
+}
+
+#[async_trait]
+impl WishlistStore for DynamoDbStore {
+    async fn get_item(&self, id: String) -> Result<Option<Wishlist>, AppError> {
+        let get_item_output = self
+            .client
+            .get_item()
+            .table_name(TABLE_NAME)
+            .key("id", AttributeValue::S(id))
+            .send()
+            .await?;
+
+        if let Some(item) = get_item_output.item {
+            match Wishlist::try_from(item) {
+                Ok(wishlist) => Ok(Some(wishlist)),
+                Err(e) => {
+                    error!("Error converting item to Wishlist: {:?}", e);
+                    Err(AppError::from(e.to_string()))
+                }
+            }
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn create_item(&self, wishlist: Wishlist) -> Result<(), AppError> {
+        let result = self
+            .client
+            .put_item()
+            .table_name(TABLE_NAME)
+            .item("id", AttributeValue::S(wishlist.id.clone()))
+            .item("name", AttributeValue::S(wishlist.name.clone()))
+            .item("owner", AttributeValue::S(wishlist.owner.clone()))
+            .item("items", items_attribute(&wishlist.items))
+            .item("version", AttributeValue::N(wishlist.version.to_string()))
+            .condition_expression("attribute_not_exists(id)")
+            .send()
+            .await;
+        match result {
+            Ok(_) => {
+                // A wishlist deleted earlier under this ID may have left
+                // records its cleanup missed; the new one mustn't inherit them.
+                self.delete_dependents(&wishlist.id).await;
+                Ok(())
+            }
+            Err(SdkError::ServiceError(e)) if e.err().is_conditional_check_failed_exception() => {
+                Err(AppError::Conflict(format!(
+                    "Wishlist '{}' already exists",
+                    wishlist.id
+                )))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn update_item(
+        &self,
+        wishlist: Wishlist,
+        expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError> {
+        let mut request = self
+            .client
+            .update_item()
+            .table_name(TABLE_NAME)
+            .key("id", AttributeValue::S(wishlist.id.clone()))
+            .update_expression(
+                "SET #name = :name, #owner = :owner, #items = :items, \
+                 #version = if_not_exists(#version, :zero) + :one",
+            )
+            .condition_expression(version_condition(expected_version))
+            .expression_attribute_names("#name", "name")
+            .expression_attribute_names("#owner", "owner")
+            .expression_attribute_names("#items", "items")
+            .expression_attribute_names("#version", "version")
+            .expression_attribute_values(":name", AttributeValue::S(wishlist.name))
+            .expression_attribute_values(":owner", AttributeValue::S(wishlist.owner))
+            .expression_attribute_values(":items", items_attribute(&wishlist.items))
+            .expression_attribute_values(":zero", AttributeValue::N("0".to_string()))
+            .expression_attribute_values(":one", AttributeValue::N("1".to_string()));
+        if let Some(expected) = expected_version {
+            request = request
+                .expression_attribute_values(":expected", AttributeValue::N(expected.to_string()));
+        }
+        send_update(request).await
+    }
+
+    async fn delete_item(&self, id: String, expected_version: Option<u64>) -> Result<(), AppError> {
+        let mut request = self
+            .client
+            .delete_item()
+            .table_name(TABLE_NAME)
+            .key("id", AttributeValue::S(id.clone()))
+            .condition_expression(version_condition(expected_version))
+            .return_values_on_condition_check_failure(ReturnValuesOnConditionCheckFailure::AllOld);
+        if let Some(expected) = expected_version {
+            request = request
+                .expression_attribute_names("#version", "version")
+                .expression_attribute_values(":expected", AttributeValue::N(expected.to_string()));
+        }
+        match request.send().await {
+            Ok(_) => {
+                self.delete_dependents(&id).await;
+                Ok(())
+            }
+            Err(SdkError::ServiceError(e)) if e.err().is_conditional_check_failed_exception() => {
+                let stored = match e.into_err() {
+                    DeleteItemError::ConditionalCheckFailedException(failure) => failure.item,
+                    _ => None,
+                };
+                Err(condition_failure(stored.is_some()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn add_wishlist_item(
+        &self,
+        wishlist_id: String,
+        item: WishlistItem,
+        expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError> {
+        let request = self
+            .item_edit(
+                &wishlist_id,
+                "SET #items = list_append(if_not_exists(#items, :empty), :new),".to_string(),
+                version_condition(expected_version).to_string(),
+                expected_version,
+            )
+            .expression_attribute_values(":empty", AttributeValue::L(Vec::new()))
+            .expression_attribute_values(":new", items_attribute(&[item]));
+        send_update(request).await
+    }
+
+    async fn update_wishlist_item(
+        &self,
+        wishlist_id: String,
+        item_id: String,
+        patch: serde_json::Value,
+        expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError> {
+        // Read the item, patch it, and write it back only if that slot of the
+        // list still holds exactly what was read; otherwise start over.
+        for _ in 0..ITEM_EDIT_ATTEMPTS {
+            let stored = self
+                .stored_item(&wishlist_id, &item_id, expected_version)
+                .await?;
+            let patched = stored.wishlist.items[stored.position].patched(&patch)?;
+            let path = format!("#items[{}]", stored.position);
+            let request = self
+                .item_edit(
+                    &wishlist_id,
+                    format!("SET {} = :item,", path),
+                    format!(
+                        "{} AND {} = :stored",
+                        version_condition(expected_version),
+                        path
+                    ),
+                    expected_version,
+                )
+                .expression_attribute_values(":item", item_attribute(&patched))
+                .expression_attribute_values(":stored", stored.raw);
+            match send_update(request).await {
+                Err(AppError::PreconditionFailed) => continue,
+                result => return result,
+            }
+        }
+        Err(AppError::Conflict(
+            "Wishlist is being modified concurrently; try again".to_string(),
+        ))
+    }
+
+    async fn delete_wishlist_item(
+        &self,
+        wishlist_id: String,
+        item_id: String,
+        expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError> {
+        for _ in 0..ITEM_EDIT_ATTEMPTS {
+            let stored = self
+                .stored_item(&wishlist_id, &item_id, expected_version)
+                .await?;
+            let request = if stored.legacy_after {
+                // Rewrite the whole list with the remaining items upgraded, so
+                // their IDs survive the shift.
+                let mut remaining = stored.wishlist.items;
+                remaining.remove(stored.position);
+                self.item_edit(
+                    &wishlist_id,
+                    "SET #items = :remaining,".to_string(),
+                    version_condition(Some(stored.wishlist.version)).to_string(),
+                    Some(stored.wishlist.version),
+                )
+                .expression_attribute_values(":remaining", items_attribute(&remaining))
+            } else {
+                let path = format!("#items[{}]", stored.position);
+                self.item_edit(
+                    &wishlist_id,
+                    format!("REMOVE {} SET", path),
+                    format!(
+                        "{} AND {} = :stored",
+                        version_condition(expected_version),
+                        path
+                    ),
+                    expected_version,
+                )
+                .expression_attribute_values(":stored", stored.raw)
+            };
+            match send_update(request).await {
+                Err(AppError::PreconditionFailed) => continue,
+                result => return result,
+            }
+        }
+        Err(AppError::Conflict(
+            "Wishlist is being modified concurrently; try again".to_string(),
+        ))
+    }
+
+    async fn scan_page(
+        &self,
+        limit: usize,
+        start_key: Option<String>,
+    ) -> Result<Page<Wishlist>, AppError> {
+        let scan_output = self
+            .client
+            .scan()
+            .table_name(TABLE_NAME)
+            .limit(limit as i32)
+            .set_exclusive_start_key(
+                start_key.map(|id| HashMap::from([("id".to_string(), AttributeValue::S(id))])),
+            )
+            .send()
+            .await?;
+        Ok(page_from(scan_output.items, scan_output.last_evaluated_key))
+    }
+
+    async fn query_by_owner(
+        &self,
+        owner: String,
+        limit: usize,
+        start_key: Option<String>,
+    ) -> Result<Page<Wishlist>, AppError> {
+        // Index start keys need the index key as well as the table key.
+        let exclusive_start_key = start_key.map(|id| {
+            HashMap::from([
+                ("id".to_string(), AttributeValue::S(id)),
+                ("owner".to_string(), AttributeValue::S(owner.clone())),
+            ])
+        });
+        let query_output = self
+            .client
+            .query()
+            .table_name(TABLE_NAME)
+            .index_name(OWNER_INDEX)
+            .key_condition_expression("#owner = :owner")
+            .expression_attribute_names("#owner", "owner")
+            .expression_attribute_values(":owner", AttributeValue::S(owner))
+            .limit(limit as i32)
+            .set_exclusive_start_key(exclusive_start_key)
+            .send()
+            .await?;
+        Ok(page_from(
+            query_output.items,
+            query_output.last_evaluated_key,
+        ))
+    }
+
+    async fn query_shared_with(
+        &self,
+        subject: String,
+        limit: usize,
+        start_key: Option<String>,
+    ) -> Result<Page<Wishlist>, AppError> {
+        let exclusive_start_key = start_key.map(|id| {
+            HashMap::from([
+                ("wishlist_id".to_string(), AttributeValue::S(id)),
+                ("subject".to_string(), AttributeValue::S(subject.clone())),
+            ])
+        });
+        let output = self
+            .client
+            .query()
+            .table_name(COLLABORATOR_TABLE_NAME)
+            .index_name(SUBJECT_INDEX)
+            .key_condition_expression("#subject = :subject")
+            .expression_attribute_names("#subject", "subject")
+            .expression_attribute_values(":subject", AttributeValue::S(subject))
+            .limit(limit as i32)
+            .set_exclusive_start_key(exclusive_start_key)
+            .send()
+            .await?;
+        let ids: Vec<String> = output
+            .items
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|item| item.get("wishlist_id")?.as_s().ok().cloned())
+            .collect();
+        let last_key = output
+            .last_evaluated_key
+            .as_ref()
+            .and_then(|key| key.get("wishlist_id"))
+            .and_then(|id| id.as_s().ok())
+            .cloned();
+        Ok(Page {
+            items: self.batch_get_wishlists(&ids).await?,
+            last_key,
+        })
+    }
+
+    async fn put_collaborator(
+        &self,
+        wishlist_id: String,
+        collaborator: Collaborator,
+    ) -> Result<(), AppError> {
+        let put = Put::builder()
+            .table_name(COLLABORATOR_TABLE_NAME)
+            .item("wishlist_id", AttributeValue::S(wishlist_id.clone()))
+            .item("subject", AttributeValue::S(collaborator.subject))
+            .item(
+                "role",
+                AttributeValue::S(collaborator.role.as_str().to_string()),
+            )
+            .build()?;
+        self.put_if_wishlist_exists(&wishlist_id, put, "Collaborator")
+            .await
+    }
+
+    async fn get_collaborator(
+        &self,
+        wishlist_id: String,
+        subject: String,
+    ) -> Result<Option<Collaborator>, AppError> {
+        let output = self
+            .client
+            .get_item()
+            .table_name(COLLABORATOR_TABLE_NAME)
+            .key("wishlist_id", AttributeValue::S(wishlist_id))
+            .key("subject", AttributeValue::S(subject))
+            .consistent_read(true)
+            .send()
+            .await?;
+        output.item.as_ref().map(collaborator_from_item).transpose()
+    }
+
+    async fn list_collaborators(&self, wishlist_id: String) -> Result<Vec<Collaborator>, AppError> {
+        let mut collaborators = Vec::new();
+        let mut start_key = None;
+        loop {
+            let output = self
+                .client
+                .query()
+                .table_name(COLLABORATOR_TABLE_NAME)
+                .key_condition_expression("wishlist_id = :wishlist_id")
+                .expression_attribute_values(":wishlist_id", AttributeValue::S(wishlist_id.clone()))
+                .consistent_read(true)
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+            for item in output.items.unwrap_or_default() {
+                collaborators.push(collaborator_from_item(&item)?);
+            }
+            start_key = output.last_evaluated_key;
+            if start_key.is_none() {
+                return Ok(collaborators);
+            }
+        }
+    }
+
+    async fn delete_collaborator(
+        &self,
+        wishlist_id: String,
+        subject: String,
+    ) -> Result<(), AppError> {
+        let result = self
+            .client
+            .delete_item()
+            .table_name(COLLABORATOR_TABLE_NAME)
+            .key("wishlist_id", AttributeValue::S(wishlist_id))
+            .key("subject", AttributeValue::S(subject))
+            .condition_expression("attribute_exists(subject)")
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(SdkError::ServiceError(e)) if e.err().is_conditional_check_failed_exception() => {
+                Err(AppError::NotFound)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn create_share(&self, link: ShareLink) -> Result<(), AppError> {
+        let put = Put::builder()
+            .table_name(SHARE_TABLE_NAME)
+            .set_item(Some(share_attributes(&link)))
+            .condition_expression("attribute_not_exists(id)")
+            .build()?;
+        self.put_if_wishlist_exists(&link.wishlist_id, put, "Share link")
+            .await
+    }
+
+    async fn get_share(&self, id: String) -> Result<Option<ShareLink>, AppError> {
+        let output = self
+            .client
+            .get_item()
+            .table_name(SHARE_TABLE_NAME)
+            .key("id", AttributeValue::S(id))
+            .consistent_read(true)
+            .send()
+            .await?;
+        output.item.map(ShareLink::try_from).transpose()
+    }
+
+    async fn list_shares(&self, wishlist_id: String) -> Result<Vec<ShareLink>, AppError> {
+        let mut links = Vec::new();
+        let mut start_key = None;
+        loop {
+            let output = self
+                .client
+                .query()
+                .table_name(SHARE_TABLE_NAME)
+                .index_name(WISHLIST_INDEX)
+                .key_condition_expression("wishlist_id = :wishlist_id")
+                .expression_attribute_values(":wishlist_id", AttributeValue::S(wishlist_id.clone()))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+            for item in output.items.unwrap_or_default() {
+                links.push(ShareLink::try_from(item)?);
+            }
+            start_key = output.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+        links.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        Ok(links)
+    }
+
+    async fn revoke_share(
+        &self,
+        wishlist_id: String,
+        id: String,
+        revoked_at: u64,
+    ) -> Result<ShareLink, AppError> {
+        let result = self
+            .client
+            .update_item()
+            .table_name(SHARE_TABLE_NAME)
+            .key("id", AttributeValue::S(id))
+            .update_expression("SET #revoked_at = if_not_exists(#revoked_at, :revoked_at)")
+            .condition_expression("wishlist_id = :wishlist_id")
+            .expression_attribute_names("#revoked_at", "revoked_at")
+            .expression_attribute_values(":wishlist_id", AttributeValue::S(wishlist_id))
+            .expression_attribute_values(":revoked_at", AttributeValue::N(revoked_at.to_string()))
+            .return_values(ReturnValue::AllNew)
+            .send()
+            .await;
+        match result {
+            Ok(output) => ShareLink::try_from(output.attributes.unwrap_or_default()),
+            Err(SdkError::ServiceError(e)) if e.err().is_conditional_check_failed_exception() => {
+                Err(AppError::NotFound)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn reserve_item(
+        &self,
+        wishlist_id: String,
+        reservation: Reservation,
+    ) -> Result<Reservation, AppError> {
+        // Check against the others' reservations and the item's quantity as
+        // read, then write only if neither the item's reservation record nor
+        // the wishlist changed since; otherwise start over.
+        for _ in 0..ITEM_EDIT_ATTEMPTS {
+            let found = self
+                .stored_item(&wishlist_id, &reservation.item_id, None)
+                .await?;
+            let (wishlist, item) = (&found.wishlist, &found.wishlist.items[found.position]);
+            let (mut reservations, revision) = self
+                .reservation_record(&wishlist_id, &reservation.item_id)
+                .await?;
+            let mut stored = reservation.clone();
+            if let Some(existing) = reservations
+                .iter()
+                .find(|existing| existing.reserver == stored.reserver)
+            {
+                if existing.claim_hash != stored.claim_hash {
+                    return Err(reservation::claimed());
+                }
+                stored.reserved_at = existing.reserved_at;
+            }
+            let others: u32 = reservations
+                .iter()
+                .filter(|other| other.reserver != reservation.reserver)
+                .map(|other| other.quantity)
+                .sum();
+            let available = item.quantity.saturating_sub(others);
+            if reservation.quantity > available {
+                return Err(reservation::unavailable(available));
+            }
+            reservations.retain(|existing| existing.reserver != stored.reserver);
+            reservations.push(stored.clone());
+
+            let unchanged = ConditionCheck::builder()
+                .table_name(TABLE_NAME)
+                .key("id", AttributeValue::S(wishlist_id.clone()))
+                .condition_expression(version_condition(Some(wishlist.version)))
+                .expression_attribute_names("#version", "version")
+                .expression_attribute_values(
+                    ":expected",
+                    AttributeValue::N(wishlist.version.to_string()),
+                )
+                .build()?;
+            let entries = reservations
+                .iter()
+                .map(|r| (r.reserver.clone(), reservation_attribute(r)))
+                .collect();
+            let mut put = Put::builder()
+                .table_name(RESERVATION_TABLE_NAME)
+                .item("wishlist_id", AttributeValue::S(wishlist_id.clone()))
+                .item("item_id", AttributeValue::S(stored.item_id.clone()))
+                .item("reservations", AttributeValue::M(entries))
+                .item(
+                    "revision",
+                    AttributeValue::N(revision.map_or(0, |r| r + 1).to_string()),
+                );
+            put = match revision {
+                Some(revision) => put
+                    .condition_expression("#revision = :revision")
+                    .expression_attribute_names("#revision", "revision")
+                    .expression_attribute_values(
+                        ":revision",
+                        AttributeValue::N(revision.to_string()),
+                    ),
+                None => put.condition_expression("attribute_not_exists(item_id)"),
+            };
+            let result = self
+                .client
+                .transact_write_items()
+                .transact_items(
+                    TransactWriteItem::builder()
+                        .condition_check(unchanged)
+                        .build(),
+                )
+                .transact_items(TransactWriteItem::builder().put(put.build()?).build())
+                .send()
+                .await;
+            match result {
+                Ok(_) => return Ok(stored),
+                Err(SdkError::ServiceError(e)) if e.err().is_transaction_canceled_exception() => {
+                    continue
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(AppError::Conflict(
+            "Item is being reserved concurrently; try again".to_string(),
+        ))
+    }
+
+    async fn list_reservations(&self, wishlist_id: String) -> Result<Vec<Reservation>, AppError> {
+        let mut reservations = Vec::new();
+        let mut start_key = None;
+        loop {
+            let output = self
+                .client
+                .query()
+                .table_name(RESERVATION_TABLE_NAME)
+                .key_condition_expression("wishlist_id = :wishlist_id")
+                .expression_attribute_values(":wishlist_id", AttributeValue::S(wishlist_id.clone()))
+                .consistent_read(true)
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+            for item in output.items.unwrap_or_default() {
+                reservations.extend(reservations_from_item(&item)?);
+            }
+            start_key = output.last_evaluated_key;
+            if start_key.is_none() {
+                return Ok(reservations);
+            }
+        }
+    }
+
+    async fn delete_reservation(
+        &self,
+        wishlist_id: String,
+        item_id: String,
+        reserver: String,
+        claim_hash: Option<String>,
+    ) -> Result<(), AppError> {
+        let mut update = self
+            .client
+            .update_item()
+            .table_name(RESERVATION_TABLE_NAME)
+            .key("wishlist_id", AttributeValue::S(wishlist_id.clone()))
+            .key("item_id", AttributeValue::S(item_id.clone()))
+            .update_expression("REMOVE #reservations.#reserver ADD #revision :one")
+            .expression_attribute_names("#reservations", "reservations")
+            .expression_attribute_names("#reserver", reserver.clone())
+            .expression_attribute_names("#claim_hash", "claim_hash")
+            .expression_attribute_names("#revision", "revision")
+            .expression_attribute_values(":one", AttributeValue::N("1".to_string()));
+        update = match claim_hash {
+            Some(claim_hash) => update
+                .condition_expression("#reservations.#reserver.#claim_hash = :claim_hash")
+                .expression_attribute_values(":claim_hash", AttributeValue::S(claim_hash)),
+            None => update.condition_expression(
+                "attribute_exists(#reservations.#reserver) \
+                 AND attribute_not_exists(#reservations.#reserver.#claim_hash)",
+            ),
+        };
+        match update.send().await {
+            Ok(_) => Ok(()),
+            Err(SdkError::ServiceError(e)) if e.err().is_conditional_check_failed_exception() => {
+                let (reservations, _) = self.reservation_record(&wishlist_id, &item_id).await?;
+                if reservations.iter().any(|r| r.reserver == reserver) {
+                    Err(reservation::claimed())
+                } else {
+                    Err(AppError::NotFound)
+                }
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn add_pledge(&self, wishlist_id: String, pledge: Pledge) -> Result<(), AppError> {
+        // Check against the item's target and funding total as read, then
+        // write the pledge and the new total only if neither the total nor
+        // the wishlist changed since; otherwise start over.
+        for _ in 0..ITEM_EDIT_ATTEMPTS {
+            let found = self
+                .stored_item(&wishlist_id, &pledge.item_id, None)
+                .await?;
+            let (wishlist, item) = (&found.wishlist, &found.wishlist.items[found.position]);
+            let target = pledge::target(item).ok_or_else(pledge::unpriced)?;
+            let funding = self
+                .client
+                .get_item()
+                .table_name(PLEDGE_TABLE_NAME)
+                .key("wishlist_id", AttributeValue::S(wishlist_id.clone()))
+                .key("id", AttributeValue::S(funding_key(&pledge.item_id)))
+                .consistent_read(true)
+                .send()
+                .await?
+                .item;
+            let pledged = funding.as_ref().map(|funding| {
+                funding
+                    .get("pledged_cents")
+                    .and_then(|v| v.as_n().ok())
+                    .and_then(|n| n.parse::<u64>().ok())
+                    .unwrap_or_default()
+            });
+            let remaining = target.saturating_sub(pledged.unwrap_or_default());
+            if pledge.amount_cents > remaining {
+                return Err(pledge::over_target(remaining));
+            }
+
+            let unchanged = ConditionCheck::builder()
+                .table_name(TABLE_NAME)
+                .key("id", AttributeValue::S(wishlist_id.clone()))
+                .condition_expression(version_condition(Some(wishlist.version)))
+                .expression_attribute_names("#version", "version")
+                .expression_attribute_values(
+                    ":expected",
+                    AttributeValue::N(wishlist.version.to_string()),
+                )
+                .build()?;
+            let mut total = Put::builder()
+                .table_name(PLEDGE_TABLE_NAME)
+                .item("wishlist_id", AttributeValue::S(wishlist_id.clone()))
+                .item("id", AttributeValue::S(funding_key(&pledge.item_id)))
+                .item(
+                    "pledged_cents",
+                    AttributeValue::N(
+                        (pledged.unwrap_or_default() + pledge.amount_cents).to_string(),
+                    ),
+                );
+            total = match pledged {
+                Some(pledged) => total
+                    .condition_expression("#pledged = :pledged")
+                    .expression_attribute_names("#pledged", "pledged_cents")
+                    .expression_attribute_values(
+                        ":pledged",
+                        AttributeValue::N(pledged.to_string()),
+                    ),
+                None => total.condition_expression("attribute_not_exists(id)"),
+            };
+            let record = Put::builder()
+                .table_name(PLEDGE_TABLE_NAME)
+                .set_item(Some(pledge_attributes(&wishlist_id, &pledge)))
+                .condition_expression("attribute_not_exists(id)")
+                .build()?;
+            let result = self
+                .client
+                .transact_write_items()
+                .transact_items(
+                    TransactWriteItem::builder()
+                        .condition_check(unchanged)
+                        .build(),
+                )
+                .transact_items(TransactWriteItem::builder().put(total.build()?).build())
+                .transact_items(TransactWriteItem::builder().put(record).build())
+                .send()
+                .await;
+            match result {
+                Ok(_) => return Ok(()),
+                Err(SdkError::ServiceError(e)) if e.err().is_transaction_canceled_exception() => {
+                    continue
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(AppError::Conflict(
+            "Item is being funded concurrently; try again".to_string(),
+        ))
+    }
+
+    async fn list_pledges(
+        &self,
+        wishlist_id: String,
+        item_id: String,
+    ) -> Result<Vec<Pledge>, AppError> {
+        let mut pledges = Vec::new();
+        let mut start_key = None;
+        loop {
+            let output = self
+                .client
+                .query()
+                .table_name(PLEDGE_TABLE_NAME)
+                .key_condition_expression("wishlist_id = :wishlist_id AND begins_with(id, :prefix)")
+                .expression_attribute_values(":wishlist_id", AttributeValue::S(wishlist_id.clone()))
+                .expression_attribute_values(":prefix", AttributeValue::S(pledge_prefix(&item_id)))
+                .consistent_read(true)
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+            for item in output.items.unwrap_or_default() {
+                pledges.push(Pledge::try_from(item)?);
+            }
+            start_key = output.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+        pledges.sort_by(|a, b| (a.pledged_at, &a.id).cmp(&(b.pledged_at, &b.id)));
+        Ok(pledges)
+    }
+
+    async fn create_exchange(&self, exchange: Exchange) -> Result<(), AppError> {
+        let result = self
+            .client
+            .put_item()
+            .table_name(EXCHANGE_TABLE_NAME)
+            .set_item(Some(exchange_attributes(&exchange)))
+            .condition_expression("attribute_not_exists(id)")
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(SdkError::ServiceError(e)) if e.err().is_conditional_check_failed_exception() => {
+                Err(AppError::Conflict(format!(
+                    "Exchange '{}' already exists",
+                    exchange.id
+                )))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn get_exchange(&self, id: String) -> Result<Option<Exchange>, AppError> {
+        let output = self
+            .client
+            .get_item()
+            .table_name(EXCHANGE_TABLE_NAME)
+            .key("id", AttributeValue::S(id))
+            .consistent_read(true)
+            .send()
+            .await?;
+        output.item.map(Exchange::try_from).transpose()
+    }
+
+    async fn record_draw(
+        &self,
+        id: String,
+        participants: Vec<Participant>,
+        drawn_at: u64,
+    ) -> Result<(), AppError> {
+        let result = self
+            .client
+            .update_item()
+            .table_name(EXCHANGE_TABLE_NAME)
+            .key("id", AttributeValue::S(id))
+            .update_expression("SET #participants = :participants, #drawn_at = :drawn_at")
+            .condition_expression("attribute_exists(id) AND attribute_not_exists(#drawn_at)")
+            .expression_attribute_names("#participants", "participants")
+            .expression_attribute_names("#drawn_at", "drawn_at")
+            .expression_attribute_values(":participants", participants_attribute(&participants))
+            .expression_attribute_values(":drawn_at", AttributeValue::N(drawn_at.to_string()))
+            .return_values_on_condition_check_failure(ReturnValuesOnConditionCheckFailure::AllOld)
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(SdkError::ServiceError(e)) if e.err().is_conditional_check_failed_exception() => {
+                let stored = match e.into_err() {
+                    UpdateItemError::ConditionalCheckFailedException(failure) => failure.item,
+                    _ => None,
+                };
+                Err(match stored {
+                    Some(_) => exchange::already_drawn(),
+                    None => AppError::NotFound,
+                })
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete_exchange(&self, id: String) -> Result<(), AppError> {
+        let result = self
+            .client
+            .delete_item()
+            .table_name(EXCHANGE_TABLE_NAME)
+            .key("id", AttributeValue::S(id))
+            .condition_expression("attribute_exists(id)")
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(SdkError::ServiceError(e)) if e.err().is_conditional_check_failed_exception() => {
+                Err(AppError::NotFound)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn create_api_key(&self, key: ApiKey) -> Result<(), AppError> {
+        let result = self
+            .client
+            .put_item()
+            .table_name(API_KEY_TABLE_NAME)
+            .set_item(Some(api_key_attributes(&key)))
+            .condition_expression("attribute_not_exists(id)")
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(SdkError::ServiceError(e)) if e.err().is_conditional_check_failed_exception() => {
+                Err(AppError::Conflict(format!(
+                    "API key '{}' already exists",
+                    key.id
+                )))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn get_api_key(&self, id: String) -> Result<Option<ApiKey>, AppError> {
+        let output = self
+            .client
+            .get_item()
+            .table_name(API_KEY_TABLE_NAME)
+            .key("id", AttributeValue::S(id))
+            .consistent_read(true)
+            .send()
+            .await?;
+        output.item.map(ApiKey::try_from).transpose()
+    }
+
+    async fn list_api_keys(&self, owner: String) -> Result<Vec<ApiKey>, AppError> {
+        let mut keys = Vec::new();
+        let mut start_key = None;
+        loop {
+            let output = self
+                .client
+                .query()
+                .table_name(API_KEY_TABLE_NAME)
+                .index_name(OWNER_INDEX)
+                .key_condition_expression("#owner = :owner")
+                .expression_attribute_names("#owner", "owner")
+                .expression_attribute_values(":owner", AttributeValue::S(owner.clone()))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+            for item in output.items.unwrap_or_default() {
+                keys.push(ApiKey::try_from(item)?);
+            }
+            start_key = output.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+        keys.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        Ok(keys)
+    }
+
+    async fn revoke_api_key(
+        &self,
+        owner: String,
+        id: String,
+        revoked_at: u64,
+    ) -> Result<ApiKey, AppError> {
+        let result = self
+            .client
+            .update_item()
+            .table_name(API_KEY_TABLE_NAME)
+            .key("id", AttributeValue::S(id))
+            .update_expression("SET #revoked_at = if_not_exists(#revoked_at, :revoked_at)")
+            .condition_expression("#owner = :owner")
+            .expression_attribute_names("#owner", "owner")
+            .expression_attribute_names("#revoked_at", "revoked_at")
+            .expression_attribute_values(":owner", AttributeValue::S(owner))
+            .expression_attribute_values(":revoked_at", AttributeValue::N(revoked_at.to_string()))
+            .return_values(ReturnValue::AllNew)
+            .send()
+            .await;
+        match result {
+            Ok(output) => ApiKey::try_from(output.attributes.unwrap_or_default()),
+            Err(SdkError::ServiceError(e)) if e.err().is_conditional_check_failed_exception() => {
+                Err(AppError::NotFound)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn touch_api_key(&self, id: String, used_at: u64) -> Result<(), AppError> {
+        let result = self
+            .client
+            .update_item()
+            .table_name(API_KEY_TABLE_NAME)
+            .key("id", AttributeValue::S(id))
+            .update_expression("SET #last_used_at = :used_at")
+            .condition_expression("attribute_exists(id)")
+            .expression_attribute_names("#last_used_at", "last_used_at")
+            .expression_attribute_values(":used_at", AttributeValue::N(used_at.to_string()))
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(SdkError::ServiceError(e)) if e.err().is_conditional_check_failed_exception() => {
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
@@ -0,0 +1,489 @@
+use crate::api_key::ApiKey;
+use crate::db::{Page, WishlistStore};
+use crate::error::AppError;
+use crate::exchange::{self, Exchange, Participant};
+use crate::handlers::wishlist::{Collaborator, CollaboratorRole, Wishlist, WishlistItem};
+use crate::pledge::{self, Pledge};
+use crate::reservation::{self, Reservation};
+use crate::share::ShareLink;
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+/// Wishlist ID, item ID, then reserver.
+type ReservationKey = (String, String, String);
+/// Each item's pledges, oldest first, keyed on wishlist ID, then item ID.
+type PledgeMap = BTreeMap<(String, String), Vec<Pledge>>;
+
+/// In-process backend for local development and tests. Clones share the same
+/// underlying map, so one store can be handed to every request task.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore {
+    wishlists: Arc<RwLock<BTreeMap<String, Wishlist>>>,
+    /// Keyed on wishlist ID, then subject.
+    collaborators: Arc<RwLock<BTreeMap<(String, String), CollaboratorRole>>>,
+    shares: Arc<RwLock<BTreeMap<String, ShareLink>>>,
+    reservations: Arc<RwLock<BTreeMap<ReservationKey, Reservation>>>,
+    pledges: Arc<RwLock<PledgeMap>>,
+    exchanges: Arc<RwLock<BTreeMap<String, Exchange>>>,
+    api_keys: Arc<RwLock<BTreeMap<String, ApiKey>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+}
+
+fn poisoned<T>(_: T) -> AppError {
+    AppError::Generic("In-memory store lock poisoned".to_string())
+}
+
+fn check_version<W: std::borrow::Borrow<Wishlist>>(
+    stored: Option<W>,
+    expected: Option<u64>,
+) -> Result<W, AppError> {
+    match (stored, expected) {
+        (None, _) => Err(AppError::NotFound),
+        (Some(stored), Some(expected)) if stored.borrow().version != expected => {
+            Err(AppError::PreconditionFailed)
+        }
+        (Some(stored), _) => Ok(stored),
+    }
+}
+
+/// Pages through wishlists already sorted by ID, resuming after `start_key`.
+fn page_after<'a>(
+    wishlists: impl Iterator<Item = &'a Wishlist>,
+    limit: usize,
+    start_key: Option<String>,
+) -> Page<Wishlist> {
+    let mut remaining = wishlists.filter(|w| match &start_key {
+        Some(key) => w.id > *key,
+        None => true,
+    });
+    let items: Vec<Wishlist> = remaining.by_ref().take(limit).cloned().collect();
+    let last_key = match remaining.next() {
+        Some(_) => items.last().map(|w| w.id.clone()),
+        None => None,
+    };
+    Page { items, last_key }
+}
+
+#[async_trait]
+impl WishlistStore for MemoryStore {
+    async fn get_item(&self, id: String) -> Result<Option<Wishlist>, AppError> {
+        let wishlists = self.wishlists.read().map_err(poisoned)?;
+        Ok(wishlists.get(&id).cloned())
+    }
+
+    async fn create_item(&self, wishlist: Wishlist) -> Result<(), AppError> {
+        let mut wishlists = self.wishlists.write().map_err(poisoned)?;
+        if wishlists.contains_key(&wishlist.id) {
+            return Err(AppError::Conflict(format!(
+                "Wishlist '{}' already exists",
+                wishlist.id
+            )));
+        }
+        wishlists.insert(wishlist.id.clone(), wishlist);
+        Ok(())
+    }
+
+    async fn update_item(
+        &self,
+        mut wishlist: Wishlist,
+        expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError> {
+        let mut wishlists = self.wishlists.write().map_err(poisoned)?;
+        let stored = check_version(wishlists.get(&wishlist.id), expected_version)?;
+        wishlist.version = stored.version + 1;
+        wishlists.insert(wishlist.id.clone(), wishlist.clone());
+        Ok(wishlist)
+    }
+
+    async fn delete_item(&self, id: String, expected_version: Option<u64>) -> Result<(), AppError> {
+        let mut wishlists = self.wishlists.write().map_err(poisoned)?;
+        check_version(wishlists.get(&id), expected_version)?;
+        wishlists.remove(&id);
+        let mut collaborators = self.collaborators.write().map_err(poisoned)?;
+        collaborators.retain(|(wishlist_id, _), _| *wishlist_id != id);
+        let mut shares = self.shares.write().map_err(poisoned)?;
+        shares.retain(|_, link| link.wishlist_id != id);
+        let mut reservations = self.reservations.write().map_err(poisoned)?;
+        reservations.retain(|(wishlist_id, _, _), _| *wishlist_id != id);
+        let mut pledges = self.pledges.write().map_err(poisoned)?;
+        pledges.retain(|(wishlist_id, _), _| *wishlist_id != id);
+        Ok(())
+    }
+
+    async fn add_wishlist_item(
+        &self,
+        wishlist_id: String,
+        item: WishlistItem,
+        expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError> {
+        let mut wishlists = self.wishlists.write().map_err(poisoned)?;
+        let stored = check_version(wishlists.get_mut(&wishlist_id), expected_version)?;
+        stored.items.push(item);
+        stored.version += 1;
+        Ok(stored.clone())
+    }
+
+    async fn update_wishlist_item(
+        &self,
+        wishlist_id: String,
+        item_id: String,
+        patch: serde_json::Value,
+        expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError> {
+        let mut wishlists = self.wishlists.write().map_err(poisoned)?;
+        let stored = check_version(wishlists.get_mut(&wishlist_id), expected_version)?;
+        let item = stored
+            .items
+            .iter_mut()
+            .find(|item| item.id == item_id)
+            .ok_or(AppError::NotFound)?;
+        *item = item.patched(&patch)?;
+        stored.version += 1;
+        Ok(stored.clone())
+    }
+
+    async fn delete_wishlist_item(
+        &self,
+        wishlist_id: String,
+        item_id: String,
+        expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError> {
+        let mut wishlists = self.wishlists.write().map_err(poisoned)?;
+        let stored = check_version(wishlists.get_mut(&wishlist_id), expected_version)?;
+        let position = stored
+            .items
+            .iter()
+            .position(|item| item.id == item_id)
+            .ok_or(AppError::NotFound)?;
+        stored.items.remove(position);
+        stored.version += 1;
+        Ok(stored.clone())
+    }
+
+    async fn scan_page(
+        &self,
+        limit: usize,
+        start_key: Option<String>,
+    ) -> Result<Page<Wishlist>, AppError> {
+        let wishlists = self.wishlists.read().map_err(poisoned)?;
+        Ok(page_after(wishlists.values(), limit, start_key))
+    }
+
+    async fn query_by_owner(
+        &self,
+        owner: String,
+        limit: usize,
+        start_key: Option<String>,
+    ) -> Result<Page<Wishlist>, AppError> {
+        let wishlists = self.wishlists.read().map_err(poisoned)?;
+        let owned = wishlists.values().filter(|w| w.owner == owner);
+        Ok(page_after(owned, limit, start_key))
+    }
+
+    async fn query_shared_with(
+        &self,
+        subject: String,
+        limit: usize,
+        start_key: Option<String>,
+    ) -> Result<Page<Wishlist>, AppError> {
+        let wishlists = self.wishlists.read().map_err(poisoned)?;
+        let collaborators = self.collaborators.read().map_err(poisoned)?;
+        let shared = wishlists
+            .values()
+            .filter(|w| collaborators.contains_key(&(w.id.clone(), subject.clone())));
+        Ok(page_after(shared, limit, start_key))
+    }
+
+    async fn put_collaborator(
+        &self,
+        wishlist_id: String,
+        collaborator: Collaborator,
+    ) -> Result<(), AppError> {
+        let wishlists = self.wishlists.read().map_err(poisoned)?;
+        if !wishlists.contains_key(&wishlist_id) {
+            return Err(AppError::NotFound);
+        }
+        let mut collaborators = self.collaborators.write().map_err(poisoned)?;
+        collaborators.insert((wishlist_id, collaborator.subject), collaborator.role);
+        Ok(())
+    }
+
+    async fn get_collaborator(
+        &self,
+        wishlist_id: String,
+        subject: String,
+    ) -> Result<Option<Collaborator>, AppError> {
+        let collaborators = self.collaborators.read().map_err(poisoned)?;
+        let role = collaborators.get(&(wishlist_id, subject.clone())).copied();
+        Ok(role.map(|role| Collaborator { subject, role }))
+    }
+
+    async fn list_collaborators(&self, wishlist_id: String) -> Result<Vec<Collaborator>, AppError> {
+        let collaborators = self.collaborators.read().map_err(poisoned)?;
+        Ok(collaborators
+            .iter()
+            .filter(|((id, _), _)| *id == wishlist_id)
+            .map(|((_, subject), role)| Collaborator {
+                subject: subject.clone(),
+                role: *role,
+            })
+            .collect())
+    }
+
+    async fn delete_collaborator(
+        &self,
+        wishlist_id: String,
+        subject: String,
+    ) -> Result<(), AppError> {
+        let mut collaborators = self.collaborators.write().map_err(poisoned)?;
+        match collaborators.remove(&(wishlist_id, subject)) {
+            Some(_) => Ok(()),
+            None => Err(AppError::NotFound),
+        }
+    }
+
+    async fn create_share(&self, link: ShareLink) -> Result<(), AppError> {
+        let wishlists = self.wishlists.read().map_err(poisoned)?;
+        if !wishlists.contains_key(&link.wishlist_id) {
+            return Err(AppError::NotFound);
+        }
+        let mut shares = self.shares.write().map_err(poisoned)?;
+        if shares.contains_key(&link.id) {
+            return Err(AppError::Conflict(format!(
+                "Share link '{}' already exists",
+                link.id
+            )));
+        }
+        shares.insert(link.id.clone(), link);
+        Ok(())
+    }
+
+    async fn get_share(&self, id: String) -> Result<Option<ShareLink>, AppError> {
+        let shares = self.shares.read().map_err(poisoned)?;
+        Ok(shares.get(&id).cloned())
+    }
+
+    async fn list_shares(&self, wishlist_id: String) -> Result<Vec<ShareLink>, AppError> {
+        let shares = self.shares.read().map_err(poisoned)?;
+        let mut links: Vec<ShareLink> = shares
+            .values()
+            .filter(|link| link.wishlist_id == wishlist_id)
+            .cloned()
+            .collect();
+        links.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        Ok(links)
+    }
+
+    async fn revoke_share(
+        &self,
+        wishlist_id: String,
+        id: String,
+        revoked_at: u64,
+    ) -> Result<ShareLink, AppError> {
+        let mut shares = self.shares.write().map_err(poisoned)?;
+        let link = shares
+            .get_mut(&id)
+            .filter(|link| link.wishlist_id == wishlist_id)
+            .ok_or(AppError::NotFound)?;
+        link.revoked_at.get_or_insert(revoked_at);
+        Ok(link.clone())
+    }
+
+    async fn reserve_item(
+        &self,
+        wishlist_id: String,
+        mut reservation: Reservation,
+    ) -> Result<Reservation, AppError> {
+        let wishlists = self.wishlists.read().map_err(poisoned)?;
+        let item = wishlists
+            .get(&wishlist_id)
+            .and_then(|wishlist| {
+                wishlist
+                    .items
+                    .iter()
+                    .find(|item| item.id == reservation.item_id)
+            })
+            .ok_or(AppError::NotFound)?;
+        let mut reservations = self.reservations.write().map_err(poisoned)?;
+        let key = (
+            wishlist_id.clone(),
+            reservation.item_id.clone(),
+            reservation.reserver.clone(),
+        );
+        let existing = reservations.get(&key);
+        if existing.is_some_and(|existing| existing.claim_hash != reservation.claim_hash) {
+            return Err(reservation::claimed());
+        }
+        if let Some(existing) = existing {
+            reservation.reserved_at = existing.reserved_at;
+        }
+        let others: u32 = reservations
+            .iter()
+            .filter(|((w, i, r), _)| {
+                *w == wishlist_id && *i == reservation.item_id && *r != reservation.reserver
+            })
+            .map(|(_, other)| other.quantity)
+            .sum();
+        let available = item.quantity.saturating_sub(others);
+        if reservation.quantity > available {
+            return Err(reservation::unavailable(available));
+        }
+        reservations.insert(key, reservation.clone());
+        Ok(reservation)
+    }
+
+    async fn list_reservations(&self, wishlist_id: String) -> Result<Vec<Reservation>, AppError> {
+        let reservations = self.reservations.read().map_err(poisoned)?;
+        Ok(reservations
+            .iter()
+            .filter(|((w, _, _), _)| *w == wishlist_id)
+            .map(|(_, reservation)| reservation.clone())
+            .collect())
+    }
+
+    async fn delete_reservation(
+        &self,
+        wishlist_id: String,
+        item_id: String,
+        reserver: String,
+        claim_hash: Option<String>,
+    ) -> Result<(), AppError> {
+        let mut reservations = self.reservations.write().map_err(poisoned)?;
+        let key = (wishlist_id, item_id, reserver);
+        match reservations.get(&key) {
+            Some(existing) if existing.claim_hash != claim_hash => Err(reservation::claimed()),
+            Some(_) => {
+                reservations.remove(&key);
+                Ok(())
+            }
+            None => Err(AppError::NotFound),
+        }
+    }
+
+    async fn add_pledge(&self, wishlist_id: String, pledge: Pledge) -> Result<(), AppError> {
+        let wishlists = self.wishlists.read().map_err(poisoned)?;
+        let item = wishlists
+            .get(&wishlist_id)
+            .and_then(|wishlist| wishlist.items.iter().find(|item| item.id == pledge.item_id))
+            .ok_or(AppError::NotFound)?;
+        let target = pledge::target(item).ok_or_else(pledge::unpriced)?;
+        let mut pledges = self.pledges.write().map_err(poisoned)?;
+        let item_pledges = pledges
+            .entry((wishlist_id, pledge.item_id.clone()))
+            .or_default();
+        let pledged: u64 = item_pledges.iter().map(|p| p.amount_cents).sum();
+        let remaining = target.saturating_sub(pledged);
+        if pledge.amount_cents > remaining {
+            return Err(pledge::over_target(remaining));
+        }
+        item_pledges.push(pledge);
+        Ok(())
+    }
+
+    async fn list_pledges(
+        &self,
+        wishlist_id: String,
+        item_id: String,
+    ) -> Result<Vec<Pledge>, AppError> {
+        let pledges = self.pledges.read().map_err(poisoned)?;
+        Ok(pledges
+            .get(&(wishlist_id, item_id))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn create_exchange(&self, exchange: Exchange) -> Result<(), AppError> {
+        let mut exchanges = self.exchanges.write().map_err(poisoned)?;
+        if exchanges.contains_key(&exchange.id) {
+            return Err(AppError::Conflict(format!(
+                "Exchange '{}' already exists",
+                exchange.id
+            )));
+        }
+        exchanges.insert(exchange.id.clone(), exchange);
+        Ok(())
+    }
+
+    async fn get_exchange(&self, id: String) -> Result<Option<Exchange>, AppError> {
+        let exchanges = self.exchanges.read().map_err(poisoned)?;
+        Ok(exchanges.get(&id).cloned())
+    }
+
+    async fn record_draw(
+        &self,
+        id: String,
+        participants: Vec<Participant>,
+        drawn_at: u64,
+    ) -> Result<(), AppError> {
+        let mut exchanges = self.exchanges.write().map_err(poisoned)?;
+        let stored = exchanges.get_mut(&id).ok_or(AppError::NotFound)?;
+        if stored.drawn_at.is_some() {
+            return Err(exchange::already_drawn());
+        }
+        stored.participants = participants;
+        stored.drawn_at = Some(drawn_at);
+        Ok(())
+    }
+
+    async fn delete_exchange(&self, id: String) -> Result<(), AppError> {
+        let mut exchanges = self.exchanges.write().map_err(poisoned)?;
+        exchanges.remove(&id).map(|_| ()).ok_or(AppError::NotFound)
+    }
+
+    async fn create_api_key(&self, key: ApiKey) -> Result<(), AppError> {
+        let mut keys = self.api_keys.write().map_err(poisoned)?;
+        if keys.contains_key(&key.id) {
+            return Err(AppError::Conflict(format!(
+                "API key '{}' already exists",
+                key.id
+            )));
+        }
+        keys.insert(key.id.clone(), key);
+        Ok(())
+    }
+
+    async fn get_api_key(&self, id: String) -> Result<Option<ApiKey>, AppError> {
+        let keys = self.api_keys.read().map_err(poisoned)?;
+        Ok(keys.get(&id).cloned())
+    }
+
+    async fn list_api_keys(&self, owner: String) -> Result<Vec<ApiKey>, AppError> {
+        let keys = self.api_keys.read().map_err(poisoned)?;
+        let mut owned: Vec<ApiKey> = keys
+            .values()
+            .filter(|k| k.owner == owner)
+            .cloned()
+            .collect();
+        owned.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        Ok(owned)
+    }
+
+    async fn revoke_api_key(
+        &self,
+        owner: String,
+        id: String,
+        revoked_at: u64,
+    ) -> Result<ApiKey, AppError> {
+        let mut keys = self.api_keys.write().map_err(poisoned)?;
+        let key = keys
+            .get_mut(&id)
+            .filter(|k| k.owner == owner)
+            .ok_or(AppError::NotFound)?;
+        key.revoked_at.get_or_insert(revoked_at);
+        Ok(key.clone())
+    }
+
+    async fn touch_api_key(&self, id: String, used_at: u64) -> Result<(), AppError> {
+        let mut keys = self.api_keys.write().map_err(poisoned)?;
+        if let Some(key) = keys.get_mut(&id) {
+            key.last_used_at = Some(used_at);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,275 @@
+use crate::api_key::ApiKey;
+use crate::error::AppError;
+use crate::exchange::{Exchange, Participant};
+use crate::handlers::wishlist::{Collaborator, Wishlist, WishlistItem};
+use crate::pledge::Pledge;
+use crate::reservation::Reservation;
+use crate::share::ShareLink;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+pub mod dynamodb;
+pub mod memory;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+pub use dynamodb::{get_db_client, DynamoDbStore, RetrySettings, API_KEY_TABLE_NAME, TABLE_NAME};
+pub use memory::MemoryStore;
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresStore;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+/// One page of results. `last_key` is the ID to resume after, or `None` once
+/// the listing is exhausted.
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub last_key: Option<String>,
+}
+
+/// Storage backend the handlers talk to. Each backend must behave the same,
+/// with every existence and version check done atomically with the write:
+///
+/// - `get_item` returns `Ok(None)` for unknown IDs.
+/// - `create_item` fails with `AppError::Conflict` if the ID is taken.
+/// - `update_item` and `delete_item` fail with `AppError::NotFound` for
+///   unknown IDs and, given an `expected_version`, with
+///   `AppError::PreconditionFailed` when the stored version differs.
+/// - `update_item` bumps the stored version and returns the new wishlist.
+/// - The `*_wishlist_item` methods change one item without touching the rest
+///   of the list, bump the wishlist's version the same way, and fail with
+///   `AppError::NotFound` if the item is missing.
+/// - Collaborators belong to a wishlist: `put_collaborator` fails with
+///   `AppError::NotFound` if the wishlist is missing, and deleting a wishlist
+///   removes its collaborators. Share links, reservations and pledges behave
+///   the same way.
+/// - `reserve_item` never lets an item's reservations add up to more than its
+///   quantity, and `add_pledge` never lets its pledges add up to more than
+///   its price times its quantity; both check and write in one step, as do
+///   the `claim_hash` checks of reservations.
+/// - `record_draw` draws an exchange at most once, checking and writing in
+///   one step.
+/// - API keys are looked up by ID and listed by owner; `revoke_api_key`
+///   fails with `AppError::NotFound` unless the key belongs to that owner.
+#[async_trait]
+pub trait WishlistStore: Send + Sync {
+    async fn get_item(&self, id: String) -> Result<Option<Wishlist>, AppError>;
+    async fn create_item(&self, wishlist: Wishlist) -> Result<(), AppError>;
+    async fn update_item(
+        &self,
+        wishlist: Wishlist,
+        expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError>;
+    async fn delete_item(&self, id: String, expected_version: Option<u64>) -> Result<(), AppError>;
+    /// Appends `item` to the end of the wishlist's items.
+    async fn add_wishlist_item(
+        &self,
+        wishlist_id: String,
+        item: WishlistItem,
+        expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError>;
+    /// Applies a JSON merge patch to one item, against its current stored
+    /// state, so concurrent patches to different fields don't overwrite each
+    /// other.
+    async fn update_wishlist_item(
+        &self,
+        wishlist_id: String,
+        item_id: String,
+        patch: serde_json::Value,
+        expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError>;
+    async fn delete_wishlist_item(
+        &self,
+        wishlist_id: String,
+        item_id: String,
+        expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError>;
+    /// Returns up to `limit` wishlists following `start_key`. A page may hold
+    /// fewer than `limit` items and still have a `last_key`.
+    async fn scan_page(
+        &self,
+        limit: usize,
+        start_key: Option<String>,
+    ) -> Result<Page<Wishlist>, AppError>;
+    /// Like `scan_page`, restricted to wishlists belonging to `owner`.
+    async fn query_by_owner(
+        &self,
+        owner: String,
+        limit: usize,
+        start_key: Option<String>,
+    ) -> Result<Page<Wishlist>, AppError>;
+
+    /// Like `scan_page`, restricted to wishlists `subject` collaborates on.
+    /// Answered from an index on the collaborator, not by scanning.
+    async fn query_shared_with(
+        &self,
+        subject: String,
+        limit: usize,
+        start_key: Option<String>,
+    ) -> Result<Page<Wishlist>, AppError>;
+    /// Adds a collaborator to a wishlist, or changes their role.
+    async fn put_collaborator(
+        &self,
+        wishlist_id: String,
+        collaborator: Collaborator,
+    ) -> Result<(), AppError>;
+    async fn get_collaborator(
+        &self,
+        wishlist_id: String,
+        subject: String,
+    ) -> Result<Option<Collaborator>, AppError>;
+    /// A wishlist's collaborators, ordered by subject.
+    async fn list_collaborators(&self, wishlist_id: String) -> Result<Vec<Collaborator>, AppError>;
+    /// Fails with `AppError::NotFound` if `subject` isn't a collaborator.
+    async fn delete_collaborator(
+        &self,
+        wishlist_id: String,
+        subject: String,
+    ) -> Result<(), AppError>;
+
+    /// Stores a new share link. Fails with `AppError::NotFound` if its
+    /// wishlist is missing.
+    async fn create_share(&self, link: ShareLink) -> Result<(), AppError>;
+    async fn get_share(&self, id: String) -> Result<Option<ShareLink>, AppError>;
+    /// Every link to a wishlist, revoked and expired ones included, oldest
+    /// first.
+    async fn list_shares(&self, wishlist_id: String) -> Result<Vec<ShareLink>, AppError>;
+    /// Marks one of a wishlist's links revoked and returns it. Revoking a link
+    /// again keeps the original `revoked_at`.
+    async fn revoke_share(
+        &self,
+        wishlist_id: String,
+        id: String,
+        revoked_at: u64,
+    ) -> Result<ShareLink, AppError>;
+
+    /// Reserves some of an item for `reservation.reserver`, replacing their
+    /// earlier reservation of it but keeping its `reserved_at`, and returns
+    /// what was stored. Fails with `AppError::NotFound` if the wishlist or
+    /// item is missing, with `reservation::claimed` if the earlier
+    /// reservation has a different `claim_hash`, and with
+    /// `reservation::unavailable` if the item doesn't have that many units
+    /// left.
+    async fn reserve_item(
+        &self,
+        wishlist_id: String,
+        reservation: Reservation,
+    ) -> Result<Reservation, AppError>;
+    /// Every reservation on a wishlist, by item ID then reserver. May include
+    /// reservations of items since removed from the list.
+    async fn list_reservations(&self, wishlist_id: String) -> Result<Vec<Reservation>, AppError>;
+    /// Fails with `AppError::NotFound` if `reserver` has no reservation of
+    /// the item, and with `reservation::claimed` if it has a different
+    /// `claim_hash`.
+    async fn delete_reservation(
+        &self,
+        wishlist_id: String,
+        item_id: String,
+        reserver: String,
+        claim_hash: Option<String>,
+    ) -> Result<(), AppError>;
+
+    /// Records a pledge toward an item. Fails with `AppError::NotFound` if
+    /// the wishlist or item is missing, with `pledge::unpriced` if the item
+    /// has no `pledge::target`, and with `pledge::over_target` if the pledge
+    /// is more than the item still needs.
+    async fn add_pledge(&self, wishlist_id: String, pledge: Pledge) -> Result<(), AppError>;
+    /// An item's pledges, oldest first.
+    async fn list_pledges(
+        &self,
+        wishlist_id: String,
+        item_id: String,
+    ) -> Result<Vec<Pledge>, AppError>;
+
+    /// Stores a new gift exchange. Fails with `AppError::Conflict` if the ID
+    /// is taken.
+    async fn create_exchange(&self, exchange: Exchange) -> Result<(), AppError>;
+    /// Returns the exchange with every participant's `receiver` once drawn.
+    async fn get_exchange(&self, id: String) -> Result<Option<Exchange>, AppError>;
+    /// Stores a draw: the exchange's `participants`, each with its
+    /// `receiver`. Fails with `AppError::NotFound` for unknown IDs and with
+    /// `exchange::already_drawn` if the exchange has been drawn before.
+    async fn record_draw(
+        &self,
+        id: String,
+        participants: Vec<Participant>,
+        drawn_at: u64,
+    ) -> Result<(), AppError>;
+    /// Fails with `AppError::NotFound` for unknown IDs.
+    async fn delete_exchange(&self, id: String) -> Result<(), AppError>;
+
+    /// Stores a new API key. Fails with `AppError::Conflict` if the ID is
+    /// taken.
+    async fn create_api_key(&self, key: ApiKey) -> Result<(), AppError>;
+    async fn get_api_key(&self, id: String) -> Result<Option<ApiKey>, AppError>;
+    /// Every key belonging to `owner`, revoked ones included, oldest first.
+    async fn list_api_keys(&self, owner: String) -> Result<Vec<ApiKey>, AppError>;
+    /// Marks one of `owner`'s keys revoked and returns it. Revoking a key
+    /// again keeps the original `revoked_at`.
+    async fn revoke_api_key(
+        &self,
+        owner: String,
+        id: String,
+        revoked_at: u64,
+    ) -> Result<ApiKey, AppError>;
+    /// Records that a key was used at `used_at`.
+    async fn touch_api_key(&self, id: String, used_at: u64) -> Result<(), AppError>;
+
+    /// Walks every page. Only meant for tests and maintenance tasks.
+    async fn scan_items(&self) -> Result<Vec<Wishlist>, AppError> {
+        let mut wishlists = Vec::new();
+        let mut start_key = None;
+        loop {
+            let page = self.scan_page(100, start_key).await?;
+            wishlists.extend(page.items);
+            match page.last_key {
+                Some(key) => start_key = Some(key),
+                None => return Ok(wishlists),
+            }
+        }
+    }
+}
+
+/// Builds the backend named by `STORAGE_BACKEND` (`dynamodb` when unset).
+/// Against DynamoDB Local (`DYNAMODB_ENDPOINT` set) the table is provisioned
+/// on startup.
+/// The `sqlite` backend reads its file path from `SQLITE_PATH`; `postgres`
+/// reads `DATABASE_URL` and an optional `POSTGRES_MAX_CONNECTIONS` (default 10).
+pub async fn get_store() -> Result<Arc<dyn WishlistStore>, AppError> {
+    let backend = std::env::var("STORAGE_BACKEND").unwrap_or_else(|_| "dynamodb".to_string());
+    match backend.as_str() {
+        "dynamodb" => {
+            let client = get_db_client().await;
+            if std::env::var("DYNAMODB_ENDPOINT").is_ok() {
+                dynamodb::create_table(&client).await?;
+            }
+            Ok(Arc::new(DynamoDbStore::new(client)))
+        }
+        "memory" => Ok(Arc::new(MemoryStore::new())),
+        #[cfg(feature = "sqlite")]
+        "sqlite" => {
+            let path =
+                std::env::var("SQLITE_PATH").unwrap_or_else(|_| "wishlists.sqlite".to_string());
+            Ok(Arc::new(SqliteStore::connect(&path).await?))
+        }
+        #[cfg(feature = "postgres")]
+        "postgres" => {
+            let url = std::env::var("DATABASE_URL")
+                .map_err(|_| AppError::Generic("DATABASE_URL must be set".to_string()))?;
+            let max_connections = std::env::var("POSTGRES_MAX_CONNECTIONS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10);
+            Ok(Arc::new(
+                PostgresStore::connect(&url, max_connections).await?,
+            ))
+        }
+        other => Err(AppError::Generic(format!(
+            "Unknown STORAGE_BACKEND: {}",
+            other
+        ))),
+    }
+}
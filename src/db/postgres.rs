@@ -0,0 +1,1017 @@
+use crate::api_key::ApiKey;
+use crate::db::{Page, WishlistStore};
+use crate::error::AppError;
+use crate::exchange::{self, Exchange, Participant};
+use crate::handlers::wishlist::{Collaborator, Wishlist, WishlistItem};
+use crate::pledge::{self, Pledge};
+use crate::reservation::{self, Reservation};
+use crate::share::ShareLink;
+use async_trait::async_trait;
+use sqlx::postgres::{PgConnection, PgPool, PgPoolOptions, PgRow};
+use sqlx::Row;
+use std::collections::HashMap;
+
+/// Backend for self-hosted deployments on PostgreSQL. Connections are pooled
+/// and the versioned migrations under `migrations/postgres` are applied on
+/// connect.
+#[derive(Clone, Debug)]
+pub struct PostgresStore {
+    pool: PgPool,
+}
+
+impl PostgresStore {
+    pub async fn connect(url: &str, max_connections: u32) -> Result<Self, AppError> {
+        let pool = PgPoolOptions::new()
+            .max_connections(max_connections)
+            .connect(url)
+            .await?;
+        Self::from_pool(pool).await
+    }
+
+    /// Wraps an existing pool, applying any pending migrations first.
+    pub async fn from_pool(pool: PgPool) -> Result<Self, AppError> {
+        sqlx::migrate!("migrations/postgres").run(&pool).await?;
+        Ok(PostgresStore { pool })
+    }
+
+    /// Shared by `scan_page`, `query_by_owner` and `query_shared_with`; with
+    /// neither `owner` nor `shared_with` it lists everything.
+    async fn fetch_page(
+        &self,
+        owner: Option<&str>,
+        shared_with: Option<&str>,
+        limit: usize,
+        start_key: Option<String>,
+    ) -> Result<Page<Wishlist>, AppError> {
+        // Fetch one extra row to learn whether another page follows.
+        let fetch = limit as i64 + 1;
+        // Both queries read one snapshot, so a list written between them
+        // can't show up with another version's items.
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+            .execute(&mut *tx)
+            .await?;
+        let rows = sqlx::query(
+            "SELECT id, name, owner, version FROM wishlists \
+             WHERE ($1::TEXT IS NULL OR id > $1) AND ($3::TEXT IS NULL OR owner = $3) \
+             AND ($4::TEXT IS NULL OR id IN (SELECT wishlist_id FROM wishlist_collaborators WHERE subject = $4)) ORDER BY id LIMIT $2",
+        )
+        .bind(&start_key)
+        .bind(fetch)
+        .bind(owner)
+        .bind(shared_with)
+        .fetch_all(&mut *tx)
+        .await?;
+        let item_rows = sqlx::query(
+            "SELECT i.* FROM wishlist_items i \
+             JOIN (SELECT id FROM wishlists WHERE ($1::TEXT IS NULL OR id > $1) AND ($3::TEXT IS NULL OR owner = $3) \
+             AND ($4::TEXT IS NULL OR id IN (SELECT wishlist_id FROM wishlist_collaborators WHERE subject = $4)) \
+             ORDER BY id LIMIT $2) w \
+             ON w.id = i.wishlist_id ORDER BY i.wishlist_id, i.position",
+        )
+        .bind(&start_key)
+        .bind(fetch)
+        .bind(owner)
+        .bind(shared_with)
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        let mut items: HashMap<String, Vec<WishlistItem>> = HashMap::new();
+        for row in item_rows {
+            items
+                .entry(row.try_get("wishlist_id")?)
+                .or_default()
+                .push(item_from_row(&row)?);
+        }
+
+        let mut wishlists = rows
+            .into_iter()
+            .map(|row| {
+                let id: String = row.try_get("id")?;
+                Ok(Wishlist {
+                    items: items.remove(&id).unwrap_or_default(),
+                    name: row.try_get("name")?,
+                    owner: row.try_get("owner")?,
+                    version: row.try_get::<i64, _>("version")? as u64,
+                    id,
+                })
+            })
+            .collect::<Result<Vec<_>, AppError>>()?;
+        let last_key = if wishlists.len() > limit {
+            wishlists.truncate(limit);
+            wishlists.last().map(|w| w.id.clone())
+        } else {
+            None
+        };
+        Ok(Page {
+            items: wishlists,
+            last_key,
+        })
+    }
+}
+
+#[async_trait]
+impl WishlistStore for PostgresStore {
+    async fn get_item(&self, id: String) -> Result<Option<Wishlist>, AppError> {
+        let mut conn = self.pool.acquire().await?;
+        fetch_wishlist(&mut conn, &id).await
+    }
+
+    async fn create_item(&self, wishlist: Wishlist) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            "INSERT INTO wishlists (id, name, owner, version) VALUES ($1, $2, $3, $4) \
+             ON CONFLICT (id) DO NOTHING",
+        )
+        .bind(&wishlist.id)
+        .bind(&wishlist.name)
+        .bind(&wishlist.owner)
+        .bind(wishlist.version as i64)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::Conflict(format!(
+                "Wishlist '{}' already exists",
+                wishlist.id
+            )));
+        }
+        replace_items(&mut tx, &wishlist.id, &wishlist.items).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn update_item(
+        &self,
+        mut wishlist: Wishlist,
+        expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let version: Option<i64> = sqlx::query_scalar(
+            "UPDATE wishlists SET name = $1, owner = $2, version = version + 1 \
+             WHERE id = $3 AND ($4::BIGINT IS NULL OR version = $4) RETURNING version",
+        )
+        .bind(&wishlist.name)
+        .bind(&wishlist.owner)
+        .bind(&wishlist.id)
+        .bind(expected_version.map(|v| v as i64))
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(version) = version else {
+            return Err(missing_or_stale(&mut tx, &wishlist.id).await?);
+        };
+        wishlist.version = version as u64;
+        replace_items(&mut tx, &wishlist.id, &wishlist.items).await?;
+        tx.commit().await?;
+        Ok(wishlist)
+    }
+
+    async fn delete_item(&self, id: String, expected_version: Option<u64>) -> Result<(), AppError> {
+        let mut conn = self.pool.acquire().await?;
+        let result = sqlx::query(
+            "DELETE FROM wishlists WHERE id = $1 AND ($2::BIGINT IS NULL OR version = $2)",
+        )
+        .bind(&id)
+        .bind(expected_version.map(|v| v as i64))
+        .execute(&mut *conn)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(missing_or_stale(&mut conn, &id).await?);
+        }
+        Ok(())
+    }
+
+    async fn add_wishlist_item(
+        &self,
+        wishlist_id: String,
+        item: WishlistItem,
+        expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError> {
+        let mut tx = self.pool.begin().await?;
+        bump_version(&mut tx, &wishlist_id, expected_version).await?;
+        let position: i32 = sqlx::query_scalar(
+            "SELECT COALESCE(MAX(position) + 1, 0) FROM wishlist_items WHERE wishlist_id = $1",
+        )
+        .bind(&wishlist_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        insert_item(&mut tx, &wishlist_id, position as usize, &item).await?;
+        let wishlist = fetch_wishlist(&mut tx, &wishlist_id)
+            .await?
+            .ok_or(AppError::NotFound)?;
+        tx.commit().await?;
+        Ok(wishlist)
+    }
+
+    async fn update_wishlist_item(
+        &self,
+        wishlist_id: String,
+        item_id: String,
+        patch: serde_json::Value,
+        expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError> {
+        let mut tx = self.pool.begin().await?;
+        bump_version(&mut tx, &wishlist_id, expected_version).await?;
+        let row =
+            sqlx::query("SELECT * FROM wishlist_items WHERE wishlist_id = $1 AND item_id = $2")
+                .bind(&wishlist_id)
+                .bind(&item_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or(AppError::NotFound)?;
+        let item = item_from_row(&row)?.patched(&patch)?;
+        sqlx::query(
+            "UPDATE wishlist_items SET name = $3, url = $4, price_cents = $5, currency = $6, \
+             quantity = $7, priority = $8, notes = $9 WHERE wishlist_id = $1 AND item_id = $2",
+        )
+        .bind(&wishlist_id)
+        .bind(&item_id)
+        .bind(&item.name)
+        .bind(&item.url)
+        .bind(item.price_cents.map(|p| p as i64))
+        .bind(&item.currency)
+        .bind(item.quantity as i32)
+        .bind(item.priority.map(|p| p.as_str()))
+        .bind(&item.notes)
+        .execute(&mut *tx)
+        .await?;
+        let wishlist = fetch_wishlist(&mut tx, &wishlist_id)
+            .await?
+            .ok_or(AppError::NotFound)?;
+        tx.commit().await?;
+        Ok(wishlist)
+    }
+
+    async fn delete_wishlist_item(
+        &self,
+        wishlist_id: String,
+        item_id: String,
+        expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError> {
+        let mut tx = self.pool.begin().await?;
+        bump_version(&mut tx, &wishlist_id, expected_version).await?;
+        let result =
+            sqlx::query("DELETE FROM wishlist_items WHERE wishlist_id = $1 AND item_id = $2")
+                .bind(&wishlist_id)
+                .bind(&item_id)
+                .execute(&mut *tx)
+                .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound);
+        }
+        let wishlist = fetch_wishlist(&mut tx, &wishlist_id)
+            .await?
+            .ok_or(AppError::NotFound)?;
+        tx.commit().await?;
+        Ok(wishlist)
+    }
+
+    async fn scan_page(
+        &self,
+        limit: usize,
+        start_key: Option<String>,
+    ) -> Result<Page<Wishlist>, AppError> {
+        self.fetch_page(None, None, limit, start_key).await
+    }
+
+    async fn query_by_owner(
+        &self,
+        owner: String,
+        limit: usize,
+        start_key: Option<String>,
+    ) -> Result<Page<Wishlist>, AppError> {
+        self.fetch_page(Some(&owner), None, limit, start_key).await
+    }
+
+    async fn query_shared_with(
+        &self,
+        subject: String,
+        limit: usize,
+        start_key: Option<String>,
+    ) -> Result<Page<Wishlist>, AppError> {
+        self.fetch_page(None, Some(&subject), limit, start_key)
+            .await
+    }
+
+    async fn put_collaborator(
+        &self,
+        wishlist_id: String,
+        collaborator: Collaborator,
+    ) -> Result<(), AppError> {
+        let result = sqlx::query(
+            "INSERT INTO wishlist_collaborators (wishlist_id, subject, role) \
+             SELECT $1, $2, $3 WHERE EXISTS (SELECT 1 FROM wishlists WHERE id = $1) \
+             ON CONFLICT (wishlist_id, subject) DO UPDATE SET role = excluded.role",
+        )
+        .bind(&wishlist_id)
+        .bind(&collaborator.subject)
+        .bind(collaborator.role.as_str())
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn get_collaborator(
+        &self,
+        wishlist_id: String,
+        subject: String,
+    ) -> Result<Option<Collaborator>, AppError> {
+        sqlx::query(
+            "SELECT subject, role FROM wishlist_collaborators \
+             WHERE wishlist_id = $1 AND subject = $2",
+        )
+        .bind(&wishlist_id)
+        .bind(&subject)
+        .fetch_optional(&self.pool)
+        .await?
+        .as_ref()
+        .map(collaborator_from_row)
+        .transpose()
+    }
+
+    async fn list_collaborators(&self, wishlist_id: String) -> Result<Vec<Collaborator>, AppError> {
+        sqlx::query(
+            "SELECT subject, role FROM wishlist_collaborators \
+             WHERE wishlist_id = $1 ORDER BY subject",
+        )
+        .bind(&wishlist_id)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(collaborator_from_row)
+        .collect()
+    }
+
+    async fn delete_collaborator(
+        &self,
+        wishlist_id: String,
+        subject: String,
+    ) -> Result<(), AppError> {
+        let result = sqlx::query(
+            "DELETE FROM wishlist_collaborators WHERE wishlist_id = $1 AND subject = $2",
+        )
+        .bind(&wishlist_id)
+        .bind(&subject)
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn create_share(&self, link: ShareLink) -> Result<(), AppError> {
+        let result = sqlx::query(
+            "INSERT INTO wishlist_shares \
+             (id, wishlist_id, created_by, created_at, expires_at, password_hash, revoked_at) \
+             SELECT $1, $2, $3, $4, $5, $6, $7 \
+             WHERE EXISTS (SELECT 1 FROM wishlists WHERE id = $2)",
+        )
+        .bind(&link.id)
+        .bind(&link.wishlist_id)
+        .bind(&link.created_by)
+        .bind(link.created_at as i64)
+        .bind(link.expires_at.map(|t| t as i64))
+        .bind(&link.password_hash)
+        .bind(link.revoked_at.map(|t| t as i64))
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn get_share(&self, id: String) -> Result<Option<ShareLink>, AppError> {
+        sqlx::query("SELECT * FROM wishlist_shares WHERE id = $1")
+            .bind(&id)
+            .fetch_optional(&self.pool)
+            .await?
+            .as_ref()
+            .map(share_from_row)
+            .transpose()
+    }
+
+    async fn list_shares(&self, wishlist_id: String) -> Result<Vec<ShareLink>, AppError> {
+        sqlx::query("SELECT * FROM wishlist_shares WHERE wishlist_id = $1 ORDER BY created_at, id")
+            .bind(&wishlist_id)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(share_from_row)
+            .collect()
+    }
+
+    async fn revoke_share(
+        &self,
+        wishlist_id: String,
+        id: String,
+        revoked_at: u64,
+    ) -> Result<ShareLink, AppError> {
+        let row = sqlx::query(
+            "UPDATE wishlist_shares SET revoked_at = COALESCE(revoked_at, $3) \
+             WHERE id = $1 AND wishlist_id = $2 RETURNING *",
+        )
+        .bind(&id)
+        .bind(&wishlist_id)
+        .bind(revoked_at as i64)
+        .fetch_optional(&self.pool)
+        .await?;
+        match row {
+            Some(row) => share_from_row(&row),
+            None => Err(AppError::NotFound),
+        }
+    }
+
+    async fn reserve_item(
+        &self,
+        wishlist_id: String,
+        reservation: Reservation,
+    ) -> Result<Reservation, AppError> {
+        let mut tx = self.pool.begin().await?;
+        // Reservations of one wishlist queue behind each other here, so the
+        // sum below can't miss one being written concurrently.
+        sqlx::query("SELECT 1 FROM wishlists WHERE id = $1 FOR UPDATE")
+            .bind(&wishlist_id)
+            .execute(&mut *tx)
+            .await?;
+        let row = sqlx::query(
+            "INSERT INTO wishlist_reservations \
+             (wishlist_id, item_id, reserver, quantity, purchased, reserved_at, claim_hash) \
+             SELECT $1, $2, $3, $4, $5, $6, $7 FROM wishlist_items i \
+             WHERE i.wishlist_id = $1 AND i.item_id = $2 AND i.quantity - ( \
+                 SELECT COALESCE(SUM(r.quantity), 0) FROM wishlist_reservations r \
+                 WHERE r.wishlist_id = $1 AND r.item_id = $2 AND r.reserver <> $3 \
+             ) >= $4 \
+             ON CONFLICT (wishlist_id, item_id, reserver) DO UPDATE \
+             SET quantity = excluded.quantity, purchased = excluded.purchased \
+             WHERE wishlist_reservations.claim_hash IS NOT DISTINCT FROM excluded.claim_hash \
+             RETURNING *",
+        )
+        .bind(&wishlist_id)
+        .bind(&reservation.item_id)
+        .bind(&reservation.reserver)
+        .bind(reservation.quantity as i32)
+        .bind(reservation.purchased)
+        .bind(reservation.reserved_at as i64)
+        .bind(&reservation.claim_hash)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(row) = row {
+            let stored = reservation_from_row(&row)?;
+            tx.commit().await?;
+            return Ok(stored);
+        }
+        if claim_of(
+            &mut tx,
+            &wishlist_id,
+            &reservation.item_id,
+            &reservation.reserver,
+        )
+        .await?
+        .is_some_and(|claim_hash| claim_hash != reservation.claim_hash)
+        {
+            return Err(reservation::claimed());
+        }
+        let available: Option<i64> = sqlx::query_scalar(
+            "SELECT CAST(i.quantity AS BIGINT) - ( \
+                 SELECT CAST(COALESCE(SUM(r.quantity), 0) AS BIGINT) FROM wishlist_reservations r \
+                 WHERE r.wishlist_id = $1 AND r.item_id = $2 AND r.reserver <> $3 \
+             ) FROM wishlist_items i WHERE i.wishlist_id = $1 AND i.item_id = $2",
+        )
+        .bind(&wishlist_id)
+        .bind(&reservation.item_id)
+        .bind(&reservation.reserver)
+        .fetch_optional(&mut *tx)
+        .await?;
+        match available {
+            Some(available) => Err(reservation::unavailable(available.max(0) as u32)),
+            None => Err(AppError::NotFound),
+        }
+    }
+
+    async fn list_reservations(&self, wishlist_id: String) -> Result<Vec<Reservation>, AppError> {
+        sqlx::query(
+            "SELECT * FROM wishlist_reservations WHERE wishlist_id = $1 \
+             ORDER BY item_id, reserver",
+        )
+        .bind(&wishlist_id)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(reservation_from_row)
+        .collect()
+    }
+
+    async fn delete_reservation(
+        &self,
+        wishlist_id: String,
+        item_id: String,
+        reserver: String,
+        claim_hash: Option<String>,
+    ) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            "DELETE FROM wishlist_reservations \
+             WHERE wishlist_id = $1 AND item_id = $2 AND reserver = $3 \
+             AND claim_hash IS NOT DISTINCT FROM $4",
+        )
+        .bind(&wishlist_id)
+        .bind(&item_id)
+        .bind(&reserver)
+        .bind(&claim_hash)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return match claim_of(&mut tx, &wishlist_id, &item_id, &reserver).await? {
+                Some(_) => Err(reservation::claimed()),
+                None => Err(AppError::NotFound),
+            };
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn add_pledge(&self, wishlist_id: String, pledge: Pledge) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+        // Pledges toward one wishlist queue behind each other here, so the
+        // sum below can't miss one being written concurrently.
+        sqlx::query("SELECT 1 FROM wishlists WHERE id = $1 FOR UPDATE")
+            .bind(&wishlist_id)
+            .execute(&mut *tx)
+            .await?;
+        let result = sqlx::query(
+            "INSERT INTO wishlist_pledges \
+             (id, wishlist_id, item_id, contributor, amount_cents, currency, message, pledged_at) \
+             SELECT $1, $2, $3, $4, $5, $6, $7, $8 FROM wishlist_items i \
+             WHERE i.wishlist_id = $2 AND i.item_id = $3 AND i.currency IS NOT NULL \
+             AND i.price_cents * i.quantity - ( \
+                 SELECT COALESCE(SUM(p.amount_cents), 0) FROM wishlist_pledges p \
+                 WHERE p.wishlist_id = $2 AND p.item_id = $3 \
+             ) >= $5",
+        )
+        .bind(&pledge.id)
+        .bind(&wishlist_id)
+        .bind(&pledge.item_id)
+        .bind(&pledge.contributor)
+        .bind(pledge.amount_cents as i64)
+        .bind(&pledge.currency)
+        .bind(&pledge.message)
+        .bind(pledge.pledged_at as i64)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() > 0 {
+            tx.commit().await?;
+            return Ok(());
+        }
+        let remaining: Option<Option<i64>> = sqlx::query_scalar(
+            "SELECT CASE WHEN i.currency IS NOT NULL \
+             THEN CAST(i.price_cents * i.quantity - ( \
+                 SELECT COALESCE(SUM(p.amount_cents), 0) FROM wishlist_pledges p \
+                 WHERE p.wishlist_id = $1 AND p.item_id = $2 \
+             ) AS BIGINT) END FROM wishlist_items i WHERE i.wishlist_id = $1 AND i.item_id = $2",
+        )
+        .bind(&wishlist_id)
+        .bind(&pledge.item_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        match remaining {
+            Some(Some(remaining)) => Err(pledge::over_target(remaining.max(0) as u64)),
+            Some(None) => Err(pledge::unpriced()),
+            None => Err(AppError::NotFound),
+        }
+    }
+
+    async fn list_pledges(
+        &self,
+        wishlist_id: String,
+        item_id: String,
+    ) -> Result<Vec<Pledge>, AppError> {
+        sqlx::query(
+            "SELECT * FROM wishlist_pledges WHERE wishlist_id = $1 AND item_id = $2 \
+             ORDER BY pledged_at, id",
+        )
+        .bind(&wishlist_id)
+        .bind(&item_id)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(pledge_from_row)
+        .collect()
+    }
+
+    async fn create_exchange(&self, exchange: Exchange) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            "INSERT INTO exchanges \
+             (id, name, organizer, previous_exchange_id, created_at, drawn_at) \
+             VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT (id) DO NOTHING",
+        )
+        .bind(&exchange.id)
+        .bind(&exchange.name)
+        .bind(&exchange.organizer)
+        .bind(&exchange.previous_exchange_id)
+        .bind(exchange.created_at as i64)
+        .bind(exchange.drawn_at.map(|t| t as i64))
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::Conflict(format!(
+                "Exchange '{}' already exists",
+                exchange.id
+            )));
+        }
+        for (position, participant) in exchange.participants.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO exchange_participants \
+                 (exchange_id, position, subject, wishlist_id, spouse, receiver) \
+                 VALUES ($1, $2, $3, $4, $5, $6)",
+            )
+            .bind(&exchange.id)
+            .bind(position as i32)
+            .bind(&participant.subject)
+            .bind(&participant.wishlist_id)
+            .bind(&participant.spouse)
+            .bind(&participant.receiver)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_exchange(&self, id: String) -> Result<Option<Exchange>, AppError> {
+        let row = sqlx::query("SELECT * FROM exchanges WHERE id = $1")
+            .bind(&id)
+            .fetch_optional(&self.pool)
+            .await?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let participants = sqlx::query(
+            "SELECT * FROM exchange_participants WHERE exchange_id = $1 ORDER BY position",
+        )
+        .bind(&id)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(participant_from_row)
+        .collect::<Result<Vec<_>, _>>()?;
+        Ok(Some(Exchange {
+            id: row.try_get("id")?,
+            name: row.try_get("name")?,
+            organizer: row.try_get("organizer")?,
+            participants,
+            previous_exchange_id: row.try_get("previous_exchange_id")?,
+            created_at: row.try_get::<i64, _>("created_at")? as u64,
+            drawn_at: row.try_get::<Option<i64>, _>("drawn_at")?.map(|t| t as u64),
+        }))
+    }
+
+    async fn record_draw(
+        &self,
+        id: String,
+        participants: Vec<Participant>,
+        drawn_at: u64,
+    ) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+        let result =
+            sqlx::query("UPDATE exchanges SET drawn_at = $2 WHERE id = $1 AND drawn_at IS NULL")
+                .bind(&id)
+                .bind(drawn_at as i64)
+                .execute(&mut *tx)
+                .await?;
+        if result.rows_affected() == 0 {
+            let exists: Option<i32> = sqlx::query_scalar("SELECT 1 FROM exchanges WHERE id = $1")
+                .bind(&id)
+                .fetch_optional(&mut *tx)
+                .await?;
+            return Err(match exists {
+                Some(_) => exchange::already_drawn(),
+                None => AppError::NotFound,
+            });
+        }
+        for participant in &participants {
+            sqlx::query(
+                "UPDATE exchange_participants SET receiver = $3 \
+                 WHERE exchange_id = $1 AND subject = $2",
+            )
+            .bind(&id)
+            .bind(&participant.subject)
+            .bind(&participant.receiver)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn delete_exchange(&self, id: String) -> Result<(), AppError> {
+        let result = sqlx::query("DELETE FROM exchanges WHERE id = $1")
+            .bind(&id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn create_api_key(&self, key: ApiKey) -> Result<(), AppError> {
+        let result = sqlx::query(
+            "INSERT INTO api_keys \
+             (id, owner, name, scope, secret_hash, created_at, last_used_at, revoked_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT (id) DO NOTHING",
+        )
+        .bind(&key.id)
+        .bind(&key.owner)
+        .bind(&key.name)
+        .bind(key.scope.as_str())
+        .bind(&key.secret_hash)
+        .bind(key.created_at as i64)
+        .bind(key.last_used_at.map(|t| t as i64))
+        .bind(key.revoked_at.map(|t| t as i64))
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::Conflict(format!(
+                "API key '{}' already exists",
+                key.id
+            )));
+        }
+        Ok(())
+    }
+
+    async fn get_api_key(&self, id: String) -> Result<Option<ApiKey>, AppError> {
+        sqlx::query("SELECT * FROM api_keys WHERE id = $1")
+            .bind(&id)
+            .fetch_optional(&self.pool)
+            .await?
+            .as_ref()
+            .map(api_key_from_row)
+            .transpose()
+    }
+
+    async fn list_api_keys(&self, owner: String) -> Result<Vec<ApiKey>, AppError> {
+        sqlx::query("SELECT * FROM api_keys WHERE owner = $1 ORDER BY created_at, id")
+            .bind(&owner)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(api_key_from_row)
+            .collect()
+    }
+
+    async fn revoke_api_key(
+        &self,
+        owner: String,
+        id: String,
+        revoked_at: u64,
+    ) -> Result<ApiKey, AppError> {
+        let row = sqlx::query(
+            "UPDATE api_keys SET revoked_at = COALESCE(revoked_at, $3) \
+             WHERE id = $1 AND owner = $2 RETURNING *",
+        )
+        .bind(&id)
+        .bind(&owner)
+        .bind(revoked_at as i64)
+        .fetch_optional(&self.pool)
+        .await?;
+        match row {
+            Some(row) => api_key_from_row(&row),
+            None => Err(AppError::NotFound),
+        }
+    }
+
+    async fn touch_api_key(&self, id: String, used_at: u64) -> Result<(), AppError> {
+        sqlx::query("UPDATE api_keys SET last_used_at = $2 WHERE id = $1")
+            .bind(&id)
+            .bind(used_at as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+async fn fetch_wishlist(conn: &mut PgConnection, id: &str) -> Result<Option<Wishlist>, AppError> {
+    let row = sqlx::query("SELECT id, name, owner, version FROM wishlists WHERE id = $1")
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let items =
+        sqlx::query("SELECT * FROM wishlist_items WHERE wishlist_id = $1 ORDER BY position")
+            .bind(id)
+            .fetch_all(&mut *conn)
+            .await?
+            .iter()
+            .map(item_from_row)
+            .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Some(Wishlist {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        owner: row.try_get("owner")?,
+        items,
+        version: row.try_get::<i64, _>("version")? as u64,
+    }))
+}
+
+/// Bumps a wishlist's version ahead of an item edit. This also takes the
+/// write lock on the wishlist, so item edits to one list are serialized.
+async fn bump_version(
+    conn: &mut PgConnection,
+    id: &str,
+    expected_version: Option<u64>,
+) -> Result<(), AppError> {
+    let version: Option<i64> = sqlx::query_scalar(
+        "UPDATE wishlists SET version = version + 1 \
+         WHERE id = $1 AND ($2::BIGINT IS NULL OR version = $2) RETURNING version",
+    )
+    .bind(id)
+    .bind(expected_version.map(|v| v as i64))
+    .fetch_optional(&mut *conn)
+    .await?;
+    match version {
+        Some(_) => Ok(()),
+        None => Err(missing_or_stale(conn, id).await?),
+    }
+}
+
+async fn insert_item(
+    conn: &mut PgConnection,
+    wishlist_id: &str,
+    position: usize,
+    item: &WishlistItem,
+) -> Result<(), AppError> {
+    sqlx::query(
+        "INSERT INTO wishlist_items \
+         (wishlist_id, position, item_id, name, url, price_cents, currency, quantity, priority, notes) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+    )
+    .bind(wishlist_id)
+    .bind(position as i32)
+    .bind(&item.id)
+    .bind(&item.name)
+    .bind(&item.url)
+    .bind(item.price_cents.map(|p| p as i64))
+    .bind(&item.currency)
+    .bind(item.quantity as i32)
+    .bind(item.priority.map(|p| p.as_str()))
+    .bind(&item.notes)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Rewrites the item rows of a wishlist inside the caller's transaction.
+async fn replace_items(
+    conn: &mut PgConnection,
+    wishlist_id: &str,
+    items: &[WishlistItem],
+) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM wishlist_items WHERE wishlist_id = $1")
+        .bind(wishlist_id)
+        .execute(&mut *conn)
+        .await?;
+    for (position, item) in items.iter().enumerate() {
+        insert_item(&mut *conn, wishlist_id, position, item).await?;
+    }
+    Ok(())
+}
+
+/// Explains why a conditional write touched no rows.
+async fn missing_or_stale(conn: &mut PgConnection, id: &str) -> Result<AppError, AppError> {
+    let exists: Option<i32> = sqlx::query_scalar("SELECT 1 FROM wishlists WHERE id = $1")
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    Ok(match exists {
+        Some(_) => AppError::PreconditionFailed,
+        None => AppError::NotFound,
+    })
+}
+
+/// The `claim_hash` of `reserver`'s reservation of an item: `None` if there
+/// is no reservation, `Some(None)` if it has no claim token.
+async fn claim_of(
+    conn: &mut PgConnection,
+    wishlist_id: &str,
+    item_id: &str,
+    reserver: &str,
+) -> Result<Option<Option<String>>, AppError> {
+    Ok(sqlx::query_scalar(
+        "SELECT claim_hash FROM wishlist_reservations \
+         WHERE wishlist_id = $1 AND item_id = $2 AND reserver = $3",
+    )
+    .bind(wishlist_id)
+    .bind(item_id)
+    .bind(reserver)
+    .fetch_optional(&mut *conn)
+    .await?)
+}
+
+/// Reads one `wishlist_items` row; the query must select all of its columns.
+fn item_from_row(row: &PgRow) -> Result<WishlistItem, AppError> {
+    Ok(WishlistItem {
+        id: row.try_get("item_id")?,
+        name: row.try_get("name")?,
+        url: row.try_get("url")?,
+        price_cents: row
+            .try_get::<Option<i64>, _>("price_cents")?
+            .map(|p| p as u64),
+        currency: row.try_get("currency")?,
+        quantity: row.try_get::<i32, _>("quantity")? as u32,
+        priority: row
+            .try_get::<Option<String>, _>("priority")?
+            .map(|p| p.parse())
+            .transpose()?,
+        notes: row.try_get("notes")?,
+    })
+}
+
+fn collaborator_from_row(row: &PgRow) -> Result<Collaborator, AppError> {
+    Ok(Collaborator {
+        subject: row.try_get("subject")?,
+        role: row.try_get::<String, _>("role")?.parse()?,
+    })
+}
+
+/// Reads one `wishlist_shares` row; the query must select all of its
+/// columns.
+fn share_from_row(row: &PgRow) -> Result<ShareLink, AppError> {
+    Ok(ShareLink {
+        id: row.try_get("id")?,
+        wishlist_id: row.try_get("wishlist_id")?,
+        created_by: row.try_get("created_by")?,
+        created_at: row.try_get::<i64, _>("created_at")? as u64,
+        expires_at: row
+            .try_get::<Option<i64>, _>("expires_at")?
+            .map(|t| t as u64),
+        password_hash: row.try_get("password_hash")?,
+        revoked_at: row
+            .try_get::<Option<i64>, _>("revoked_at")?
+            .map(|t| t as u64),
+    })
+}
+
+/// Reads one `wishlist_reservations` row; the query must select all of its
+/// columns.
+fn reservation_from_row(row: &PgRow) -> Result<Reservation, AppError> {
+    Ok(Reservation {
+        item_id: row.try_get("item_id")?,
+        reserver: row.try_get("reserver")?,
+        quantity: row.try_get::<i32, _>("quantity")? as u32,
+        purchased: row.try_get("purchased")?,
+        reserved_at: row.try_get::<i64, _>("reserved_at")? as u64,
+        claim_hash: row.try_get("claim_hash")?,
+    })
+}
+
+/// Reads one `wishlist_pledges` row; the query must select all of its
+/// columns.
+fn pledge_from_row(row: &PgRow) -> Result<Pledge, AppError> {
+    Ok(Pledge {
+        id: row.try_get("id")?,
+        item_id: row.try_get("item_id")?,
+        contributor: row.try_get("contributor")?,
+        amount_cents: row.try_get::<i64, _>("amount_cents")? as u64,
+        currency: row.try_get("currency")?,
+        message: row.try_get("message")?,
+        pledged_at: row.try_get::<i64, _>("pledged_at")? as u64,
+    })
+}
+
+/// Reads one `exchange_participants` row; the query must select all of its
+/// columns.
+fn participant_from_row(row: &PgRow) -> Result<Participant, AppError> {
+    Ok(Participant {
+        subject: row.try_get("subject")?,
+        wishlist_id: row.try_get("wishlist_id")?,
+        spouse: row.try_get("spouse")?,
+        receiver: row.try_get("receiver")?,
+    })
+}
+
+/// Reads one `api_keys` row; the query must select all of its columns.
+fn api_key_from_row(row: &PgRow) -> Result<ApiKey, AppError> {
+    Ok(ApiKey {
+        id: row.try_get("id")?,
+        owner: row.try_get("owner")?,
+        name: row.try_get("name")?,
+        scope: row.try_get::<String, _>("scope")?.parse()?,
+        secret_hash: row.try_get("secret_hash")?,
+        created_at: row.try_get::<i64, _>("created_at")? as u64,
+        last_used_at: row
+            .try_get::<Option<i64>, _>("last_used_at")?
+            .map(|t| t as u64),
+        revoked_at: row
+            .try_get::<Option<i64>, _>("revoked_at")?
+            .map(|t| t as u64),
+    })
+}
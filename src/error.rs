@@ -1,23 +1,96 @@
-use aws_smithy_runtime_api;
+use aws_sdk_dynamodb::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
 use lambda_http::http::StatusCode;
 use lambda_http::Body;
 use lambda_http::Response;
 use thiserror::Error;
 
+use crate::problem::Problem;
+use crate::validation::FieldError;
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("DynamoDB error: {0}")]
     DynamoDb(String),
+    #[error("Database error: {0}")]
+    Database(String),
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
     #[error("HTTP error: {0}")]
     Http(#[from] lambda_http::http::Error),
     #[error("Missing ID in request")]
     MissingId,
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+    #[error("Precondition failed: the wishlist has been modified")]
+    PreconditionFailed,
+    #[error("Not found")]
+    NotFound,
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error("Validation failed: {}", describe(.0))]
+    Validation(Vec<FieldError>),
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+    #[error("Throttled: {0}")]
+    Throttled(String),
+    #[error("Service unavailable: {0}")]
+    Unavailable(String),
     #[error("Generic error: {0}")]
     Generic(String),
 }
 
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::DynamoDb(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::Serialization(_) => StatusCode::BAD_REQUEST,
+            AppError::Http(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::MissingId => StatusCode::BAD_REQUEST,
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+            AppError::NotFound => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::Throttled(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Generic(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Machine-readable code sent as `code` in problem responses. These are
+    /// part of the API and must not change.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::DynamoDb(_) | AppError::Database(_) => "storage_error",
+            AppError::Serialization(_) => "invalid_json",
+            AppError::Http(_) | AppError::Generic(_) => "internal_error",
+            AppError::MissingId => "missing_id",
+            AppError::BadRequest(_) => "bad_request",
+            AppError::PreconditionFailed => "precondition_failed",
+            AppError::NotFound => "not_found",
+            AppError::Conflict(_) => "conflict",
+            AppError::Validation(_) => "validation_failed",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::Throttled(_) => "throttled",
+            AppError::Unavailable(_) => "service_unavailable",
+        }
+    }
+}
+
+fn describe(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|e| format!("{} {}", e.field, e.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 impl From<String> for AppError {
     fn from(s: String) -> Self {
         AppError::Generic(s)
@@ -32,17 +105,91 @@ impl From<&str> for AppError {
 
 impl From<aws_sdk_dynamodb::Error> for AppError {
     fn from(err: aws_sdk_dynamodb::Error) -> Self {
+        let description = DisplayErrorContext(&err).to_string();
+        classify_dynamodb_error(err.code(), err.message(), description)
+    }
+}
+
+impl From<aws_sdk_dynamodb::error::BuildError> for AppError {
+    fn from(err: aws_sdk_dynamodb::error::BuildError) -> Self {
         AppError::DynamoDb(err.to_string())
     }
 }
 
-impl<E, R> From<aws_smithy_runtime_api::client::result::SdkError<E, R>> for AppError
+impl<E, R> From<SdkError<E, R>> for AppError
 where
-    E: std::error::Error + Send + Sync + 'static,
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
     R: std::fmt::Debug + 'static, // Add R as a generic parameter
 {
-    fn from(err: aws_smithy_runtime_api::client::result::SdkError<E, R>) -> Self {
-        AppError::DynamoDb(err.to_string())
+    fn from(err: SdkError<E, R>) -> Self {
+        let description = DisplayErrorContext(&err).to_string();
+        match &err {
+            // The request never got a usable answer: the connection failed,
+            // it timed out, or the response couldn't be read.
+            SdkError::TimeoutError(_)
+            | SdkError::DispatchFailure(_)
+            | SdkError::ResponseError(_) => AppError::Unavailable(description),
+            SdkError::ServiceError(e) => {
+                classify_dynamodb_error(e.err().code(), e.err().message(), description)
+            }
+            _ => AppError::DynamoDb(description),
+        }
+    }
+}
+
+/// DynamoDB error codes for requests rejected for exceeding capacity or rate
+/// limits. These are worth retrying after a pause.
+const THROTTLING_ERROR_CODES: &[&str] = &[
+    "ProvisionedThroughputExceededException",
+    "ThrottlingException",
+    "RequestLimitExceeded",
+];
+
+/// DynamoDB error codes for failures on the service side that may succeed on
+/// another attempt.
+const TRANSIENT_ERROR_CODES: &[&str] = &["InternalServerError", "ServiceUnavailable"];
+
+/// Sorts a DynamoDB service error by what the client can do about it.
+fn classify_dynamodb_error(
+    code: Option<&str>,
+    message: Option<&str>,
+    description: String,
+) -> AppError {
+    match code {
+        Some(code) if THROTTLING_ERROR_CODES.contains(&code) => AppError::Throttled(description),
+        Some(code) if TRANSIENT_ERROR_CODES.contains(&code) => AppError::Unavailable(description),
+        // Validation limits are meant to keep wishlists under the item size
+        // limit; if one slips through, it is still the request's fault.
+        Some("ValidationException")
+            if message.is_some_and(|m| m.contains("Item size has exceeded")) =>
+        {
+            AppError::Validation(vec![FieldError::new(
+                ".",
+                "max_size",
+                "wishlist is larger than the storage backend allows",
+            )])
+        }
+        _ => AppError::DynamoDb(description),
+    }
+}
+
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+impl From<sqlx::Error> for AppError {
+    fn from(err: sqlx::Error) -> Self {
+        AppError::Database(err.to_string())
+    }
+}
+
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+impl From<sqlx::migrate::MigrateError> for AppError {
+    fn from(err: sqlx::migrate::MigrateError) -> Self {
+        AppError::Database(format!("Migration failed: {}", err))
+    }
+}
+
+impl From<lambda_http::http::header::InvalidHeaderValue> for AppError {
+    fn from(err: lambda_http::http::header::InvalidHeaderValue) -> Self {
+        AppError::Http(err.into())
     }
 }
 
@@ -60,16 +207,8 @@ impl From<std::io::Error> for AppError {
 
 impl From<AppError> for Response<Body> {
     fn from(val: AppError) -> Self {
-        let status_code = match val {
-            AppError::DynamoDb(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            AppError::Serialization(_) => StatusCode::BAD_REQUEST,
-            AppError::Http(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            AppError::MissingId => StatusCode::BAD_REQUEST,
-            AppError::Generic(_) => StatusCode::INTERNAL_SERVER_ERROR,
-        };
-        Response::builder()
-            .status(status_code)
-            .body(Body::from(val.to_string()))
+        Problem::from_error(&val)
+            .into_response()
             .expect("Failed to build error response")
     }
 }
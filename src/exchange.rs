@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::error::AppError;
+use crate::share::SharedWishlist;
+
+/// One person in a gift exchange, with the wishlist their giver will see.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Participant {
+    pub subject: String,
+    /// One of the participant's own wishlists.
+    pub wishlist_id: String,
+    /// Another participant this one must neither give to nor get from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spouse: Option<String>,
+    /// Subject this participant gives to, once drawn. Never serialized, so
+    /// no response can reveal it; only `GET .../assignment` shows a
+    /// participant their own.
+    #[serde(skip)]
+    pub receiver: Option<String>,
+}
+
+/// A Secret Santa style gift exchange. Only its organizer can draw or
+/// delete it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Exchange {
+    pub id: String,
+    pub name: String,
+    pub organizer: String,
+    pub participants: Vec<Participant>,
+    /// Last year's exchange; nobody gives to the person they gave to then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_exchange_id: Option<String>,
+    /// Unix time in seconds.
+    pub created_at: u64,
+    /// Unix time in seconds of the draw, or `None` before it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drawn_at: Option<u64>,
+}
+
+impl Exchange {
+    pub fn participant(&self, subject: &str) -> Option<&Participant> {
+        self.participants
+            .iter()
+            .find(|participant| participant.subject == subject)
+    }
+
+    /// Whether `subject` may see the exchange: its organizer and its
+    /// participants.
+    pub fn is_visible_to(&self, subject: &str) -> bool {
+        self.organizer == subject || self.participant(subject).is_some()
+    }
+}
+
+/// Body of `POST /exchanges`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExchangeRequest {
+    pub name: String,
+    pub participants: Vec<Participant>,
+    #[serde(default)]
+    pub previous_exchange_id: Option<String>,
+}
+
+/// What a participant learns from the draw: who they give to and that
+/// person's wishlist, if it still exists.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Assignment {
+    pub receiver: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wishlist: Option<SharedWishlist>,
+}
+
+/// Draws a receiver for every participant so that nobody gives to
+/// themselves, to or from their spouse, or to whoever they gave to in
+/// `previous`. The same `seed` always gives the same draw. Returns the
+/// participants with `receiver` set, or `None` if the exclusions leave no
+/// valid draw.
+pub fn draw(
+    participants: &[Participant],
+    previous: Option<&Exchange>,
+    seed: u64,
+) -> Option<Vec<Participant>> {
+    let last_year: HashMap<&str, &str> = previous
+        .map(|previous| {
+            previous
+                .participants
+                .iter()
+                .filter_map(|p| Some((p.subject.as_str(), p.receiver.as_deref()?)))
+                .collect()
+        })
+        .unwrap_or_default();
+    let allowed = |giver: &Participant, receiver: &Participant| {
+        giver.subject != receiver.subject
+            && giver.spouse.as_deref() != Some(receiver.subject.as_str())
+            && receiver.spouse.as_deref() != Some(giver.subject.as_str())
+            && last_year.get(giver.subject.as_str()) != Some(&receiver.subject.as_str())
+    };
+
+    // A perfect matching of givers to receivers over the allowed pairs, found
+    // with augmenting paths so a draw is only refused when none exists.
+    // Shuffling the order givers and receivers are tried in randomizes which
+    // matching comes out.
+    let mut rng = SplitMix64(seed);
+    let n = participants.len();
+    let candidates: Vec<Vec<usize>> = participants
+        .iter()
+        .map(|giver| {
+            let mut candidates: Vec<usize> = (0..n)
+                .filter(|&r| allowed(giver, &participants[r]))
+                .collect();
+            rng.shuffle(&mut candidates);
+            candidates
+        })
+        .collect();
+    let mut order: Vec<usize> = (0..n).collect();
+    rng.shuffle(&mut order);
+    let mut giver_of: Vec<Option<usize>> = vec![None; n];
+    for giver in order {
+        let mut visited = vec![false; n];
+        if !augment(giver, &candidates, &mut visited, &mut giver_of) {
+            return None;
+        }
+    }
+
+    let mut drawn = participants.to_vec();
+    for (receiver, giver) in giver_of.into_iter().enumerate() {
+        drawn[giver?].receiver = Some(participants[receiver].subject.clone());
+    }
+    Some(drawn)
+}
+
+/// Finds `giver` a receiver, moving earlier givers to other candidates if
+/// that frees one up.
+fn augment(
+    giver: usize,
+    candidates: &[Vec<usize>],
+    visited: &mut [bool],
+    giver_of: &mut [Option<usize>],
+) -> bool {
+    for &receiver in &candidates[giver] {
+        if visited[receiver] {
+            continue;
+        }
+        visited[receiver] = true;
+        let free = match giver_of[receiver] {
+            None => true,
+            Some(other) => augment(other, candidates, visited, giver_of),
+        };
+        if free {
+            giver_of[receiver] = Some(giver);
+            return true;
+        }
+    }
+    false
+}
+
+/// Small seeded generator; draws only need to be unpredictable to the
+/// participants, not cryptographically random.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn shuffle<T>(&mut self, values: &mut [T]) {
+        for i in (1..values.len()).rev() {
+            let j = (self.next() % (i as u64 + 1)) as usize;
+            values.swap(i, j);
+        }
+    }
+}
+
+/// The `Conflict` for drawing an exchange a second time.
+pub fn already_drawn() -> AppError {
+    AppError::Conflict("Names have already been drawn for this exchange".to_string())
+}
+
+/// A seed for a draw. Never taken from a request: anyone who knows the seed
+/// can rerun `draw` and learn every pairing.
+pub fn random_seed() -> u64 {
+    uuid::Uuid::new_v4().as_u64_pair().0
+}
@@ -0,0 +1,89 @@
+use lambda_http::http::{HeaderValue, StatusCode};
+use lambda_http::{Body, Request, Response};
+use log::info;
+
+use crate::api_key::{ApiKey, ApiKeyRequest, CreatedApiKey};
+use crate::auth::Principal;
+use crate::error::AppError;
+use crate::policy;
+use crate::state::AppState;
+use crate::utils::{
+    api_key_location, build_app_error_response, build_response, build_validation_response, now,
+};
+use crate::validation::{parse_body, validate_api_key_request};
+
+/// The caller managing their keys. Keys belong to an authenticated owner, so
+/// these routes need authentication enabled and a bearer token.
+fn key_owner(event: &Request) -> Result<&Principal, AppError> {
+    let principal = Principal::from_request(event).ok_or_else(|| {
+        AppError::Unauthorized("API keys require authentication to be enabled".to_string())
+    })?;
+    policy::require_token(principal)?;
+    Ok(principal)
+}
+
+pub async fn handle_create_api_key(
+    event: Request,
+    state: &AppState,
+) -> Result<Response<Body>, AppError> {
+    let owner = match key_owner(&event) {
+        Ok(principal) => principal.subject.clone(),
+        Err(e) => return build_app_error_response(e),
+    };
+    let request: ApiKeyRequest = match parse_body(event.body().as_ref()) {
+        Ok(request) => request,
+        Err(e) => return build_app_error_response(e),
+    };
+    let errors = validate_api_key_request(&request, &state.config.limits);
+    if !errors.is_empty() {
+        return build_validation_response(&errors);
+    }
+
+    let (api_key, key) = ApiKey::generate(&owner, &request.name, request.scope);
+    info!("[DEBUG] Creating API key {} for {}", api_key.id, owner);
+    match state.store.create_api_key(api_key.clone()).await {
+        Ok(()) => {
+            let location = HeaderValue::from_str(&api_key_location(&api_key.id))?;
+            let mut response =
+                build_response(StatusCode::CREATED, Some(CreatedApiKey { api_key, key }))?;
+            response.headers_mut().insert("Location", location);
+            response
+                .headers_mut()
+                .insert("Cache-Control", HeaderValue::from_static("no-store"));
+            Ok(response)
+        }
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+pub async fn handle_list_api_keys(
+    event: Request,
+    state: &AppState,
+) -> Result<Response<Body>, AppError> {
+    let owner = match key_owner(&event) {
+        Ok(principal) => principal.subject.clone(),
+        Err(e) => return build_app_error_response(e),
+    };
+    match state.store.list_api_keys(owner).await {
+        Ok(keys) => build_response(StatusCode::OK, Some(keys)),
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+/// Revokes a key. The key stays listed, with its `revoked_at`, but can no
+/// longer be used.
+pub async fn handle_revoke_api_key(
+    event: Request,
+    state: &AppState,
+    id: String,
+) -> Result<Response<Body>, AppError> {
+    let owner = match key_owner(&event) {
+        Ok(principal) => principal.subject.clone(),
+        Err(e) => return build_app_error_response(e),
+    };
+    info!("[DEBUG] Revoking API key {} of {}", id, owner);
+    match state.store.revoke_api_key(owner, id, now()).await {
+        Ok(_) => build_response::<()>(StatusCode::NO_CONTENT, None),
+        Err(e) => build_app_error_response(e),
+    }
+}
@@ -0,0 +1,96 @@
+use lambda_http::http::StatusCode;
+use lambda_http::{Body, Request, Response};
+use log::info;
+use serde::Deserialize;
+
+use crate::error::AppError;
+use crate::handlers::wishlist::{Collaborator, CollaboratorRole};
+use crate::state::AppState;
+use crate::utils::{build_app_error_response, build_response, build_validation_response};
+use crate::validation::{parse_body, FieldError};
+
+/// Body of `PUT /wishlists/{id}/collaborators/{subject}`.
+#[derive(Debug, Deserialize)]
+struct CollaboratorBody {
+    role: CollaboratorRole,
+}
+
+pub async fn handle_list_collaborators(
+    state: &AppState,
+    wishlist_id: String,
+) -> Result<Response<Body>, AppError> {
+    match state.store.get_item(wishlist_id.clone()).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return build_app_error_response(AppError::NotFound),
+        Err(e) => return build_app_error_response(e),
+    }
+    match state.store.list_collaborators(wishlist_id).await {
+        Ok(collaborators) => build_response(StatusCode::OK, Some(collaborators)),
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+/// Invites `subject` to the wishlist with the role in the body, or changes
+/// the role of an existing collaborator.
+pub async fn handle_put_collaborator(
+    event: Request,
+    state: &AppState,
+    wishlist_id: String,
+    subject: String,
+) -> Result<Response<Body>, AppError> {
+    let body: CollaboratorBody = match parse_body(event.body().as_ref()) {
+        Ok(body) => body,
+        Err(e) => return build_app_error_response(e),
+    };
+    let wishlist = match state.store.get_item(wishlist_id.clone()).await {
+        Ok(Some(wishlist)) => wishlist,
+        Ok(None) => return build_app_error_response(AppError::NotFound),
+        Err(e) => return build_app_error_response(e),
+    };
+    let max_length = state.config.limits.max_owner_length;
+    if subject.chars().count() > max_length {
+        return build_validation_response(&[FieldError::new(
+            "subject",
+            "max_length",
+            format!("must be at most {} characters", max_length),
+        )]);
+    }
+    if subject == wishlist.owner {
+        return build_validation_response(&[FieldError::new(
+            "subject",
+            "not_owner",
+            "the owner can't also be a collaborator",
+        )]);
+    }
+
+    let collaborator = Collaborator {
+        subject,
+        role: body.role,
+    };
+    info!(
+        "[DEBUG] Sharing wishlist {} with {} as {}",
+        wishlist_id,
+        collaborator.subject,
+        collaborator.role.as_str()
+    );
+    match state
+        .store
+        .put_collaborator(wishlist_id, collaborator.clone())
+        .await
+    {
+        Ok(()) => build_response(StatusCode::OK, Some(collaborator)),
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+pub async fn handle_delete_collaborator(
+    state: &AppState,
+    wishlist_id: String,
+    subject: String,
+) -> Result<Response<Body>, AppError> {
+    info!("[DEBUG] Removing {} from wishlist {}", subject, wishlist_id);
+    match state.store.delete_collaborator(wishlist_id, subject).await {
+        Ok(()) => build_response::<()>(StatusCode::NO_CONTENT, None),
+        Err(e) => build_app_error_response(e),
+    }
+}
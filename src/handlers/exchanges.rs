@@ -0,0 +1,260 @@
+use lambda_http::http::{HeaderValue, StatusCode};
+use lambda_http::{Body, Request, Response};
+use log::info;
+
+use crate::auth::Principal;
+use crate::error::AppError;
+use crate::exchange::{self, Assignment, Exchange, ExchangeRequest};
+use crate::share::SharedWishlist;
+use crate::state::AppState;
+use crate::utils::{
+    build_app_error_response, build_response, build_validation_response, exchange_location, now,
+};
+use crate::validation::{parse_body, validate_exchange_request, FieldError};
+
+/// The caller acting on an exchange. Exchanges are organized and joined by
+/// authenticated subjects, so these routes need authentication enabled.
+fn exchange_caller(event: &Request) -> Result<&Principal, AppError> {
+    Principal::from_request(event).ok_or_else(|| {
+        AppError::Unauthorized("Gift exchanges require authentication to be enabled".to_string())
+    })
+}
+
+async fn fetch_exchange(state: &AppState, id: String) -> Result<Exchange, AppError> {
+    state
+        .store
+        .get_exchange(id)
+        .await?
+        .ok_or(AppError::NotFound)
+}
+
+fn require_organizer(principal: &Principal, exchange: &Exchange) -> Result<(), AppError> {
+    if exchange.organizer == principal.subject {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden(
+            "Only the exchange's organizer can do this".to_string(),
+        ))
+    }
+}
+
+/// Sets up an exchange organized by the caller. Each participant's wishlist
+/// must exist and belong to them.
+pub async fn handle_create_exchange(
+    event: Request,
+    state: &AppState,
+) -> Result<Response<Body>, AppError> {
+    let organizer = match exchange_caller(&event) {
+        Ok(principal) => principal.subject.clone(),
+        Err(e) => return build_app_error_response(e),
+    };
+    let request: ExchangeRequest = match parse_body(event.body().as_ref()) {
+        Ok(request) => request,
+        Err(e) => return build_app_error_response(e),
+    };
+    let mut errors = validate_exchange_request(&request, &state.config.limits);
+    if !errors.is_empty() {
+        return build_validation_response(&errors);
+    }
+    for (position, participant) in request.participants.iter().enumerate() {
+        let field = format!("participants[{}].wishlist_id", position);
+        match state.store.get_item(participant.wishlist_id.clone()).await {
+            Ok(Some(wishlist)) if wishlist.owner == participant.subject => {}
+            Ok(Some(_)) => errors.push(FieldError::new(
+                field,
+                "not_owned",
+                "must be one of the participant's own wishlists",
+            )),
+            Ok(None) => errors.push(FieldError::new(field, "not_found", "no such wishlist")),
+            Err(e) => return build_app_error_response(e),
+        }
+    }
+    if let Some(previous_id) = &request.previous_exchange_id {
+        match state.store.get_exchange(previous_id.clone()).await {
+            Ok(Some(previous)) if previous.organizer == organizer => {}
+            Ok(_) => errors.push(FieldError::new(
+                "previous_exchange_id",
+                "not_found",
+                "no such exchange of yours",
+            )),
+            Err(e) => return build_app_error_response(e),
+        }
+    }
+    if !errors.is_empty() {
+        return build_validation_response(&errors);
+    }
+
+    let exchange = Exchange {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: request.name,
+        organizer,
+        participants: request.participants,
+        previous_exchange_id: request.previous_exchange_id,
+        created_at: now(),
+        drawn_at: None,
+    };
+    info!(
+        "[DEBUG] Creating exchange {} for {}",
+        exchange.id, exchange.organizer
+    );
+    match state.store.create_exchange(exchange.clone()).await {
+        Ok(()) => {
+            let location = HeaderValue::from_str(&exchange_location(&exchange.id))?;
+            let mut response = build_response(StatusCode::CREATED, Some(exchange))?;
+            response.headers_mut().insert("Location", location);
+            Ok(response)
+        }
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+/// Shows an exchange to its organizer and participants. Nobody's receiver is
+/// ever included.
+pub async fn handle_get_exchange(
+    event: Request,
+    state: &AppState,
+    id: String,
+) -> Result<Response<Body>, AppError> {
+    let principal = match exchange_caller(&event) {
+        Ok(principal) => principal,
+        Err(e) => return build_app_error_response(e),
+    };
+    let exchange = match fetch_exchange(state, id).await {
+        Ok(exchange) => exchange,
+        Err(e) => return build_app_error_response(e),
+    };
+    if !exchange.is_visible_to(&principal.subject) {
+        return build_app_error_response(AppError::Forbidden(
+            "Only the organizer and participants can see this exchange".to_string(),
+        ));
+    }
+    build_response(StatusCode::OK, Some(exchange))
+}
+
+pub async fn handle_delete_exchange(
+    event: Request,
+    state: &AppState,
+    id: String,
+) -> Result<Response<Body>, AppError> {
+    let principal = match exchange_caller(&event) {
+        Ok(principal) => principal,
+        Err(e) => return build_app_error_response(e),
+    };
+    let exchange = match fetch_exchange(state, id.clone()).await {
+        Ok(exchange) => exchange,
+        Err(e) => return build_app_error_response(e),
+    };
+    if let Err(e) = require_organizer(principal, &exchange) {
+        return build_app_error_response(e);
+    }
+    info!("[DEBUG] Deleting exchange {}", id);
+    match state.store.delete_exchange(id).await {
+        Ok(()) => build_response::<()>(StatusCode::NO_CONTENT, None),
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+/// Draws names, once.
+pub async fn handle_draw_exchange(
+    event: Request,
+    state: &AppState,
+    id: String,
+) -> Result<Response<Body>, AppError> {
+    let principal = match exchange_caller(&event) {
+        Ok(principal) => principal,
+        Err(e) => return build_app_error_response(e),
+    };
+    let mut exchange = match fetch_exchange(state, id.clone()).await {
+        Ok(exchange) => exchange,
+        Err(e) => return build_app_error_response(e),
+    };
+    if let Err(e) = require_organizer(principal, &exchange) {
+        return build_app_error_response(e);
+    }
+    if exchange.drawn_at.is_some() {
+        return build_app_error_response(exchange::already_drawn());
+    }
+    // Last year's exchange may have been deleted since; then there is no
+    // pairing to avoid.
+    let previous = match &exchange.previous_exchange_id {
+        Some(previous_id) => match state.store.get_exchange(previous_id.clone()).await {
+            Ok(previous) => previous,
+            Err(e) => return build_app_error_response(e),
+        },
+        None => None,
+    };
+    let seed = exchange::random_seed();
+    let Some(participants) = exchange::draw(&exchange.participants, previous.as_ref(), seed) else {
+        return build_app_error_response(AppError::Conflict(
+            "No draw satisfies the exclusions; add participants or remove a spouse".to_string(),
+        ));
+    };
+    info!("[DEBUG] Drawing exchange {}", id);
+    let drawn_at = now();
+    match state
+        .store
+        .record_draw(id, participants.clone(), drawn_at)
+        .await
+    {
+        Ok(()) => {
+            exchange.participants = participants;
+            exchange.drawn_at = Some(drawn_at);
+            build_response(StatusCode::OK, Some(exchange))
+        }
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+/// Tells a participant who they give to, with that person's wishlist as a
+/// share link would show it. Nobody else's assignment is ever revealed, not
+/// even to the organizer.
+pub async fn handle_get_assignment(
+    event: Request,
+    state: &AppState,
+    id: String,
+) -> Result<Response<Body>, AppError> {
+    let principal = match exchange_caller(&event) {
+        Ok(principal) => principal,
+        Err(e) => return build_app_error_response(e),
+    };
+    let exchange = match fetch_exchange(state, id).await {
+        Ok(exchange) => exchange,
+        Err(e) => return build_app_error_response(e),
+    };
+    let Some(giver) = exchange.participant(&principal.subject) else {
+        return build_app_error_response(AppError::Forbidden(
+            "Only participants have an assignment".to_string(),
+        ));
+    };
+    let Some(receiver) = giver
+        .receiver
+        .as_deref()
+        .and_then(|receiver| exchange.participant(receiver))
+    else {
+        return build_app_error_response(AppError::Conflict(
+            "Names haven't been drawn for this exchange yet".to_string(),
+        ));
+    };
+
+    let wishlist = match state.store.get_item(receiver.wishlist_id.clone()).await {
+        Ok(wishlist) => wishlist.filter(|wishlist| wishlist.owner == receiver.subject),
+        Err(e) => return build_app_error_response(e),
+    };
+    let wishlist = match wishlist {
+        Some(wishlist) => match state.store.list_reservations(wishlist.id.clone()).await {
+            Ok(reservations) => Some(SharedWishlist::new(wishlist, &reservations)),
+            Err(e) => return build_app_error_response(e),
+        },
+        None => None,
+    };
+    let assignment = Assignment {
+        receiver: receiver.subject.clone(),
+        wishlist,
+    };
+    let mut response = build_response(StatusCode::OK, Some(assignment))?;
+    response.headers_mut().insert(
+        "Cache-Control",
+        HeaderValue::from_static("private, no-store"),
+    );
+    Ok(response)
+}
@@ -0,0 +1,180 @@
+use lambda_http::http::{HeaderValue, StatusCode};
+use lambda_http::{Body, Request, Response};
+use log::info;
+
+use super::expected_version;
+use crate::error::AppError;
+use crate::handlers::wishlist::{Wishlist, WishlistItem};
+use crate::state::AppState;
+use crate::utils::{
+    build_app_error_response, build_error_response, build_response, build_validation_response,
+    item_location,
+};
+use crate::validation::{parse_body, validate_item, FieldError};
+
+pub async fn handle_add_item(
+    event: Request,
+    state: &AppState,
+    wishlist_id: String,
+) -> Result<Response<Body>, AppError> {
+    let mut item: WishlistItem = match parse_body(event.body().as_ref()) {
+        Ok(item) => item,
+        Err(e) => return build_app_error_response(e),
+    };
+    item.id = uuid::Uuid::new_v4().to_string();
+    let limits = &state.config.limits;
+    let mut errors = validate_item("", &item, limits);
+    // Room on the list is checked against what is stored now, so adds racing
+    // each other can overshoot the limits by a few items.
+    match state.store.get_item(wishlist_id.clone()).await {
+        Ok(Some(wishlist)) => {
+            if wishlist.items.len() >= limits.max_items {
+                errors.push(FieldError::new(
+                    "items",
+                    "max_items",
+                    format!("wishlist already holds {} items", limits.max_items),
+                ));
+            }
+            let size = serde_json::to_vec(&wishlist)?.len() + serde_json::to_vec(&item)?.len();
+            if size > limits.max_wishlist_bytes {
+                errors.push(FieldError::new(
+                    ".",
+                    "max_size",
+                    format!(
+                        "adding the item would make the wishlist larger than {} bytes",
+                        limits.max_wishlist_bytes
+                    ),
+                ));
+            }
+        }
+        Ok(None) => return build_app_error_response(AppError::NotFound),
+        Err(e) => return build_app_error_response(e),
+    }
+    if !errors.is_empty() {
+        return build_validation_response(&errors);
+    }
+    info!(
+        "[DEBUG] Adding item {} to wishlist {}",
+        item.id, wishlist_id
+    );
+
+    let result = match expected_version(&event) {
+        Ok(expected) => {
+            state
+                .store
+                .add_wishlist_item(wishlist_id.clone(), item.clone(), expected)
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(wishlist) => {
+            let location = HeaderValue::from_str(&item_location(&wishlist_id, &item.id))?;
+            let mut response = build_item_response(StatusCode::CREATED, &wishlist, &item.id)?;
+            response.headers_mut().insert("Location", location);
+            Ok(response)
+        }
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+pub async fn handle_get_item(
+    state: &AppState,
+    wishlist_id: String,
+    item_id: String,
+) -> Result<Response<Body>, AppError> {
+    match state.store.get_item(wishlist_id).await {
+        Ok(Some(wishlist)) => build_item_response(StatusCode::OK, &wishlist, &item_id),
+        Ok(None) => build_app_error_response(AppError::NotFound),
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+pub async fn handle_patch_item(
+    event: Request,
+    state: &AppState,
+    wishlist_id: String,
+    item_id: String,
+) -> Result<Response<Body>, AppError> {
+    let patch: serde_json::Value = match parse_body(event.body().as_ref()) {
+        Ok(patch) => patch,
+        Err(e) => return build_app_error_response(e),
+    };
+    if !patch.is_object() {
+        return build_error_response(StatusCode::BAD_REQUEST, "Item patch must be a JSON object");
+    }
+    // Validate the patched item as it would be now. The store applies the
+    // patch again to whatever it finds, which only differs if the item is
+    // edited concurrently.
+    match state.store.get_item(wishlist_id.clone()).await {
+        Ok(Some(wishlist)) => {
+            if let Some(current) = wishlist.items.iter().find(|item| item.id == item_id) {
+                let errors = match current.patched(&patch) {
+                    Ok(patched) => validate_item("", &patched, &state.config.limits),
+                    Err(e) => return build_app_error_response(e),
+                };
+                if !errors.is_empty() {
+                    return build_validation_response(&errors);
+                }
+            }
+        }
+        Ok(None) => return build_app_error_response(AppError::NotFound),
+        Err(e) => return build_app_error_response(e),
+    }
+
+    let result = match expected_version(&event) {
+        Ok(expected) => {
+            state
+                .store
+                .update_wishlist_item(wishlist_id, item_id.clone(), patch, expected)
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(wishlist) => build_item_response(StatusCode::OK, &wishlist, &item_id),
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+pub async fn handle_delete_item(
+    event: Request,
+    state: &AppState,
+    wishlist_id: String,
+    item_id: String,
+) -> Result<Response<Body>, AppError> {
+    let result = match expected_version(&event) {
+        Ok(expected) => {
+            state
+                .store
+                .delete_wishlist_item(wishlist_id, item_id, expected)
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(wishlist) => {
+            let etag = HeaderValue::from_str(&wishlist.etag())?;
+            let mut response = build_response::<()>(StatusCode::NO_CONTENT, None)?;
+            response.headers_mut().insert("ETag", etag);
+            Ok(response)
+        }
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+/// Responds with one item of `wishlist`. The `ETag` is the wishlist's, since
+/// that is what `If-Match` on item requests is checked against.
+fn build_item_response(
+    status_code: StatusCode,
+    wishlist: &Wishlist,
+    item_id: &str,
+) -> Result<Response<Body>, AppError> {
+    let Some(item) = wishlist.items.iter().find(|item| item.id == item_id) else {
+        return build_app_error_response(AppError::NotFound);
+    };
+    let etag = HeaderValue::from_str(&wishlist.etag())?;
+    let mut response = build_response(status_code, Some(item))?;
+    response.headers_mut().insert("ETag", etag);
+    Ok(response)
+}
@@ -1,150 +1,666 @@
-use lambda_http::http::StatusCode;
+use lambda_http::http::header::CONTENT_TYPE;
+use lambda_http::http::{HeaderValue, Method, StatusCode};
 use lambda_http::{Body, Request, Response};
 use log::{error, info};
 use serde_json::json;
 
+pub mod api_keys;
+pub mod collaborators;
+pub mod exchanges;
+pub mod items;
+pub mod pledges;
+pub mod reservations;
+pub mod shares;
 pub mod wishlist;
 
-pub use crate::handlers::wishlist::Wishlist;
+pub use crate::handlers::api_keys::{
+    handle_create_api_key, handle_list_api_keys, handle_revoke_api_key,
+};
+pub use crate::handlers::collaborators::{
+    handle_delete_collaborator, handle_list_collaborators, handle_put_collaborator,
+};
+pub use crate::handlers::exchanges::{
+    handle_create_exchange, handle_delete_exchange, handle_draw_exchange, handle_get_assignment,
+    handle_get_exchange,
+};
+pub use crate::handlers::items::{
+    handle_add_item, handle_delete_item, handle_get_item, handle_patch_item,
+};
+pub use crate::handlers::pledges::{handle_add_pledge, handle_list_pledges};
+pub use crate::handlers::reservations::{
+    handle_list_reservations, handle_release_item, handle_release_shared, handle_reserve_item,
+    handle_reserve_shared,
+};
+pub use crate::handlers::shares::{
+    handle_create_share, handle_get_shared, handle_list_shares, handle_revoke_share,
+};
+pub use crate::handlers::wishlist::{
+    Collaborator, CollaboratorRole, Priority, Wishlist, WishlistItem, WishlistPatch,
+};
 
+use crate::api_key;
+use crate::auth::Principal;
+use crate::db::Page;
 use crate::error::AppError;
-use aws_sdk_dynamodb::Client as DynamoDbClient;
+use crate::pagination::{encode_cursor, PageRequest};
+use crate::policy::{self, Access};
+use crate::problem::{annotate, request_id};
+use crate::router::Route;
+use crate::state::AppState;
 
-use crate::utils::{build_error_response, build_response};
+use crate::utils::{
+    build_app_error_response, build_error_response, build_response, build_validation_response,
+    next_page_query, query_param, wishlist_location, IfMatch,
+};
+use crate::validation::{parse_body, validate_wishlist};
 
+/// Value of `Accept-Patch` for routes that take a `PATCH`.
+const ACCEPT_PATCH: &str = "application/merge-patch+json, application/json-patch+json";
+
+/// Entry point for every request. Errors, whether returned by a handler or
+/// raised on the way, come back as `application/problem+json` tagged with the
+/// request path and ID.
 pub async fn handle_request(
-    event: Request,
-    db_client: &DynamoDbClient,
+    mut event: Request,
+    state: &AppState,
 ) -> Result<Response<Body>, AppError> {
-    let path = event.uri().path();
-    let method = event.method();
+    let path = event.uri().path().to_string();
+    let request_id = request_id(&event);
+    let head = event.method() == Method::HEAD;
 
     info!("[DEBUG] Request path: {}", path);
-    info!("[DEBUG] Request method: {}", method);
+    info!("[DEBUG] Request method: {}", event.method());
+    info!("[DEBUG] Request ID: {}", request_id);
+
+    let result = match authenticate(&mut event, state).await {
+        Ok(()) => route_request(event, state).await,
+        Err(e) => Err(e),
+    };
+    let mut response = match result {
+        Ok(response) => response,
+        Err(e) => build_app_error_response(e)?,
+    };
+    if response.status() == StatusCode::UNAUTHORIZED {
+        response
+            .headers_mut()
+            .insert("WWW-Authenticate", HeaderValue::from_static("Bearer"));
+    }
+    let mut response = annotate(response, &path, &request_id)?;
+    if head {
+        *response.body_mut() = Body::Empty;
+    }
+    Ok(response)
+}
 
-    let cleaned_path = path.trim_start_matches("/prod"); // Remove /prod prefix
+/// Verifies the caller when authentication is enabled and records who they
+/// are for the handlers. Callers identify with an API key if they send one,
+/// otherwise with a bearer token. Health checks, CORS preflights and share
+/// links stay open.
+async fn authenticate(event: &mut Request, state: &AppState) -> Result<(), AppError> {
+    let Some(auth) = &state.auth else {
+        return Ok(());
+    };
+    if event.method() == Method::OPTIONS
+        || Route::recognize(event.uri().path()).is_some_and(|route| route.is_public())
+    {
+        return Ok(());
+    }
+    let principal = match api_key::presented_key(event) {
+        Some(key) => api_key::authenticate(state.store.as_ref(), key).await?,
+        None => auth.authenticate(event).await?,
+    };
+    info!("[DEBUG] Authenticated as: {}", principal.subject);
+    policy::require_scope(&principal, event.method())?;
+    event.extensions_mut().insert(principal);
+    Ok(())
+}
 
-    match (method.as_str(), cleaned_path) {
-        ("GET", _) => handle_get(event, db_client).await,
-        ("POST", "/wishlists") => handle_post(event, db_client).await,
-        ("PUT", "/wishlists") => handle_put(event, db_client).await,
-        ("DELETE", "/wishlists") => handle_delete(event, db_client).await,
-        _ => {
-            error!("Unhandled request: {} {}", method, path);
-            build_error_response(StatusCode::NOT_FOUND, "Not Found")
+async fn route_request(event: Request, state: &AppState) -> Result<Response<Body>, AppError> {
+    let path = event.uri().path().to_string();
+    let method = event.method().clone();
+
+    let Some(route) = Route::recognize(&path) else {
+        error!("Unhandled request: {} {}", method, path);
+        return build_app_error_response(AppError::NotFound);
+    };
+    let allow = HeaderValue::from_str(&route.allow())?;
+    if method == Method::OPTIONS {
+        let mut response = build_response::<()>(StatusCode::NO_CONTENT, None)?;
+        response.headers_mut().insert("Allow", allow);
+        return Ok(response);
+    }
+
+    // HEAD is answered by the GET handler; `handle_request` drops the body.
+    let method = if method == Method::HEAD {
+        Method::GET
+    } else {
+        method
+    };
+    if !route.methods().contains(&method) {
+        let mut response =
+            build_error_response(StatusCode::METHOD_NOT_ALLOWED, "Method Not Allowed")?;
+        response.headers_mut().insert("Allow", allow);
+        return Ok(response);
+    }
+    let principal = Principal::from_request(&event);
+    if let Some((wishlist_id, needed)) = policy::required_access(&route, &method, principal) {
+        authorize(&event, state, wishlist_id, needed).await?;
+    }
+
+    match (route, method.as_str()) {
+        (Route::Health, "GET") => build_response(StatusCode::OK, Some(json!({"status": "OK"}))),
+        (Route::Wishlists, "GET") => {
+            let owner = query_param(event.uri().query(), "owner");
+            handle_list(&event, state, owner).await
+        }
+        (Route::Wishlists, "POST") => handle_post(event, state).await,
+        (Route::Wishlists, "PUT") => handle_put(event, state).await,
+        (Route::Wishlists, "DELETE") => handle_delete(event, state).await,
+        (Route::OwnerWishlists { owner }, "GET") => handle_list(&event, state, Some(owner)).await,
+        (Route::Wishlist { id }, "GET") => handle_get_wishlist(state, id).await,
+        (Route::Wishlist { id }, "PUT") => handle_put_by_id(event, state, id).await,
+        (Route::Wishlist { id }, "PATCH") => handle_patch(event, state, id).await,
+        (Route::Wishlist { id }, "DELETE") => handle_delete_by_id(event, state, id).await,
+        (Route::WishlistItems { wishlist_id }, "POST") => {
+            handle_add_item(event, state, wishlist_id).await
+        }
+        (
+            Route::WishlistItem {
+                wishlist_id,
+                item_id,
+            },
+            "GET",
+        ) => handle_get_item(state, wishlist_id, item_id).await,
+        (
+            Route::WishlistItem {
+                wishlist_id,
+                item_id,
+            },
+            "PATCH",
+        ) => handle_patch_item(event, state, wishlist_id, item_id).await,
+        (
+            Route::WishlistItem {
+                wishlist_id,
+                item_id,
+            },
+            "DELETE",
+        ) => handle_delete_item(event, state, wishlist_id, item_id).await,
+        (
+            Route::Reservation {
+                wishlist_id,
+                item_id,
+            },
+            "PUT",
+        ) => handle_reserve_item(event, state, wishlist_id, item_id).await,
+        (
+            Route::Reservation {
+                wishlist_id,
+                item_id,
+            },
+            "DELETE",
+        ) => handle_release_item(event, state, wishlist_id, item_id).await,
+        (Route::Reservations { wishlist_id }, "GET") => {
+            handle_list_reservations(event, state, wishlist_id).await
+        }
+        (
+            Route::Pledges {
+                wishlist_id,
+                item_id,
+            },
+            "GET",
+        ) => handle_list_pledges(state, wishlist_id, item_id).await,
+        (
+            Route::Pledges {
+                wishlist_id,
+                item_id,
+            },
+            "POST",
+        ) => handle_add_pledge(event, state, wishlist_id, item_id).await,
+        (Route::Collaborators { wishlist_id }, "GET") => {
+            handle_list_collaborators(state, wishlist_id).await
+        }
+        (
+            Route::Collaborator {
+                wishlist_id,
+                subject,
+            },
+            "PUT",
+        ) => handle_put_collaborator(event, state, wishlist_id, subject).await,
+        (
+            Route::Collaborator {
+                wishlist_id,
+                subject,
+            },
+            "DELETE",
+        ) => handle_delete_collaborator(state, wishlist_id, subject).await,
+        (Route::CollaboratorWishlists { subject }, "GET") => {
+            handle_list_shared(&event, state, subject).await
+        }
+        (Route::Shares { wishlist_id }, "GET") => handle_list_shares(state, wishlist_id).await,
+        (Route::Shares { wishlist_id }, "POST") => {
+            handle_create_share(event, state, wishlist_id).await
+        }
+        (Route::Share { wishlist_id, id }, "DELETE") => {
+            handle_revoke_share(state, wishlist_id, id).await
+        }
+        (Route::Shared { token }, "GET") => handle_get_shared(event, state, token).await,
+        (Route::SharedReservation { token, item_id }, "PUT") => {
+            handle_reserve_shared(event, state, token, item_id).await
+        }
+        (Route::SharedReservation { token, item_id }, "DELETE") => {
+            handle_release_shared(event, state, token, item_id).await
+        }
+        (Route::Exchanges, "POST") => handle_create_exchange(event, state).await,
+        (Route::Exchange { id }, "GET") => handle_get_exchange(event, state, id).await,
+        (Route::Exchange { id }, "DELETE") => handle_delete_exchange(event, state, id).await,
+        (Route::ExchangeDraw { id }, "POST") => handle_draw_exchange(event, state, id).await,
+        (Route::ExchangeAssignment { id }, "GET") => handle_get_assignment(event, state, id).await,
+        (Route::ApiKeys, "GET") => handle_list_api_keys(event, state).await,
+        (Route::ApiKeys, "POST") => handle_create_api_key(event, state).await,
+        (Route::ApiKey { id }, "DELETE") => handle_revoke_api_key(event, state, id).await,
+        (route, method) => {
+            error!("No handler for {} on {:?}", method, route);
+            build_error_response(StatusCode::METHOD_NOT_ALLOWED, "Method Not Allowed")
         }
     }
 }
-pub async fn handle_get(
-    event: Request,
-    db_client: &DynamoDbClient,
+
+/// Checks the caller may act on `wishlist_id` as `needed` per the policy. A
+/// missing wishlist passes, leaving the handler to answer 404.
+async fn authorize(
+    event: &Request,
+    state: &AppState,
+    wishlist_id: &str,
+    needed: Access,
+) -> Result<(), AppError> {
+    let Some(principal) = Principal::from_request(event) else {
+        return Ok(());
+    };
+    let Some(wishlist) = state.store.get_item(wishlist_id.to_string()).await? else {
+        return Ok(());
+    };
+    let collaborator = if principal.subject == wishlist.owner {
+        None
+    } else {
+        state
+            .store
+            .get_collaborator(wishlist.id.clone(), principal.subject.clone())
+            .await?
+    };
+    policy::require(Some(principal), &wishlist, collaborator.as_ref(), needed)
+}
+
+/// Serves a read. Equivalent to `handle_request` with the method forced to
+/// GET.
+pub async fn handle_get(mut event: Request, state: &AppState) -> Result<Response<Body>, AppError> {
+    *event.method_mut() = Method::GET;
+    handle_request(event, state).await
+}
+
+async fn handle_get_wishlist(state: &AppState, id: String) -> Result<Response<Body>, AppError> {
+    match state.store.get_item(id).await {
+        Ok(Some(wishlist)) => build_wishlist_response(StatusCode::OK, wishlist),
+        Ok(None) => build_app_error_response(AppError::NotFound),
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+/// Lists one page of wishlists, optionally only those belonging to `owner`.
+async fn handle_list(
+    event: &Request,
+    state: &AppState,
+    owner: Option<String>,
 ) -> Result<Response<Body>, AppError> {
-    let path = event.uri().path();
-    let cleaned_path = path.trim_start_matches("/prod"); // Remove /prod prefix
-    info!("[DEBUG] GET request path: {}", path);
-    info!("[DEBUG] Cleaned GET request path: {}", cleaned_path);
-    match cleaned_path {
-        "/health" => build_response(StatusCode::OK, Some(json!({"status": "OK"}))),
-        "/wishlists" | "/wishlist" => match crate::db::scan_items(db_client).await {
-            Ok(wishlists) => build_response(StatusCode::OK, Some(wishlists)),
-            Err(e) => {
-                error!("Error scanning DynamoDB: {:?}", e);
-                build_error_response(StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error")
+    let page = match PageRequest::from_query(event.uri().query(), &state.config.cursor_secret) {
+        Ok(page) => page,
+        Err(e) => return build_error_response(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+    // Signed-in callers see their own wishlists unless they ask for others'.
+    let owner = match Principal::from_request(event) {
+        Some(principal) => {
+            let owner = owner.unwrap_or_else(|| principal.subject.clone());
+            if let Err(e) = policy::require_list(Some(principal), &owner) {
+                return build_app_error_response(e);
             }
-        },
-        path if path.starts_with("/wishlists/") => {
-            let id = path.trim_start_matches("/wishlists/").trim_end_matches('/');
-            match crate::db::get_item(db_client, id.to_string()).await {
-                Ok(Some(wishlist)) => build_response(StatusCode::OK, Some(wishlist)),
-                Ok(None) => build_error_response(StatusCode::NOT_FOUND, "Not Found"),
-                Err(e) => {
-                    error!("Error getting item from DynamoDB: {:?}", e);
-                    build_error_response(StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error")
-                }
+            Some(owner)
+        }
+        None => owner,
+    };
+    let result = match owner {
+        Some(owner) => {
+            state
+                .store
+                .query_by_owner(owner, page.limit, page.start_key)
+                .await
+        }
+        None => state.store.scan_page(page.limit, page.start_key).await,
+    };
+    build_page_response(event, state, page.limit, result)
+}
+
+/// Lists one page of the wishlists shared with `subject`.
+async fn handle_list_shared(
+    event: &Request,
+    state: &AppState,
+    subject: String,
+) -> Result<Response<Body>, AppError> {
+    let page = match PageRequest::from_query(event.uri().query(), &state.config.cursor_secret) {
+        Ok(page) => page,
+        Err(e) => return build_error_response(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+    if let Err(e) = policy::require_list(Principal::from_request(event), &subject) {
+        return build_app_error_response(e);
+    }
+    let result = state
+        .store
+        .query_shared_with(subject, page.limit, page.start_key)
+        .await;
+    build_page_response(event, state, page.limit, result)
+}
+
+/// Responds with a page of wishlists, linking to the next page if there is
+/// one.
+fn build_page_response(
+    event: &Request,
+    state: &AppState,
+    limit: usize,
+    result: Result<Page<Wishlist>, AppError>,
+) -> Result<Response<Body>, AppError> {
+    match result {
+        Ok(result) => {
+            let mut response = build_response(StatusCode::OK, Some(result.items))?;
+            if let Some(last_key) = result.last_key {
+                let cursor = encode_cursor(&state.config.cursor_secret, &last_key);
+                let link = format!(
+                    "<{}?{}>; rel=\"next\"",
+                    event.uri().path(),
+                    next_page_query(event.uri().query(), limit, &cursor)
+                );
+                let headers = response.headers_mut();
+                headers.insert("X-Next-Cursor", HeaderValue::from_str(&cursor)?);
+                headers.insert("Link", HeaderValue::from_str(&link)?);
             }
+            Ok(response)
         }
-        _ => build_error_response(StatusCode::NOT_FOUND, "Not Found"),
+        Err(e) => build_app_error_response(e),
     }
 }
 
-pub async fn handle_post(
-    event: Request,
-    db_client: &DynamoDbClient,
+/// Responds with the wishlist as the body and its version as the `ETag`.
+fn build_wishlist_response(
+    status_code: StatusCode,
+    wishlist: Wishlist,
 ) -> Result<Response<Body>, AppError> {
+    let etag = HeaderValue::from_str(&wishlist.etag())?;
+    let mut response = build_response(status_code, Some(wishlist))?;
+    response.headers_mut().insert("ETag", etag);
+    Ok(response)
+}
+
+pub async fn handle_post(event: Request, state: &AppState) -> Result<Response<Body>, AppError> {
     let body = event.body().as_ref();
     info!("[DEBUG] POST body: {:?}", String::from_utf8_lossy(body));
-    let wishlist: Wishlist = serde_json::from_slice(body)?;
+    let mut wishlist: Wishlist = match parse_body(body) {
+        Ok(wishlist) => wishlist,
+        Err(e) => return build_app_error_response(e),
+    };
+    if let Some(principal) = Principal::from_request(&event) {
+        wishlist.owner = principal.subject.clone();
+    }
+    if wishlist.id.is_empty() {
+        wishlist.id = uuid::Uuid::new_v4().to_string();
+    } else if !state.config.allow_client_ids {
+        return build_error_response(
+            StatusCode::BAD_REQUEST,
+            "Wishlist IDs are assigned by the server; omit 'id'",
+        );
+    }
+    wishlist.version = 1;
+    wishlist.assign_item_ids();
+    let errors = validate_wishlist(&wishlist, &state.config.limits);
+    if !errors.is_empty() {
+        return build_validation_response(&errors);
+    }
     info!("[DEBUG] Parsed wishlist: {:?}", wishlist);
-    match crate::db::put_item(db_client, wishlist.clone()).await {
-        Ok(_) => build_response(StatusCode::CREATED, Some(wishlist)),
-        Err(e) => {
-            error!("Error putting item to DynamoDB: {:?}", e);
-            build_error_response(StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error")
+    match state.store.create_item(wishlist.clone()).await {
+        Ok(_) => {
+            let location = HeaderValue::from_str(&wishlist_location(&wishlist.id))?;
+            let mut response = build_wishlist_response(StatusCode::CREATED, wishlist)?;
+            response.headers_mut().insert("Location", location);
+            Ok(response)
         }
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+/// Resolves `If-Match` into the version a conditional write must find.
+fn expected_version(event: &Request) -> Result<Option<u64>, AppError> {
+    match IfMatch::from_request(event) {
+        Some(precondition) => precondition.expected_version(),
+        None => Ok(None),
+    }
+}
+
+/// `PUT /wishlists` with the ID in the body. Deprecated in favour of
+/// `PUT /wishlists/{id}`.
+pub async fn handle_put(event: Request, state: &AppState) -> Result<Response<Body>, AppError> {
+    let updated: Wishlist = match parse_body(event.body().as_ref()) {
+        Ok(updated) => updated,
+        Err(e) => return build_app_error_response(e),
+    };
+    if updated.id.is_empty() {
+        return Err(AppError::MissingId);
+    }
+    if let Err(e) = authorize(&event, state, &updated.id, Access::Write).await {
+        return build_app_error_response(e);
     }
+    let successor = wishlist_location(&updated.id);
+    let response = put_wishlist(&event, state, updated).await?;
+    mark_deprecated(response, &successor)
 }
 
-pub async fn handle_put(
+/// `PUT /wishlists/{id}`. The body may omit `id`; if it has one it must match
+/// the path.
+pub async fn handle_put_by_id(
     event: Request,
-    db_client: &DynamoDbClient,
+    state: &AppState,
+    id: String,
 ) -> Result<Response<Body>, AppError> {
-    let updated: Wishlist = serde_json::from_slice(event.body().as_ref())?;
+    let mut updated: Wishlist = match parse_body(event.body().as_ref()) {
+        Ok(updated) => updated,
+        Err(e) => return build_app_error_response(e),
+    };
+    if updated.id.is_empty() {
+        updated.id = id;
+    } else if updated.id != id {
+        return build_error_response(
+            StatusCode::BAD_REQUEST,
+            "Wishlist ID in the body does not match the path",
+        );
+    }
+    put_wishlist(&event, state, updated).await
+}
+
+async fn put_wishlist(
+    event: &Request,
+    state: &AppState,
+    mut updated: Wishlist,
+) -> Result<Response<Body>, AppError> {
+    if Principal::from_request(event).is_some() {
+        // Ownership comes from tokens, so a replacement can't reassign it.
+        match state.store.get_item(updated.id.clone()).await {
+            Ok(Some(current)) => updated.owner = current.owner,
+            Ok(None) => return build_app_error_response(AppError::NotFound),
+            Err(e) => return build_app_error_response(e),
+        }
+    }
+    updated.assign_item_ids();
+    let errors = validate_wishlist(&updated, &state.config.limits);
+    if !errors.is_empty() {
+        return build_validation_response(&errors);
+    }
     info!("[DEBUG] Updating wishlist with ID: {}", updated.id);
 
-    // Check if the item exists before attempting to update
-    match crate::db::get_item(db_client, updated.id.clone()).await {
-        Ok(Some(_)) => {
-            // Item found, proceed with put_item
-            match crate::db::put_item(db_client, updated.clone()).await {
-                Ok(_) => build_response(StatusCode::OK, Some(updated)),
-                Err(e) => {
-                    error!("Error updating item in DynamoDB: {:?}", e);
-                    build_error_response(StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error")
-                }
-            }
+    let result = match expected_version(event) {
+        Ok(expected) => state.store.update_item(updated, expected).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(stored) => build_wishlist_response(StatusCode::OK, stored),
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+/// Attempts at a PATCH without `If-Match` before giving up on a wishlist
+/// that keeps changing underneath it.
+const PATCH_ATTEMPTS: usize = 5;
+
+/// `PATCH /wishlists/{id}` with a JSON Merge Patch or JSON Patch body. The
+/// patch is applied to the stored wishlist and written back with one update
+/// conditioned on the version it was applied to, so `test` operations and
+/// unchanged fields are checked against exactly what gets replaced.
+pub async fn handle_patch(
+    event: Request,
+    state: &AppState,
+    id: String,
+) -> Result<Response<Body>, AppError> {
+    let content_type = event
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase());
+    let body = event.body().as_ref();
+    let patch = match content_type.as_deref() {
+        Some(WishlistPatch::MERGE_CONTENT_TYPE) => parse_body(body).map(WishlistPatch::Merge),
+        Some(WishlistPatch::JSON_CONTENT_TYPE) => parse_body(body).map(WishlistPatch::Json),
+        _ => {
+            let mut response = build_error_response(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "PATCH bodies must be application/merge-patch+json or application/json-patch+json",
+            )?;
+            response
+                .headers_mut()
+                .insert("Accept-Patch", HeaderValue::from_static(ACCEPT_PATCH));
+            return Ok(response);
+        }
+    };
+    let patch = match patch {
+        Ok(patch) => patch,
+        Err(e) => return build_app_error_response(e),
+    };
+    let expected = match expected_version(&event) {
+        Ok(expected) => expected,
+        Err(e) => return build_app_error_response(e),
+    };
+
+    for _ in 0..PATCH_ATTEMPTS {
+        let current = match state.store.get_item(id.clone()).await {
+            Ok(Some(current)) => current,
+            Ok(None) => return build_app_error_response(AppError::NotFound),
+            Err(e) => return build_app_error_response(e),
+        };
+        if expected.is_some_and(|expected| expected != current.version) {
+            return build_app_error_response(AppError::PreconditionFailed);
+        }
+        let mut patched = match patch.apply(&current) {
+            Ok(patched) => patched,
+            Err(e) => return build_app_error_response(e),
+        };
+        if Principal::from_request(&event).is_some() {
+            patched.owner = current.owner.clone();
+        }
+        patched.assign_item_ids();
+        let errors = validate_wishlist(&patched, &state.config.limits);
+        if !errors.is_empty() {
+            return build_validation_response(&errors);
         }
-        Ok(None) => build_error_response(StatusCode::NOT_FOUND, "Not Found"),
-        Err(e) => {
-            error!("Error checking item existence in DynamoDB: {:?}", e);
-            build_error_response(StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error")
+        match state
+            .store
+            .update_item(patched, Some(current.version))
+            .await
+        {
+            Ok(stored) => return build_wishlist_response(StatusCode::OK, stored),
+            // Someone else wrote in between; reapply to their version.
+            Err(AppError::PreconditionFailed) if expected.is_none() => continue,
+            Err(e) => return build_app_error_response(e),
         }
     }
+    build_app_error_response(AppError::Conflict(
+        "Wishlist is being modified concurrently; try again".to_string(),
+    ))
 }
 
-pub async fn handle_delete(
+/// `DELETE /wishlists` with `{"id": ...}` in the body. Deprecated in favour
+/// of `DELETE /wishlists/{id}`.
+pub async fn handle_delete(event: Request, state: &AppState) -> Result<Response<Body>, AppError> {
+    let id = match body_id(&event) {
+        Ok(Some(id)) => id,
+        Ok(None) => return Err(AppError::MissingId),
+        Err(e) => return build_app_error_response(e),
+    };
+    if let Err(e) = authorize(&event, state, &id, Access::Admin).await {
+        return build_app_error_response(e);
+    }
+    let successor = wishlist_location(&id);
+    let response = delete_wishlist(&event, state, id).await?;
+    mark_deprecated(response, &successor)
+}
+
+/// `DELETE /wishlists/{id}`. No body is needed; if one is sent with an `id`,
+/// it must match the path.
+pub async fn handle_delete_by_id(
     event: Request,
-    db_client: &DynamoDbClient,
+    state: &AppState,
+    id: String,
 ) -> Result<Response<Body>, AppError> {
-    let body = event.body().as_ref();
-    let id_map: std::collections::HashMap<String, String> = match serde_json::from_slice(body) {
-        Ok(map) => map,
-        Err(e) => return Err(AppError::from(e)),
-    };
-
-    let id = match id_map.get("id") {
-        Some(id) => id,
-        None => return Err(AppError::MissingId),
-    };
-
-    // Check if the item exists before attempting to delete
-    match crate::db::get_item(db_client, id.to_string()).await {
-        Ok(Some(_)) => {
-            // Item found, proceed with delete
-            match crate::db::delete_item(db_client, id.to_string()).await {
-                Ok(_) => build_response::<()>(StatusCode::NO_CONTENT, None),
-                Err(e) => {
-                    error!("Error deleting item from DynamoDB: {:?}", e);
-                    build_error_response(StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error")
-                }
+    if !event.body().is_empty() {
+        match body_id(&event) {
+            Ok(Some(body_id)) if body_id != id => {
+                return build_error_response(
+                    StatusCode::BAD_REQUEST,
+                    "Wishlist ID in the body does not match the path",
+                );
             }
-        }
-        Ok(None) => build_error_response(StatusCode::NOT_FOUND, "Not Found"),
-        Err(e) => {
-            error!(
-                "Error checking item existence for deletion in DynamoDB: {:?}",
-                e
-            );
-            build_error_response(StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error")
+            Ok(_) => {}
+            Err(e) => return build_app_error_response(e),
         }
     }
+    delete_wishlist(&event, state, id).await
+}
+
+/// Reads the `id` member of a JSON object body.
+fn body_id(event: &Request) -> Result<Option<String>, AppError> {
+    let body: serde_json::Value = parse_body(event.body().as_ref())?;
+    Ok(body
+        .get("id")
+        .and_then(|id| id.as_str())
+        .map(str::to_string))
+}
+
+async fn delete_wishlist(
+    event: &Request,
+    state: &AppState,
+    id: String,
+) -> Result<Response<Body>, AppError> {
+    let result = match expected_version(event) {
+        Ok(expected) => state.store.delete_item(id, expected).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(_) => build_response::<()>(StatusCode::NO_CONTENT, None),
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+/// Flags a response from a deprecated endpoint and points at its replacement.
+fn mark_deprecated(
+    mut response: Response<Body>,
+    successor: &str,
+) -> Result<Response<Body>, AppError> {
+    let link = HeaderValue::from_str(&format!("<{}>; rel=\"successor-version\"", successor))?;
+    let headers = response.headers_mut();
+    headers.insert("Deprecation", HeaderValue::from_static("true"));
+    headers.insert("Link", link);
+    Ok(response)
 }
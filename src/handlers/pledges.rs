@@ -0,0 +1,105 @@
+use lambda_http::http::StatusCode;
+use lambda_http::{Body, Request, Response};
+use log::info;
+
+use crate::auth::Principal;
+use crate::error::AppError;
+use crate::handlers::WishlistItem;
+use crate::pledge::{self, Funding, Pledge, PledgeReceipt, PledgeRequest};
+use crate::state::AppState;
+use crate::utils::{build_app_error_response, build_response, build_validation_response};
+use crate::validation::{parse_body, validate_pledge_request};
+
+/// Shows how far an item's group gift has got, with every pledge toward it.
+pub async fn handle_list_pledges(
+    state: &AppState,
+    wishlist_id: String,
+    item_id: String,
+) -> Result<Response<Body>, AppError> {
+    let item = match find_item(state, &wishlist_id, &item_id).await {
+        Ok(item) => item,
+        Err(e) => return build_app_error_response(e),
+    };
+    match state.store.list_pledges(wishlist_id, item_id).await {
+        Ok(pledges) => build_response(StatusCode::OK, Some(Funding::new(&item, pledges))),
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+/// Pledges part of an item's price. The item closes once its pledges cover
+/// its price for every unit wanted; a pledge of more than what remains is a
+/// conflict. Without authentication the body must name the contributor.
+pub async fn handle_add_pledge(
+    event: Request,
+    state: &AppState,
+    wishlist_id: String,
+    item_id: String,
+) -> Result<Response<Body>, AppError> {
+    let request: PledgeRequest = match parse_body(event.body().as_ref()) {
+        Ok(request) => request,
+        Err(e) => return build_app_error_response(e),
+    };
+    let item = match find_item(state, &wishlist_id, &item_id).await {
+        Ok(item) => item,
+        Err(e) => return build_app_error_response(e),
+    };
+    let principal = Principal::from_request(&event);
+    let errors =
+        validate_pledge_request(&request, &item, &state.config.limits, principal.is_none());
+    if !errors.is_empty() {
+        return build_validation_response(&errors);
+    }
+    let (Some(currency), Some(_)) = (&item.currency, pledge::target(&item)) else {
+        return build_app_error_response(pledge::unpriced());
+    };
+    let contributor = match principal {
+        Some(principal) => principal.subject.clone(),
+        None => request
+            .contributor
+            .as_deref()
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    };
+    let pledge = Pledge::new(
+        &item_id,
+        &contributor,
+        request.amount_cents,
+        currency,
+        request.message.filter(|message| !message.trim().is_empty()),
+    );
+    info!(
+        "[DEBUG] Pledging {} {} toward {} on {} for {}",
+        pledge.amount_cents, pledge.currency, item_id, wishlist_id, contributor
+    );
+    if let Err(e) = state
+        .store
+        .add_pledge(wishlist_id.clone(), pledge.clone())
+        .await
+    {
+        return build_app_error_response(e);
+    }
+    match state.store.list_pledges(wishlist_id, item_id).await {
+        Ok(pledges) => {
+            let receipt = PledgeReceipt {
+                pledge,
+                funding: Funding::new(&item, pledges),
+            };
+            build_response(StatusCode::CREATED, Some(receipt))
+        }
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+async fn find_item(
+    state: &AppState,
+    wishlist_id: &str,
+    item_id: &str,
+) -> Result<WishlistItem, AppError> {
+    state
+        .store
+        .get_item(wishlist_id.to_string())
+        .await?
+        .and_then(|wishlist| wishlist.items.into_iter().find(|item| item.id == item_id))
+        .ok_or(AppError::NotFound)
+}
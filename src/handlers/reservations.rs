@@ -0,0 +1,245 @@
+use lambda_http::http::StatusCode;
+use lambda_http::{Body, Request, Response};
+use log::info;
+
+use crate::auth::Principal;
+use crate::error::AppError;
+use crate::handlers::shares::open_link;
+use crate::policy;
+use crate::reservation::{self, GuestReservation, Reservation, ReservationRequest, CLAIM_HEADER};
+use crate::secret::{random_token, sha256_hex};
+use crate::state::AppState;
+use crate::utils::{
+    build_app_error_response, build_response, build_validation_response, now, query_param,
+};
+use crate::validation::{parse_body, validate_reservation_request, FieldError};
+
+/// Lists who reserved what. Open to anyone who can see the wishlist except
+/// its owner.
+pub async fn handle_list_reservations(
+    event: Request,
+    state: &AppState,
+    wishlist_id: String,
+) -> Result<Response<Body>, AppError> {
+    let wishlist = match state.store.get_item(wishlist_id.clone()).await {
+        Ok(Some(wishlist)) => wishlist,
+        Ok(None) => return build_app_error_response(AppError::NotFound),
+        Err(e) => return build_app_error_response(e),
+    };
+    if let Err(e) = policy::require_not_owner(Principal::from_request(&event), &wishlist) {
+        return build_app_error_response(e);
+    }
+    match state.store.list_reservations(wishlist_id).await {
+        Ok(reservations) => build_response(
+            StatusCode::OK,
+            Some(reservation::current(&wishlist, reservations)),
+        ),
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+/// Reserves an item for the caller, or changes their reservation, e.g. to
+/// mark it purchased. Without authentication the body must name the
+/// reserver.
+pub async fn handle_reserve_item(
+    event: Request,
+    state: &AppState,
+    wishlist_id: String,
+    item_id: String,
+) -> Result<Response<Body>, AppError> {
+    let request = match reservation_request(&event) {
+        Ok(request) => request,
+        Err(e) => return build_app_error_response(e),
+    };
+    let principal = Principal::from_request(&event);
+    let errors = validate_reservation_request(&request, &state.config.limits, principal.is_none());
+    if !errors.is_empty() {
+        return build_validation_response(&errors);
+    }
+    let wishlist = match state.store.get_item(wishlist_id.clone()).await {
+        Ok(Some(wishlist)) => wishlist,
+        Ok(None) => return build_app_error_response(AppError::NotFound),
+        Err(e) => return build_app_error_response(e),
+    };
+    if let Err(e) = policy::require_not_owner(principal, &wishlist) {
+        return build_app_error_response(e);
+    }
+    let reserver = match principal {
+        Some(principal) => principal.subject.clone(),
+        None => request
+            .name
+            .as_deref()
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    };
+    match reserve(state, wishlist_id, item_id, reserver, &request, None).await {
+        Ok(stored) => build_response(StatusCode::OK, Some(stored)),
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+/// Withdraws the caller's reservation of an item. Without authentication the
+/// reserver is given as `?name=`.
+pub async fn handle_release_item(
+    event: Request,
+    state: &AppState,
+    wishlist_id: String,
+    item_id: String,
+) -> Result<Response<Body>, AppError> {
+    let reserver = match Principal::from_request(&event) {
+        Some(principal) => principal.subject.clone(),
+        None => match reserver_name(&event) {
+            Some(name) => name,
+            None => return build_validation_response(&[name_required()]),
+        },
+    };
+    release(state, wishlist_id, item_id, reserver, None).await
+}
+
+/// Reserves an item through a share link, as the guest named in the body.
+/// A new reservation comes with a claim token, returned only this once;
+/// changing the reservation later needs it in `X-Claim-Token`.
+pub async fn handle_reserve_shared(
+    event: Request,
+    state: &AppState,
+    token: String,
+    item_id: String,
+) -> Result<Response<Body>, AppError> {
+    let link = match open_link(&event, state, &token).await {
+        Ok(link) => link,
+        Err(e) => return build_app_error_response(e),
+    };
+    let request = match reservation_request(&event) {
+        Ok(request) => request,
+        Err(e) => return build_app_error_response(e),
+    };
+    let errors = validate_reservation_request(&request, &state.config.limits, true);
+    if !errors.is_empty() {
+        return build_validation_response(&errors);
+    }
+    let reserver = Reservation::guest(request.name.as_deref().unwrap_or_default());
+    let (claim_token, minted) = match claim_token(&event) {
+        Some(token) => (token, false),
+        None => (random_token(), true),
+    };
+    let claim_hash = Some(sha256_hex(&claim_token));
+    match reserve(
+        state,
+        link.wishlist_id,
+        item_id,
+        reserver,
+        &request,
+        claim_hash,
+    )
+    .await
+    {
+        Ok(reservation) => build_response(
+            StatusCode::OK,
+            Some(GuestReservation {
+                reservation,
+                claim_token: minted.then_some(claim_token),
+            }),
+        ),
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+/// Withdraws a guest's reservation made through a share link. The guest is
+/// given as `?name=`, with the reservation's claim token in
+/// `X-Claim-Token`.
+pub async fn handle_release_shared(
+    event: Request,
+    state: &AppState,
+    token: String,
+    item_id: String,
+) -> Result<Response<Body>, AppError> {
+    let link = match open_link(&event, state, &token).await {
+        Ok(link) => link,
+        Err(e) => return build_app_error_response(e),
+    };
+    let Some(name) = reserver_name(&event) else {
+        return build_validation_response(&[name_required()]);
+    };
+    let claim_hash = claim_token(&event).map(|token| sha256_hex(&token));
+    release(
+        state,
+        link.wishlist_id,
+        item_id,
+        Reservation::guest(&name),
+        claim_hash,
+    )
+    .await
+}
+
+/// The body of a reservation. It may be left out to reserve one unit.
+fn reservation_request(event: &Request) -> Result<ReservationRequest, AppError> {
+    if event.body().is_empty() {
+        Ok(ReservationRequest::default())
+    } else {
+        parse_body(event.body().as_ref())
+    }
+}
+
+fn reserver_name(event: &Request) -> Option<String> {
+    query_param(event.uri().query(), "name")
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+fn claim_token(event: &Request) -> Option<String> {
+    event
+        .headers()
+        .get(CLAIM_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+fn name_required() -> FieldError {
+    FieldError::new("name", "required", "must not be empty")
+}
+
+async fn reserve(
+    state: &AppState,
+    wishlist_id: String,
+    item_id: String,
+    reserver: String,
+    request: &ReservationRequest,
+    claim_hash: Option<String>,
+) -> Result<Reservation, AppError> {
+    info!(
+        "[DEBUG] Reserving {} of {} on {} for {}",
+        request.quantity, item_id, wishlist_id, reserver
+    );
+    let reservation = Reservation {
+        item_id,
+        reserver,
+        quantity: request.quantity,
+        purchased: request.purchased,
+        reserved_at: now(),
+        claim_hash,
+    };
+    state.store.reserve_item(wishlist_id, reservation).await
+}
+
+async fn release(
+    state: &AppState,
+    wishlist_id: String,
+    item_id: String,
+    reserver: String,
+    claim_hash: Option<String>,
+) -> Result<Response<Body>, AppError> {
+    info!(
+        "[DEBUG] Releasing {} on {} for {}",
+        item_id, wishlist_id, reserver
+    );
+    match state
+        .store
+        .delete_reservation(wishlist_id, item_id, reserver, claim_hash)
+        .await
+    {
+        Ok(()) => build_response::<()>(StatusCode::NO_CONTENT, None),
+        Err(e) => build_app_error_response(e),
+    }
+}
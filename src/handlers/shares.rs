@@ -0,0 +1,154 @@
+use lambda_http::http::{HeaderValue, StatusCode};
+use lambda_http::{Body, Request, Response};
+use log::info;
+
+use crate::auth::Principal;
+use crate::error::AppError;
+use crate::share::{
+    CreatedShareLink, ShareLink, ShareLinkSummary, ShareRequest, SharedWishlist, PASSWORD_HEADER,
+};
+use crate::state::AppState;
+use crate::utils::{
+    build_app_error_response, build_response, build_validation_response, now, share_location,
+    shared_location,
+};
+use crate::validation::{parse_body, validate_share_request};
+
+/// Makes a share link to the wishlist. The body is optional; without one the
+/// link has no password and never expires.
+pub async fn handle_create_share(
+    event: Request,
+    state: &AppState,
+    wishlist_id: String,
+) -> Result<Response<Body>, AppError> {
+    let request: ShareRequest = if event.body().is_empty() {
+        ShareRequest::default()
+    } else {
+        match parse_body(event.body().as_ref()) {
+            Ok(request) => request,
+            Err(e) => return build_app_error_response(e),
+        }
+    };
+    let errors = validate_share_request(&request, &state.config.limits, now());
+    if !errors.is_empty() {
+        return build_validation_response(&errors);
+    }
+
+    let created_by = Principal::from_request(&event).map(|principal| principal.subject.clone());
+    let (link, token) = ShareLink::generate(&wishlist_id, created_by, &request);
+    info!("[DEBUG] Creating share link {} to {}", link.id, wishlist_id);
+    match state.store.create_share(link.clone()).await {
+        Ok(()) => {
+            let location = HeaderValue::from_str(&share_location(&wishlist_id, &link.id))?;
+            let created = CreatedShareLink {
+                has_password: link.password_hash.is_some(),
+                url: shared_location(&token),
+                link,
+                token,
+            };
+            let mut response = build_response(StatusCode::CREATED, Some(created))?;
+            response.headers_mut().insert("Location", location);
+            response
+                .headers_mut()
+                .insert("Cache-Control", HeaderValue::from_static("no-store"));
+            Ok(response)
+        }
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+/// Lists the wishlist's share links, revoked and expired ones included.
+pub async fn handle_list_shares(
+    state: &AppState,
+    wishlist_id: String,
+) -> Result<Response<Body>, AppError> {
+    match state.store.get_item(wishlist_id.clone()).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return build_app_error_response(AppError::NotFound),
+        Err(e) => return build_app_error_response(e),
+    }
+    match state.store.list_shares(wishlist_id).await {
+        Ok(links) => {
+            let links: Vec<ShareLinkSummary> = links.into_iter().map(Into::into).collect();
+            build_response(StatusCode::OK, Some(links))
+        }
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+/// Revokes a share link. It stays listed, with its `revoked_at`, but no
+/// longer opens the wishlist.
+pub async fn handle_revoke_share(
+    state: &AppState,
+    wishlist_id: String,
+    id: String,
+) -> Result<Response<Body>, AppError> {
+    info!("[DEBUG] Revoking share link {} to {}", id, wishlist_id);
+    match state.store.revoke_share(wishlist_id, id, now()).await {
+        Ok(_) => build_response::<()>(StatusCode::NO_CONTENT, None),
+        Err(e) => build_app_error_response(e),
+    }
+}
+
+/// The link behind `token`, if it works and the request has its password.
+/// Unknown, revoked and expired links are all `NotFound`, so the response
+/// doesn't reveal which tokens once worked.
+pub(crate) async fn open_link(
+    event: &Request,
+    state: &AppState,
+    token: &str,
+) -> Result<ShareLink, AppError> {
+    let link = match state
+        .store
+        .get_share(ShareLink::id_for_token(token))
+        .await?
+    {
+        Some(link) if link.is_active(now()) => link,
+        _ => return Err(AppError::NotFound),
+    };
+    let password = event
+        .headers()
+        .get(PASSWORD_HEADER)
+        .and_then(|value| value.to_str().ok());
+    if !link.accepts(password) {
+        let message = match password {
+            Some(_) => "Wrong password for this share link",
+            None => "This share link needs a password in X-Share-Password",
+        };
+        return Err(AppError::Forbidden(message.to_string()));
+    }
+    Ok(link)
+}
+
+/// `GET /shared/{token}`, open to anyone holding the token.
+pub async fn handle_get_shared(
+    event: Request,
+    state: &AppState,
+    token: String,
+) -> Result<Response<Body>, AppError> {
+    let link = match open_link(&event, state, &token).await {
+        Ok(link) => link,
+        Err(e) => return build_app_error_response(e),
+    };
+    let reservations = match state
+        .store
+        .list_reservations(link.wishlist_id.clone())
+        .await
+    {
+        Ok(reservations) => reservations,
+        Err(e) => return build_app_error_response(e),
+    };
+    match state.store.get_item(link.wishlist_id).await {
+        Ok(Some(wishlist)) => {
+            let shared = SharedWishlist::new(wishlist, &reservations);
+            let mut response = build_response(StatusCode::OK, Some(shared))?;
+            response.headers_mut().insert(
+                "Cache-Control",
+                HeaderValue::from_static("private, no-store"),
+            );
+            Ok(response)
+        }
+        Ok(None) => build_app_error_response(AppError::NotFound),
+        Err(e) => build_app_error_response(e),
+    }
+}
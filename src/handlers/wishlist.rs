@@ -1,13 +1,281 @@
+use crate::error::AppError;
+use crate::validation::parse_value;
 use aws_sdk_dynamodb::types::AttributeValue;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
+use std::str::FromStr;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Wishlist {
+    /// Generated by the server on create; may be omitted from POST bodies.
+    #[serde(default)]
     pub id: String,
     pub name: String,
+    /// Taken from the caller's token when authentication is enabled, so it
+    /// may be omitted from request bodies.
+    #[serde(default)]
     pub owner: String,
-    pub items: Vec<String>,
+    #[serde(deserialize_with = "deserialize_items")]
+    pub items: Vec<WishlistItem>,
+    /// Bumped on every write; clients see it as the `ETag`. Set by the
+    /// server, so any value sent in a request body is ignored.
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl Wishlist {
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.version)
+    }
+
+    /// Gives every item sent without an `id` a fresh one.
+    pub fn assign_item_ids(&mut self) {
+        for item in self.items.iter_mut().filter(|item| item.id.is_empty()) {
+            item.id = uuid::Uuid::new_v4().to_string();
+        }
+    }
+}
+
+/// Body of a `PATCH /wishlists/{id}`, by its content type.
+#[derive(Debug, Clone)]
+pub enum WishlistPatch {
+    /// `application/merge-patch+json` (RFC 7396).
+    Merge(serde_json::Value),
+    /// `application/json-patch+json` (RFC 6902).
+    Json(json_patch::Patch),
+}
+
+impl WishlistPatch {
+    pub const MERGE_CONTENT_TYPE: &'static str = "application/merge-patch+json";
+    pub const JSON_CONTENT_TYPE: &'static str = "application/json-patch+json";
+
+    /// Returns `wishlist` with the patch applied. The ID and version are
+    /// managed by the server and can't be patched; a failed `test` operation
+    /// is a `Conflict`, and a result that is no longer a wishlist is a
+    /// `Validation` error.
+    pub fn apply(&self, wishlist: &Wishlist) -> Result<Wishlist, AppError> {
+        let mut value = serde_json::to_value(wishlist)?;
+        match self {
+            WishlistPatch::Merge(patch) => json_patch::merge(&mut value, patch),
+            WishlistPatch::Json(patch) => {
+                json_patch::patch(&mut value, patch).map_err(|e| match e.kind {
+                    json_patch::PatchErrorKind::TestFailed => AppError::Conflict(format!(
+                        "Patch test failed at operation {} ({})",
+                        e.operation, e.path
+                    )),
+                    _ => AppError::BadRequest(format!("Patch could not be applied: {}", e)),
+                })?
+            }
+        }
+        let mut patched: Wishlist = parse_value(value)?;
+        if patched.id != wishlist.id {
+            return Err(AppError::BadRequest(
+                "Wishlist ID can't be changed".to_string(),
+            ));
+        }
+        patched.version = wishlist.version;
+        Ok(patched)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+impl Priority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(Priority::Low),
+            "medium" => Ok(Priority::Medium),
+            "high" => Ok(Priority::High),
+            other => Err(format!("Unknown priority '{}'", other)),
+        }
+    }
+}
+
+/// What a collaborator may do with someone else's wishlist.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CollaboratorRole {
+    /// Read the list and its items.
+    Viewer,
+    /// Also change the list and its items.
+    Editor,
+    /// Everything the owner can do, including deleting the list and managing
+    /// its collaborators.
+    CoOwner,
+}
+
+impl CollaboratorRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CollaboratorRole::Viewer => "viewer",
+            CollaboratorRole::Editor => "editor",
+            CollaboratorRole::CoOwner => "co-owner",
+        }
+    }
+}
+
+impl FromStr for CollaboratorRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(CollaboratorRole::Viewer),
+            "editor" => Ok(CollaboratorRole::Editor),
+            "co-owner" => Ok(CollaboratorRole::CoOwner),
+            other => Err(format!("Unknown collaborator role '{}'", other)),
+        }
+    }
+}
+
+/// Someone other than the owner who has been given access to a wishlist,
+/// identified by the same subject as `owner`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Collaborator {
+    pub subject: String,
+    pub role: CollaboratorRole,
+}
+
+/// One wish on a list. Prices are in minor units of `currency` (cents for
+/// USD) so they round-trip exactly.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WishlistItem {
+    /// Assigned by the server when missing; stable across edits.
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_cents: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    #[serde(default = "default_quantity")]
+    pub quantity: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+fn default_quantity() -> u32 {
+    1
+}
+
+impl WishlistItem {
+    pub fn new(id: impl Into<String>, name: impl Into<String>) -> Self {
+        WishlistItem {
+            id: id.into(),
+            name: name.into(),
+            url: None,
+            price_cents: None,
+            currency: None,
+            quantity: default_quantity(),
+            priority: None,
+            notes: None,
+        }
+    }
+
+    /// Upgrades an item stored as a bare string before items had IDs. The ID
+    /// is derived from the position so it stays the same until the list is
+    /// next written, at which point it is persisted.
+    pub fn from_legacy(position: usize, name: impl Into<String>) -> Self {
+        WishlistItem::new(legacy_item_id(position), name)
+    }
+
+    /// Returns the item with a JSON merge patch applied. The ID can't be
+    /// patched.
+    pub fn patched(&self, patch: &serde_json::Value) -> Result<WishlistItem, AppError> {
+        if !patch.is_object() {
+            return Err(AppError::BadRequest(
+                "Item patch must be a JSON object".to_string(),
+            ));
+        }
+        let mut value = serde_json::to_value(self)?;
+        json_patch::merge(&mut value, patch);
+        let mut item: WishlistItem = parse_value(value)?;
+        item.id = self.id.clone();
+        Ok(item)
+    }
+}
+
+pub fn legacy_item_id(position: usize) -> String {
+    format!("item-{}", position)
+}
+
+/// Request bodies may still send items as plain strings.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ItemInput {
+    Name(String),
+    Item(WishlistItem),
+}
+
+fn deserialize_items<'de, D>(deserializer: D) -> Result<Vec<WishlistItem>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let items = Vec::<ItemInput>::deserialize(deserializer)?;
+    Ok(items
+        .into_iter()
+        .map(|item| match item {
+            ItemInput::Name(name) => WishlistItem::new(String::new(), name),
+            ItemInput::Item(item) => item,
+        })
+        .collect())
+}
+
+pub(crate) fn item_from_attribute(
+    position: usize,
+    value: &AttributeValue,
+) -> Result<WishlistItem, String> {
+    let fields = match value {
+        AttributeValue::S(name) => return Ok(WishlistItem::from_legacy(position, name.clone())),
+        AttributeValue::M(fields) => fields,
+        _ => return Err("Item is neither a string nor a map".to_string()),
+    };
+    let string = |key: &str| fields.get(key).and_then(|v| v.as_s().ok()).cloned();
+    let number = |key: &str| -> Result<Option<u64>, String> {
+        match fields.get(key) {
+            Some(v) => v
+                .as_n()
+                .ok()
+                .and_then(|n| n.parse().ok())
+                .map(Some)
+                .ok_or(format!("Item {} is not a number", key)),
+            None => Ok(None),
+        }
+    };
+    Ok(WishlistItem {
+        id: string("id").unwrap_or_else(|| legacy_item_id(position)),
+        name: string("name").ok_or("Item name not found or not a string")?,
+        url: string("url"),
+        price_cents: number("price_cents")?,
+        currency: string("currency"),
+        quantity: match number("quantity")? {
+            Some(q) => u32::try_from(q).map_err(|_| "Item quantity is out of range".to_string())?,
+            None => default_quantity(),
+        },
+        priority: string("priority").map(|p| p.parse()).transpose()?,
+        notes: string("notes"),
+    })
 }
 
 impl TryFrom<HashMap<String, AttributeValue>> for Wishlist {
@@ -34,16 +302,28 @@ impl TryFrom<HashMap<String, AttributeValue>> for Wishlist {
             .and_then(|v| v.as_l().ok())
             .map(|v| {
                 v.iter()
-                    .filter_map(|attr| attr.as_s().ok().map(|s| s.to_string()))
-                    .collect()
+                    .enumerate()
+                    .map(|(position, attr)| item_from_attribute(position, attr))
+                    .collect::<Result<Vec<_>, _>>()
             })
+            .transpose()?
             .unwrap_or_default();
+        // Items written before versioning was introduced have no version.
+        let version = match value.get("version") {
+            Some(v) => v
+                .as_n()
+                .ok()
+                .and_then(|n| n.parse().ok())
+                .ok_or("Version is not a number")?,
+            None => 0,
+        };
 
         Ok(Wishlist {
             id,
             name,
             owner,
             items,
+            version,
         })
     }
 }
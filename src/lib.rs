@@ -1,4 +1,18 @@
+pub mod api_key;
+pub mod auth;
+pub mod config;
 pub mod db;
 pub mod error;
+pub mod exchange;
 pub mod handlers;
+pub mod pagination;
+pub mod pledge;
+pub mod policy;
+pub mod problem;
+pub mod reservation;
+pub mod router;
+pub mod secret;
+pub mod share;
+pub mod state;
 pub mod utils;
+pub mod validation;
@@ -6,8 +6,14 @@ use log::error;
 #[tokio::main]
 async fn main() -> Result<(), AppError> {
     env_logger::init();
-    use wishlist_api::db::get_db_client;
-    let db_client = get_db_client().await;
+    use wishlist_api::auth::Authenticator;
+    use wishlist_api::config::Config;
+    use wishlist_api::db::get_store;
+    use wishlist_api::state::AppState;
+    let mut state = AppState::with_config(get_store().await?, Config::from_env()?);
+    if let Some(auth) = Authenticator::from_env()? {
+        state = state.with_auth(auth);
+    }
 
     #[cfg(not(feature = "aws_lambda"))]
     {
@@ -30,20 +36,20 @@ async fn main() -> Result<(), AppError> {
 
         loop {
             let (stream, _) = listener.accept().await?;
-            let db_client_clone = db_client.clone(); // Clone for each spawned task
+            let state_clone = state.clone(); // Clone for each spawned task
             tokio::task::spawn(async move {
                 if let Err(err) = http1::Builder::new()
                     .serve_connection(
                         TokioIo::new(stream),
                         service_fn(move |req: HyperRequest<hyper::body::Incoming>| {
-                            let db_client_inner_clone = db_client_clone.clone(); // Clone for each request
+                            let state_inner_clone = state_clone.clone(); // Clone for each request
                             async move {
                                 let (parts, body) = req.into_parts();
                                 let body_bytes = body.collect().await?.to_bytes();
                                 let lambda_body = lambda_http::Body::from(body_bytes.to_vec());
                                 let lambda_req =
                                     lambda_http::Request::from_parts(parts, lambda_body);
-                                match handle_request(lambda_req, &db_client_inner_clone).await {
+                                match handle_request(lambda_req, &state_inner_clone).await {
                                     Ok(resp) => {
                                         let (parts, body) = resp.into_parts();
                                         let hyper_resp_body = Full::new(Bytes::from(body.to_vec()));
@@ -65,7 +71,7 @@ async fn main() -> Result<(), AppError> {
     #[cfg(feature = "aws_lambda")]
     {
         lambda_http::run(lambda_http::service_fn(|event| {
-            handle_request(event, &db_client)
+            handle_request(event, &state)
         }))
         .await
         .map_err(AppError::from)?
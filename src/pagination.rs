@@ -0,0 +1,85 @@
+use crate::error::AppError;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+pub const DEFAULT_LIMIT: usize = 50;
+pub const MAX_LIMIT: usize = 100;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Cursor contents. These are only base64-encoded, not encrypted; the
+/// signature is what stops clients from forging start keys.
+#[derive(Serialize, Deserialize)]
+struct CursorPayload {
+    #[serde(rename = "k")]
+    last_key: String,
+}
+
+/// Parsed `limit` and `cursor` query parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRequest {
+    pub limit: usize,
+    pub start_key: Option<String>,
+}
+
+impl PageRequest {
+    pub fn from_query(query: Option<&str>, secret: &[u8]) -> Result<Self, AppError> {
+        let mut page = PageRequest {
+            limit: DEFAULT_LIMIT,
+            start_key: None,
+        };
+        for (key, value) in form_urlencoded::parse(query.unwrap_or_default().as_bytes()) {
+            match key.as_ref() {
+                "limit" => {
+                    page.limit = value
+                        .parse()
+                        .ok()
+                        .filter(|limit| (1..=MAX_LIMIT).contains(limit))
+                        .ok_or_else(|| {
+                            AppError::BadRequest(format!(
+                                "limit must be between 1 and {}",
+                                MAX_LIMIT
+                            ))
+                        })?;
+                }
+                "cursor" => page.start_key = Some(decode_cursor(secret, &value)?),
+                _ => {}
+            }
+        }
+        Ok(page)
+    }
+}
+
+fn sign(secret: &[u8], payload: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(payload);
+    mac
+}
+
+pub fn encode_cursor(secret: &[u8], last_key: &str) -> String {
+    let payload = serde_json::to_vec(&CursorPayload {
+        last_key: last_key.to_string(),
+    })
+    .expect("cursor payload is always serializable");
+    let signature = sign(secret, &payload).finalize().into_bytes();
+    format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(&payload),
+        URL_SAFE_NO_PAD.encode(signature)
+    )
+}
+
+pub fn decode_cursor(secret: &[u8], token: &str) -> Result<String, AppError> {
+    let invalid = || AppError::BadRequest("Invalid cursor".to_string());
+    let (payload, signature) = token.split_once('.').ok_or_else(invalid)?;
+    let payload = URL_SAFE_NO_PAD.decode(payload).map_err(|_| invalid())?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid())?;
+    sign(secret, &payload)
+        .verify_slice(&signature)
+        .map_err(|_| invalid())?;
+    let cursor: CursorPayload = serde_json::from_slice(&payload).map_err(|_| invalid())?;
+    Ok(cursor.last_key)
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::handlers::WishlistItem;
+use crate::utils::now;
+
+/// A contribution toward a group gift. Pledges are kept outside the wishlist
+/// document, next to a running total the store checks them against.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pledge {
+    pub id: String,
+    pub item_id: String,
+    /// Subject of the contributor, or the name they gave when authentication
+    /// is disabled.
+    pub contributor: String,
+    pub amount_cents: u64,
+    pub currency: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Unix time in seconds.
+    pub pledged_at: u64,
+}
+
+impl Pledge {
+    pub fn new(
+        item_id: &str,
+        contributor: &str,
+        amount_cents: u64,
+        currency: &str,
+        message: Option<String>,
+    ) -> Self {
+        Pledge {
+            id: uuid::Uuid::new_v4().to_string(),
+            item_id: item_id.to_string(),
+            contributor: contributor.to_string(),
+            amount_cents,
+            currency: currency.to_string(),
+            message,
+            pledged_at: now(),
+        }
+    }
+}
+
+/// Body of `POST /wishlists/{id}/items/{item_id}/pledges`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PledgeRequest {
+    pub amount_cents: u64,
+    /// Defaults to the item's currency, which it must match.
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Who is pledging, when authentication is disabled. Ignored for
+    /// authenticated callers.
+    #[serde(default)]
+    pub contributor: Option<String>,
+}
+
+/// The amount a group gift needs: the item's price for every unit wanted, or
+/// `None` if the item has no price or no currency to pledge in.
+pub fn target(item: &WishlistItem) -> Option<u64> {
+    item.currency.as_ref()?;
+    item.price_cents?.checked_mul(u64::from(item.quantity))
+}
+
+/// How far an item's group gift has got. An item is closed to further
+/// pledges once it is fully funded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Funding {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_cents: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    pub pledged_cents: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_cents: Option<u64>,
+    /// Whole percent of the target pledged, at most 100.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub funded_percent: Option<u64>,
+    pub closed: bool,
+    pub pledges: Vec<Pledge>,
+}
+
+impl Funding {
+    pub fn new(item: &WishlistItem, pledges: Vec<Pledge>) -> Self {
+        let target = target(item);
+        let pledged: u64 = pledges.iter().map(|pledge| pledge.amount_cents).sum();
+        Funding {
+            target_cents: target,
+            currency: item.currency.clone(),
+            pledged_cents: pledged,
+            remaining_cents: target.map(|target| target.saturating_sub(pledged)),
+            funded_percent: target.map(|target| match target {
+                0 => 100,
+                target => (pledged.saturating_mul(100) / target).min(100),
+            }),
+            closed: target.is_some_and(|target| pledged >= target),
+            pledges,
+        }
+    }
+}
+
+/// Response to a new pledge: the pledge and where the item stands with it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PledgeReceipt {
+    pub pledge: Pledge,
+    pub funding: Funding,
+}
+
+/// The `Conflict` for a pledge toward an item without a `target`.
+pub fn unpriced() -> AppError {
+    AppError::Conflict("This item needs a price and currency to be funded".to_string())
+}
+
+/// The `Conflict` for a pledge of more than the `remaining` amount.
+pub fn over_target(remaining: u64) -> AppError {
+    AppError::Conflict(match remaining {
+        0 => "This item is already fully funded".to_string(),
+        n => format!("Only {} cents remain to be funded", n),
+    })
+}
@@ -0,0 +1,165 @@
+use lambda_http::http::Method;
+
+use crate::api_key::ApiKeyScope;
+use crate::auth::Principal;
+use crate::error::AppError;
+use crate::handlers::{Collaborator, CollaboratorRole, Wishlist};
+use crate::router::Route;
+
+/// Role that may read and change every wishlist.
+pub const ADMIN_ROLE: &str = "admin";
+
+/// What a caller may do with a wishlist. Each level includes the ones below
+/// it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Access {
+    None,
+    /// See the wishlist and its items.
+    Read,
+    /// Change its name and items.
+    Write,
+    /// Delete it and manage its collaborators.
+    Admin,
+}
+
+/// Decides `principal`'s access to `wishlist`, given their entry in its
+/// collaborators, if any. With authentication disabled there is no principal
+/// and the API is open, as it always was.
+pub fn access(
+    principal: Option<&Principal>,
+    wishlist: &Wishlist,
+    collaborator: Option<&Collaborator>,
+) -> Access {
+    let Some(principal) = principal else {
+        return Access::Admin;
+    };
+    if principal.has_role(ADMIN_ROLE) || principal.subject == wishlist.owner {
+        return Access::Admin;
+    }
+    match collaborator {
+        Some(collaborator) if collaborator.subject == principal.subject => {
+            match collaborator.role {
+                CollaboratorRole::Viewer => Access::Read,
+                CollaboratorRole::Editor => Access::Write,
+                CollaboratorRole::CoOwner => Access::Admin,
+            }
+        }
+        _ => Access::None,
+    }
+}
+
+/// Fails with `Forbidden` unless `principal` has at least `needed` access.
+pub fn require(
+    principal: Option<&Principal>,
+    wishlist: &Wishlist,
+    collaborator: Option<&Collaborator>,
+    needed: Access,
+) -> Result<(), AppError> {
+    if access(principal, wishlist, collaborator) >= needed {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden(format!(
+            "You do not have {} access to this wishlist",
+            match needed {
+                Access::Read | Access::None => "read",
+                Access::Write => "write",
+                Access::Admin => "admin",
+            }
+        )))
+    }
+}
+
+/// Fails with `Forbidden` unless `principal` may list `owner`'s wishlists, or
+/// the wishlists shared with them.
+pub fn require_list(principal: Option<&Principal>, owner: &str) -> Result<(), AppError> {
+    match principal {
+        Some(principal) if principal.subject != owner && !principal.has_role(ADMIN_ROLE) => Err(
+            AppError::Forbidden("You may only list your own wishlists".to_string()),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Fails with `Forbidden` for the wishlist's owner, so reservations don't
+/// spoil the surprise. The admin role doesn't lift this on one's own list.
+pub fn require_not_owner(
+    principal: Option<&Principal>,
+    wishlist: &Wishlist,
+) -> Result<(), AppError> {
+    match principal {
+        Some(principal) if principal.subject == wishlist.owner => Err(AppError::Forbidden(
+            "Reservations are hidden from the wishlist's owner".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Fails with `Forbidden` when a read-only API key is used for anything but
+/// a read.
+pub fn require_scope(principal: &Principal, method: &Method) -> Result<(), AppError> {
+    let read = method == Method::GET || method == Method::HEAD || method == Method::OPTIONS;
+    if principal.api_key == Some(ApiKeyScope::ReadOnly) && !read {
+        return Err(AppError::Forbidden("This API key is read-only".to_string()));
+    }
+    Ok(())
+}
+
+/// Fails with `Forbidden` for callers using an API key, so a leaked key
+/// can't be used to mint or revoke others.
+pub fn require_token(principal: &Principal) -> Result<(), AppError> {
+    match principal.api_key {
+        Some(_) => Err(AppError::Forbidden(
+            "API keys can only be managed with a bearer token".to_string(),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// The wishlist a request acts on and the access it needs, for every route
+/// that names one in its path. Collaborators may always remove themselves,
+/// share links are managed by those who could delete the wishlist, and
+/// anyone who can see a wishlist can reserve its items.
+pub fn required_access<'a>(
+    route: &'a Route,
+    method: &Method,
+    principal: Option<&Principal>,
+) -> Option<(&'a str, Access)> {
+    let needed = if method == Method::GET || method == Method::HEAD {
+        Access::Read
+    } else {
+        Access::Write
+    };
+    match route {
+        Route::Wishlist { id } if method == Method::DELETE => Some((id, Access::Admin)),
+        Route::Wishlist { id } => Some((id, needed)),
+        Route::WishlistItems { wishlist_id }
+        | Route::WishlistItem { wishlist_id, .. }
+        | Route::Collaborators { wishlist_id } => Some((wishlist_id, needed)),
+        Route::Reservation { wishlist_id, .. }
+        | Route::Reservations { wishlist_id }
+        | Route::Pledges { wishlist_id, .. } => Some((wishlist_id, Access::Read)),
+        Route::Collaborator {
+            wishlist_id,
+            subject,
+        } if method == Method::DELETE
+            && principal.is_some_and(|principal| principal.subject == *subject) =>
+        {
+            Some((wishlist_id, Access::Read))
+        }
+        Route::Collaborator { wishlist_id, .. }
+        | Route::Shares { wishlist_id }
+        | Route::Share { wishlist_id, .. } => Some((wishlist_id, Access::Admin)),
+        Route::Health
+        | Route::Wishlists
+        | Route::OwnerWishlists { .. }
+        | Route::CollaboratorWishlists { .. }
+        | Route::Shared { .. }
+        | Route::SharedReservation { .. }
+        | Route::Exchanges
+        | Route::Exchange { .. }
+        | Route::ExchangeDraw { .. }
+        | Route::ExchangeAssignment { .. }
+        | Route::ApiKeys
+        | Route::ApiKey { .. } => None,
+    }
+}
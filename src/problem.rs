@@ -0,0 +1,154 @@
+use lambda_http::http::{HeaderValue, StatusCode};
+use lambda_http::{Body, Request, RequestExt, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::validation::FieldError;
+
+pub const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
+/// `Retry-After` sent with 503s. The SDK has already retried with backoff by
+/// the time one is returned, so clients are asked to pause briefly.
+const RETRY_AFTER_SECONDS: u64 = 1;
+
+/// Longest client-supplied `X-Request-Id` that is echoed back.
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+/// An RFC 7807 problem details body, the shape of every error response.
+/// `code` is stable and meant for programs; `title` and `detail` are for
+/// people and may change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub type_uri: String,
+    pub title: String,
+    pub status: u16,
+    pub code: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Path of the request that failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Field-level errors of a `validation_failed` problem.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub errors: Option<Vec<FieldError>>,
+    /// Seconds the client should wait before retrying, sent as
+    /// `Retry-After` rather than in the body.
+    #[serde(skip)]
+    pub retry_after: Option<u64>,
+}
+
+impl Problem {
+    pub fn new(status: StatusCode, code: &str, detail: impl Into<String>) -> Self {
+        Problem {
+            type_uri: "about:blank".to_string(),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            code: code.to_string(),
+            detail: Some(detail.into()),
+            instance: None,
+            request_id: None,
+            errors: None,
+            retry_after: None,
+        }
+    }
+
+    /// Describes `error` to the client. Details of server-side failures are
+    /// not exposed.
+    pub fn from_error(error: &AppError) -> Self {
+        let status = error.status();
+        let detail = match error {
+            AppError::Throttled(_) => {
+                "Too many requests to the storage backend; try again later".to_string()
+            }
+            AppError::Unavailable(_) => {
+                "The storage backend is temporarily unavailable".to_string()
+            }
+            _ if status.is_server_error() => {
+                "The server could not complete the request".to_string()
+            }
+            _ => error.to_string(),
+        };
+        let mut problem = Problem::new(status, error.code(), detail);
+        match error {
+            AppError::Validation(errors) => problem.errors = Some(errors.clone()),
+            AppError::Throttled(_) | AppError::Unavailable(_) => {
+                problem.retry_after = Some(RETRY_AFTER_SECONDS)
+            }
+            _ => {}
+        }
+        problem
+    }
+
+    /// Builds the response. The problem is also kept in the response's
+    /// extensions so `handle_request` can fill in `instance` and
+    /// `request_id` without reparsing the body.
+    pub fn into_response(self) -> Result<Response<Body>, AppError> {
+        let body = serde_json::to_string(&self)?;
+        let mut builder = Response::builder()
+            .status(self.status)
+            .header("Content-Type", PROBLEM_CONTENT_TYPE);
+        if let Some(seconds) = self.retry_after {
+            builder = builder.header("Retry-After", seconds);
+        }
+        let mut response = builder.body(Body::from(body)).map_err(AppError::from)?;
+        response.extensions_mut().insert(self);
+        Ok(response)
+    }
+}
+
+/// Stable code for a status that was produced without an `AppError`.
+pub fn code_for_status(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "bad_request",
+        StatusCode::UNAUTHORIZED => "unauthorized",
+        StatusCode::FORBIDDEN => "forbidden",
+        StatusCode::NOT_FOUND => "not_found",
+        StatusCode::METHOD_NOT_ALLOWED => "method_not_allowed",
+        StatusCode::CONFLICT => "conflict",
+        StatusCode::PRECONDITION_FAILED => "precondition_failed",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+        StatusCode::UNPROCESSABLE_ENTITY => "validation_failed",
+        StatusCode::TOO_MANY_REQUESTS => "throttled",
+        StatusCode::SERVICE_UNAVAILABLE => "service_unavailable",
+        status if status.is_server_error() => "internal_error",
+        _ => "error",
+    }
+}
+
+/// Identifies a request in logs and error responses: the caller's
+/// `X-Request-Id` if it sent a reasonable one, else the Lambda request ID,
+/// else a fresh UUID.
+pub fn request_id(event: &Request) -> String {
+    let supplied = event
+        .headers()
+        .get("X-Request-Id")
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LENGTH);
+    if let Some(id) = supplied {
+        return id.to_string();
+    }
+    match event.lambda_context_ref() {
+        Some(context) if !context.request_id.is_empty() => context.request_id.clone(),
+        _ => uuid::Uuid::new_v4().to_string(),
+    }
+}
+
+/// Tags a response with its request ID and, for a problem, the request path.
+pub fn annotate(
+    mut response: Response<Body>,
+    instance: &str,
+    request_id: &str,
+) -> Result<Response<Body>, AppError> {
+    if let Some(mut problem) = response.extensions_mut().remove::<Problem>() {
+        problem.instance = Some(instance.to_string());
+        problem.request_id = Some(request_id.to_string());
+        *response.body_mut() = Body::from(serde_json::to_string(&problem)?);
+    }
+    response
+        .headers_mut()
+        .insert("X-Request-Id", HeaderValue::from_str(request_id)?);
+    Ok(response)
+}
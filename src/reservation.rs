@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::error::AppError;
+use crate::handlers::{Wishlist, WishlistItem};
+
+/// Prefix of the reserver of a reservation made through a share link, by a
+/// guest known only by the name they gave.
+pub const GUEST_PREFIX: &str = "guest:";
+
+/// Header a share link guest sends their claim token in to change or
+/// release their reservation.
+pub const CLAIM_HEADER: &str = "X-Claim-Token";
+
+/// A gift-giver's claim on some of an item's quantity. Kept outside the
+/// wishlist document, so the owner's edits can't overwrite it and the owner's
+/// view of the list never shows it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reservation {
+    pub item_id: String,
+    /// Subject of the reserver, or `guest:<name>` for a share link guest.
+    pub reserver: String,
+    pub quantity: u32,
+    /// Whether the reserver has bought the gift.
+    #[serde(default)]
+    pub purchased: bool,
+    /// Unix time in seconds of the first reservation; kept when it changes.
+    pub reserved_at: u64,
+    /// SHA-256 of the claim token a share link guest got with their
+    /// reservation. Never serialized; only the token changes or releases it.
+    #[serde(default, skip_serializing)]
+    pub claim_hash: Option<String>,
+}
+
+impl Reservation {
+    /// Reserver recorded for a guest who reserves through a share link.
+    pub fn guest(name: &str) -> String {
+        format!("{}{}", GUEST_PREFIX, name.trim())
+    }
+}
+
+/// Response to a guest's reservation through a share link. The claim token
+/// is only included when the reservation is first made; it isn't stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuestReservation {
+    #[serde(flatten)]
+    pub reservation: Reservation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub claim_token: Option<String>,
+}
+
+/// Body of `PUT .../items/{item_id}/reservation`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReservationRequest {
+    #[serde(default = "default_quantity")]
+    pub quantity: u32,
+    #[serde(default)]
+    pub purchased: bool,
+    /// Who is reserving, for share link guests and when authentication is
+    /// disabled. Ignored for authenticated callers.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+fn default_quantity() -> u32 {
+    1
+}
+
+impl Default for ReservationRequest {
+    fn default() -> Self {
+        ReservationRequest {
+            quantity: default_quantity(),
+            purchased: false,
+            name: None,
+        }
+    }
+}
+
+/// How much of an item is spoken for, as a share link shows it: counts only,
+/// not who reserved.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReservationSummary {
+    pub reserved: u32,
+    pub purchased: u32,
+}
+
+/// An item as a share link shows it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReservedItem {
+    #[serde(flatten)]
+    pub item: WishlistItem,
+    pub reservations: ReservationSummary,
+}
+
+/// Totals `reservations` per item.
+pub fn summarize(reservations: &[Reservation]) -> HashMap<&str, ReservationSummary> {
+    let mut summaries: HashMap<&str, ReservationSummary> = HashMap::new();
+    for reservation in reservations {
+        let summary = summaries.entry(&reservation.item_id).or_default();
+        summary.reserved += reservation.quantity;
+        if reservation.purchased {
+            summary.purchased += reservation.quantity;
+        }
+    }
+    summaries
+}
+
+/// The `Conflict` for a reservation that asks for more than the
+/// `available` units nobody else has reserved.
+pub fn unavailable(available: u32) -> AppError {
+    AppError::Conflict(match available {
+        0 => "This item is already fully reserved".to_string(),
+        n => format!("Only {} of this item can still be reserved", n),
+    })
+}
+
+/// The `Forbidden` for changing or releasing a reservation without the
+/// claim token it was made with.
+pub fn claimed() -> AppError {
+    AppError::Forbidden(format!(
+        "This reservation was made by someone else; send its claim token in {}",
+        CLAIM_HEADER
+    ))
+}
+
+/// Drops reservations of items no longer on the wishlist.
+pub fn current(wishlist: &Wishlist, reservations: Vec<Reservation>) -> Vec<Reservation> {
+    reservations
+        .into_iter()
+        .filter(|reservation| {
+            wishlist
+                .items
+                .iter()
+                .any(|item| item.id == reservation.item_id)
+        })
+        .collect()
+}
@@ -0,0 +1,212 @@
+use lambda_http::http::Method;
+use percent_encoding::percent_decode_str;
+
+/// API Gateway stage prefix, present when the API is called through its
+/// default stage URL.
+const STAGE_PREFIX: &str = "/prod";
+
+/// Every endpoint the API serves, with its decoded path parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Route {
+    Health,
+    Wishlists,
+    OwnerWishlists {
+        owner: String,
+    },
+    Wishlist {
+        id: String,
+    },
+    WishlistItems {
+        wishlist_id: String,
+    },
+    WishlistItem {
+        wishlist_id: String,
+        item_id: String,
+    },
+    /// The caller's reservation of an item.
+    Reservation {
+        wishlist_id: String,
+        item_id: String,
+    },
+    Reservations {
+        wishlist_id: String,
+    },
+    /// Contributions toward an item as a group gift.
+    Pledges {
+        wishlist_id: String,
+        item_id: String,
+    },
+    Collaborators {
+        wishlist_id: String,
+    },
+    Collaborator {
+        wishlist_id: String,
+        subject: String,
+    },
+    /// Wishlists shared with `subject`.
+    CollaboratorWishlists {
+        subject: String,
+    },
+    Shares {
+        wishlist_id: String,
+    },
+    Share {
+        wishlist_id: String,
+        id: String,
+    },
+    /// A wishlist as seen through a share link.
+    Shared {
+        token: String,
+    },
+    /// A share link guest's reservation of an item.
+    SharedReservation {
+        token: String,
+        item_id: String,
+    },
+    Exchanges,
+    Exchange {
+        id: String,
+    },
+    ExchangeDraw {
+        id: String,
+    },
+    /// The caller's own assignment in an exchange.
+    ExchangeAssignment {
+        id: String,
+    },
+    ApiKeys,
+    ApiKey {
+        id: String,
+    },
+}
+
+impl Route {
+    /// Matches a request path against the known routes. A trailing slash is
+    /// ignored; empty or undecodable segments match nothing.
+    pub fn recognize(path: &str) -> Option<Route> {
+        let path = match path.strip_prefix(STAGE_PREFIX) {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => rest,
+            _ => path,
+        };
+        let path = path.strip_prefix('/')?;
+        let path = path.strip_suffix('/').unwrap_or(path);
+        let segments = path
+            .split('/')
+            .map(|segment| {
+                percent_decode_str(segment)
+                    .decode_utf8()
+                    .ok()
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.into_owned())
+            })
+            .collect::<Option<Vec<String>>>()?;
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+        let route = match segments.as_slice() {
+            ["health"] => Route::Health,
+            ["wishlists"] => Route::Wishlists,
+            ["owners", owner, "wishlists"] => Route::OwnerWishlists {
+                owner: owner.to_string(),
+            },
+            ["wishlists", id] => Route::Wishlist { id: id.to_string() },
+            ["wishlists", id, "items"] => Route::WishlistItems {
+                wishlist_id: id.to_string(),
+            },
+            ["wishlists", id, "items", item_id] => Route::WishlistItem {
+                wishlist_id: id.to_string(),
+                item_id: item_id.to_string(),
+            },
+            ["wishlists", id, "items", item_id, "reservation"] => Route::Reservation {
+                wishlist_id: id.to_string(),
+                item_id: item_id.to_string(),
+            },
+            ["wishlists", id, "reservations"] => Route::Reservations {
+                wishlist_id: id.to_string(),
+            },
+            ["wishlists", id, "items", item_id, "pledges"] => Route::Pledges {
+                wishlist_id: id.to_string(),
+                item_id: item_id.to_string(),
+            },
+            ["wishlists", id, "collaborators"] => Route::Collaborators {
+                wishlist_id: id.to_string(),
+            },
+            ["wishlists", id, "collaborators", subject] => Route::Collaborator {
+                wishlist_id: id.to_string(),
+                subject: subject.to_string(),
+            },
+            ["collaborators", subject, "wishlists"] => Route::CollaboratorWishlists {
+                subject: subject.to_string(),
+            },
+            ["wishlists", id, "shares"] => Route::Shares {
+                wishlist_id: id.to_string(),
+            },
+            ["wishlists", wishlist_id, "shares", id] => Route::Share {
+                wishlist_id: wishlist_id.to_string(),
+                id: id.to_string(),
+            },
+            ["shared", token] => Route::Shared {
+                token: token.to_string(),
+            },
+            ["shared", token, "items", item_id, "reservation"] => Route::SharedReservation {
+                token: token.to_string(),
+                item_id: item_id.to_string(),
+            },
+            ["exchanges"] => Route::Exchanges,
+            ["exchanges", id] => Route::Exchange { id: id.to_string() },
+            ["exchanges", id, "draw"] => Route::ExchangeDraw { id: id.to_string() },
+            ["exchanges", id, "assignment"] => Route::ExchangeAssignment { id: id.to_string() },
+            ["api-keys"] => Route::ApiKeys,
+            ["api-keys", id] => Route::ApiKey { id: id.to_string() },
+            _ => return None,
+        };
+        Some(route)
+    }
+
+    /// Methods with a handler on this route. On top of these, `OPTIONS` is
+    /// served for every route and `HEAD` for every route that has `GET`.
+    pub fn methods(&self) -> &'static [Method] {
+        match self {
+            Route::Health
+            | Route::OwnerWishlists { .. }
+            | Route::Reservations { .. }
+            | Route::Collaborators { .. }
+            | Route::CollaboratorWishlists { .. }
+            | Route::Shared { .. }
+            | Route::ExchangeAssignment { .. } => &[Method::GET],
+            Route::Collaborator { .. }
+            | Route::Reservation { .. }
+            | Route::SharedReservation { .. } => &[Method::PUT, Method::DELETE],
+            Route::Wishlist { .. } => &[Method::GET, Method::PUT, Method::PATCH, Method::DELETE],
+            Route::Wishlists => &[Method::GET, Method::POST, Method::PUT, Method::DELETE],
+            Route::WishlistItems { .. } | Route::Exchanges | Route::ExchangeDraw { .. } => {
+                &[Method::POST]
+            }
+            Route::Exchange { .. } => &[Method::GET, Method::DELETE],
+            Route::WishlistItem { .. } => &[Method::GET, Method::PATCH, Method::DELETE],
+            Route::Pledges { .. } | Route::Shares { .. } | Route::ApiKeys => {
+                &[Method::GET, Method::POST]
+            }
+            Route::Share { .. } | Route::ApiKey { .. } => &[Method::DELETE],
+        }
+    }
+
+    /// Routes served without authentication: health checks, and share links,
+    /// which carry their own token.
+    pub fn is_public(&self) -> bool {
+        matches!(
+            self,
+            Route::Health | Route::Shared { .. } | Route::SharedReservation { .. }
+        )
+    }
+
+    /// Value for the `Allow` header.
+    pub fn allow(&self) -> String {
+        let methods = self.methods();
+        let mut allowed: Vec<&str> = methods.iter().map(Method::as_str).collect();
+        if methods.contains(&Method::GET) {
+            allowed.push(Method::HEAD.as_str());
+        }
+        allowed.push(Method::OPTIONS.as_str());
+        allowed.join(", ")
+    }
+}
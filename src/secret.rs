@@ -0,0 +1,73 @@
+use sha2::{Digest, Sha256};
+
+/// PBKDF2 rounds for new password hashes. Stored with each hash, so it can be
+/// raised without invalidating existing ones.
+const PASSWORD_ITERATIONS: u32 = 100_000;
+
+/// 64 random hex characters (244 bits of entropy), for API key secrets and
+/// share tokens.
+pub fn random_token() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// Hex-encoded SHA-256. Enough for random tokens, which can't be guessed; use
+/// `hash_password` for anything a person chose.
+pub fn sha256_hex(value: &str) -> String {
+    hex(&Sha256::digest(value.as_bytes()))
+}
+
+/// Compares two strings without stopping at the first difference.
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+/// Salted PBKDF2-HMAC-SHA256, stored as
+/// `pbkdf2-sha256$<iterations>$<salt>$<hash>`.
+pub fn hash_password(password: &str) -> String {
+    let salt = uuid::Uuid::new_v4().simple().to_string();
+    let hash = pbkdf2(password, &salt, PASSWORD_ITERATIONS);
+    format!(
+        "pbkdf2-sha256${}${}${}",
+        PASSWORD_ITERATIONS,
+        salt,
+        hex(&hash)
+    )
+}
+
+/// Checks `password` against a hash from `hash_password`. Malformed hashes
+/// match nothing.
+pub fn verify_password(password: &str, stored: &str) -> bool {
+    let mut parts = stored.split('$');
+    let (Some("pbkdf2-sha256"), Some(iterations), Some(salt), Some(hash), None) = (
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+    ) else {
+        return false;
+    };
+    match iterations.parse() {
+        Ok(iterations) if iterations > 0 => {
+            constant_time_eq(&hex(&pbkdf2(password, salt, iterations)), hash)
+        }
+        _ => false,
+    }
+}
+
+/// PBKDF2-HMAC-SHA256 (RFC 8018) with a 32-byte output.
+fn pbkdf2(password: &str, salt: &str, iterations: u32) -> [u8; 32] {
+    pbkdf2::pbkdf2_hmac_array::<Sha256, 32>(password.as_bytes(), salt.as_bytes(), iterations)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
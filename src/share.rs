@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+
+use crate::handlers::Wishlist;
+use crate::reservation::{summarize, Reservation, ReservedItem};
+use crate::secret::{hash_password, random_token, sha256_hex, verify_password};
+use crate::utils::now;
+
+/// Header carrying the password of a protected share link.
+pub const PASSWORD_HEADER: &str = "X-Share-Password";
+
+/// A link giving read-only access to a wishlist without an account. The
+/// token itself is never stored: `id` is its SHA-256, which both finds the
+/// link and proves the token, but can't be turned back into a working link.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ShareLink {
+    pub id: String,
+    pub wishlist_id: String,
+    /// Who made the link, when authentication is enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
+    /// Timestamps are Unix times in seconds.
+    pub created_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    #[serde(skip)]
+    pub password_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<u64>,
+}
+
+impl ShareLink {
+    /// Makes a new link to `wishlist_id`, returning it with its token.
+    pub fn generate(
+        wishlist_id: &str,
+        created_by: Option<String>,
+        request: &ShareRequest,
+    ) -> (ShareLink, String) {
+        let token = random_token();
+        let link = ShareLink {
+            id: sha256_hex(&token),
+            wishlist_id: wishlist_id.to_string(),
+            created_by,
+            created_at: now(),
+            expires_at: request.expires_at,
+            password_hash: request.password.as_deref().map(hash_password),
+            revoked_at: None,
+        };
+        (link, token)
+    }
+
+    /// The ID of the link a token belongs to.
+    pub fn id_for_token(token: &str) -> String {
+        sha256_hex(token)
+    }
+
+    /// Whether the link still works at Unix time `now`.
+    pub fn is_active(&self, now: u64) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires| now < expires)
+    }
+
+    /// Whether `password` opens the link. Links without a password open with
+    /// any.
+    pub fn accepts(&self, password: Option<&str>) -> bool {
+        match (&self.password_hash, password) {
+            (None, _) => true,
+            (Some(hash), Some(password)) => verify_password(password, hash),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// Body of `POST /wishlists/{id}/shares`. Both fields are optional.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ShareRequest {
+    /// Unix time after which the link stops working.
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// Response to `POST /wishlists/{id}/shares`, the only time the token is
+/// shown.
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedShareLink {
+    #[serde(flatten)]
+    pub link: ShareLink,
+    pub has_password: bool,
+    pub token: String,
+    /// Path to hand out, relative to the API's base URL.
+    pub url: String,
+}
+
+/// A link as listed to the people managing the wishlist.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShareLinkSummary {
+    #[serde(flatten)]
+    pub link: ShareLink,
+    pub has_password: bool,
+}
+
+impl From<ShareLink> for ShareLinkSummary {
+    fn from(link: ShareLink) -> Self {
+        ShareLinkSummary {
+            has_password: link.password_hash.is_some(),
+            link,
+        }
+    }
+}
+
+/// What a share link shows: the list's name and items, without its owner,
+/// ID or version, and how much of each item is already reserved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedWishlist {
+    pub name: String,
+    pub items: Vec<ReservedItem>,
+}
+
+impl SharedWishlist {
+    pub fn new(wishlist: Wishlist, reservations: &[Reservation]) -> Self {
+        let summaries = summarize(reservations);
+        let items = wishlist
+            .items
+            .into_iter()
+            .map(|item| ReservedItem {
+                reservations: summaries.get(item.id.as_str()).copied().unwrap_or_default(),
+                item,
+            })
+            .collect();
+        SharedWishlist {
+            name: wishlist.name,
+            items,
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use crate::auth::Authenticator;
+use crate::config::Config;
+use crate::db::WishlistStore;
+use std::sync::Arc;
+
+/// Everything a handler needs besides the request itself. Cheap to clone, so
+/// the local server hands a copy to each connection.
+#[derive(Clone)]
+pub struct AppState {
+    pub store: Arc<dyn WishlistStore>,
+    pub config: Config,
+    /// Verifies callers. `None` leaves the API open, with `owner` taken from
+    /// request bodies.
+    pub auth: Option<Arc<Authenticator>>,
+}
+
+impl AppState {
+    pub fn new(store: Arc<dyn WishlistStore>) -> Self {
+        AppState::with_config(store, Config::default())
+    }
+
+    pub fn with_config(store: Arc<dyn WishlistStore>, config: Config) -> Self {
+        AppState {
+            store,
+            config,
+            auth: None,
+        }
+    }
+
+    pub fn with_auth(mut self, auth: Authenticator) -> Self {
+        self.auth = Some(Arc::new(auth));
+        self
+    }
+}
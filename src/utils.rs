@@ -1,8 +1,12 @@
 use crate::error::AppError;
+use crate::problem::{code_for_status, Problem};
+use crate::validation::FieldError;
 use lambda_http::http::StatusCode;
-use lambda_http::{Body, Response};
+use lambda_http::{Body, Request, Response};
+use log::error;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
 use serde::Serialize;
-use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub fn build_response<T: Serialize>(
     status_code: StatusCode,
@@ -23,15 +27,146 @@ pub fn build_response<T: Serialize>(
     Ok(response)
 }
 
+/// Problem response for an error detected in a handler. The `code` follows
+/// from the status.
 pub fn build_error_response(
     status_code: StatusCode,
     message: &str,
 ) -> Result<Response<Body>, AppError> {
-    let response = Response::builder()
-        .status(status_code)
-        .header("Content-Type", "application/json")
-        .body(json!({"error": message}).to_string().into())
-        .map_err(AppError::from)?; // Convert the error from body() to AppError
+    Problem::new(status_code, code_for_status(status_code), message).into_response()
+}
 
-    Ok(response)
+/// 422 listing every field that failed validation.
+pub fn build_validation_response(errors: &[FieldError]) -> Result<Response<Body>, AppError> {
+    build_app_error_response(AppError::Validation(errors.to_vec()))
+}
+
+/// Problem response for any `AppError`, with the status and code it maps
+/// to. Server-side failures are logged here, since their details are not
+/// sent to the client.
+pub fn build_app_error_response(e: AppError) -> Result<Response<Body>, AppError> {
+    if e.status().is_server_error() {
+        error!("Request failed: {:?}", e);
+    }
+    Problem::from_error(&e).into_response()
+}
+
+/// Returns the first value of `name` in a URL query string, decoded.
+pub fn query_param(query: Option<&str>, name: &str) -> Option<String> {
+    form_urlencoded::parse(query.unwrap_or_default().as_bytes())
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+/// Rebuilds a query string for the following page, keeping any filters from
+/// the original request.
+pub fn next_page_query(query: Option<&str>, limit: usize, cursor: &str) -> String {
+    let mut serializer = form_urlencoded::Serializer::new(String::new());
+    for (key, value) in form_urlencoded::parse(query.unwrap_or_default().as_bytes()) {
+        if key != "limit" && key != "cursor" {
+            serializer.append_pair(&key, &value);
+        }
+    }
+    serializer
+        .append_pair("limit", &limit.to_string())
+        .append_pair("cursor", cursor)
+        .finish()
+}
+
+/// Characters that can't appear literally in a single URL path segment.
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Path of a single wishlist, as used in `Location` headers.
+pub fn wishlist_location(id: &str) -> String {
+    format!("/wishlists/{}", utf8_percent_encode(id, PATH_SEGMENT))
+}
+
+/// Path of an API key.
+pub fn api_key_location(id: &str) -> String {
+    format!("/api-keys/{}", utf8_percent_encode(id, PATH_SEGMENT))
+}
+
+/// Path of a gift exchange.
+pub fn exchange_location(id: &str) -> String {
+    format!("/exchanges/{}", utf8_percent_encode(id, PATH_SEGMENT))
+}
+
+/// Path of one of a wishlist's share links.
+pub fn share_location(wishlist_id: &str, id: &str) -> String {
+    format!(
+        "{}/shares/{}",
+        wishlist_location(wishlist_id),
+        utf8_percent_encode(id, PATH_SEGMENT)
+    )
+}
+
+/// Public path of a share link's wishlist.
+pub fn shared_location(token: &str) -> String {
+    format!("/shared/{}", utf8_percent_encode(token, PATH_SEGMENT))
+}
+
+/// Path of a single item of a wishlist.
+pub fn item_location(wishlist_id: &str, item_id: &str) -> String {
+    format!(
+        "{}/items/{}",
+        wishlist_location(wishlist_id),
+        utf8_percent_encode(item_id, PATH_SEGMENT)
+    )
+}
+
+/// Parsed `If-Match` request header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IfMatch {
+    Any,
+    Tags(Vec<String>),
+}
+
+impl IfMatch {
+    pub fn from_request(event: &Request) -> Option<Self> {
+        let value = event.headers().get("If-Match")?.to_str().ok()?.trim();
+        if value == "*" {
+            return Some(IfMatch::Any);
+        }
+        Some(IfMatch::Tags(
+            value.split(',').map(|tag| tag.trim().to_string()).collect(),
+        ))
+    }
+
+    /// The version a conditional write must find, or `None` when any existing
+    /// wishlist will do. Only strong tags we issued (`"<version>"`) can match,
+    /// and since a write is checked against a single version, a list naming
+    /// several versions is treated as unsatisfiable.
+    pub fn expected_version(&self) -> Result<Option<u64>, AppError> {
+        match self {
+            IfMatch::Any => Ok(None),
+            IfMatch::Tags(tags) => {
+                let versions: Vec<u64> = tags
+                    .iter()
+                    .filter_map(|tag| tag.strip_prefix('"')?.strip_suffix('"')?.parse().ok())
+                    .collect();
+                match versions.as_slice() {
+                    [version] => Ok(Some(*version)),
+                    _ => Err(AppError::PreconditionFailed),
+                }
+            }
+        }
+    }
+}
+
+/// The current Unix time in seconds.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
 }
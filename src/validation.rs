@@ -0,0 +1,435 @@
+use log::warn;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::api_key::ApiKeyRequest;
+use crate::error::AppError;
+use crate::exchange::ExchangeRequest;
+use crate::handlers::{Wishlist, WishlistItem};
+use crate::pledge::PledgeRequest;
+use crate::reservation::ReservationRequest;
+use crate::share::ShareRequest;
+
+/// Largest item DynamoDB will store, attribute names included.
+pub const DYNAMODB_ITEM_LIMIT: usize = 400 * 1024;
+
+/// Longest `url` accepted on an item.
+const MAX_URL_LENGTH: usize = 2048;
+
+/// Longest `message` accepted on a pledge.
+const MAX_MESSAGE_LENGTH: usize = 500;
+
+/// Bounds on what a client may store. The serialized wishlist is used as an
+/// estimate of its size in the table, so `max_wishlist_bytes` defaults to
+/// well under DynamoDB's limit and is never allowed above it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// Characters in a wishlist or item name.
+    pub max_name_length: usize,
+    /// Characters in an owner.
+    pub max_owner_length: usize,
+    pub max_items: usize,
+    /// Serialized size of a single item.
+    pub max_item_bytes: usize,
+    /// Serialized size of a whole wishlist.
+    pub max_wishlist_bytes: usize,
+}
+
+impl Limits {
+    /// Reads `MAX_NAME_LENGTH`, `MAX_OWNER_LENGTH`, `MAX_ITEMS`,
+    /// `MAX_ITEM_BYTES` and `MAX_WISHLIST_BYTES`, keeping the default for any
+    /// that is unset or not a positive number.
+    pub fn from_env() -> Self {
+        let defaults = Limits::default();
+        let limits = Limits {
+            max_name_length: env_limit("MAX_NAME_LENGTH", defaults.max_name_length),
+            max_owner_length: env_limit("MAX_OWNER_LENGTH", defaults.max_owner_length),
+            max_items: env_limit("MAX_ITEMS", defaults.max_items),
+            max_item_bytes: env_limit("MAX_ITEM_BYTES", defaults.max_item_bytes),
+            max_wishlist_bytes: env_limit("MAX_WISHLIST_BYTES", defaults.max_wishlist_bytes),
+        };
+        if limits.max_wishlist_bytes > DYNAMODB_ITEM_LIMIT {
+            warn!(
+                "MAX_WISHLIST_BYTES {} exceeds the DynamoDB item limit, using {}",
+                limits.max_wishlist_bytes, DYNAMODB_ITEM_LIMIT
+            );
+            return Limits {
+                max_wishlist_bytes: DYNAMODB_ITEM_LIMIT,
+                ..limits
+            };
+        }
+        limits
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_name_length: 200,
+            max_owner_length: 200,
+            max_items: 500,
+            max_item_bytes: 4 * 1024,
+            max_wishlist_bytes: 350 * 1024,
+        }
+    }
+}
+
+fn env_limit(name: &str, default: usize) -> usize {
+    match std::env::var(name) {
+        Ok(value) => match value.parse() {
+            Ok(limit) if limit > 0 => limit,
+            _ => {
+                warn!("Ignoring invalid {}={:?}, using {}", name, value, default);
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+/// One problem with a request body: where it is, which rule it broke and a
+/// message for humans. `field` uses the `items[0].name` notation, with `.`
+/// for the body as a whole.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub rule: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, rule: &str, message: impl Into<String>) -> Self {
+        FieldError {
+            field: field.into(),
+            rule: rule.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Deserializes a JSON request body. Syntax errors are a `BadRequest`; a
+/// body of the wrong shape is a `Validation` error naming the field.
+pub fn parse_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, AppError> {
+    let deserializer = &mut serde_json::Deserializer::from_slice(body);
+    serde_path_to_error::deserialize(deserializer).map_err(deserialize_error)
+}
+
+/// Like `parse_body`, for a document that has already been parsed, such as
+/// the result of applying a patch.
+pub fn parse_value<T: DeserializeOwned>(value: serde_json::Value) -> Result<T, AppError> {
+    serde_path_to_error::deserialize(value).map_err(deserialize_error)
+}
+
+fn deserialize_error(e: serde_path_to_error::Error<serde_json::Error>) -> AppError {
+    let path = e.path().to_string();
+    let inner = e.into_inner();
+    if !inner.is_data() {
+        return AppError::BadRequest(format!("Request body is not valid JSON: {}", inner));
+    }
+    let message = inner.to_string();
+    let message = match message.rsplit_once(" at line ") {
+        Some((message, _)) => message.to_string(),
+        None => message,
+    };
+    let missing = message
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.split('`').next());
+    let error = match missing {
+        Some(name) if path == "." => FieldError::new(name, "required", "is required"),
+        Some(name) => FieldError::new(format!("{}.{}", path, name), "required", "is required"),
+        None if message.contains("untagged enum ItemInput") => FieldError::new(
+            path,
+            "type",
+            "must be a name or an item object with a valid name, quantity and priority",
+        ),
+        None => FieldError::new(path, "type", message),
+    };
+    AppError::Validation(vec![error])
+}
+
+/// Checks a whole wishlist against `limits`, including every item.
+pub fn validate_wishlist(wishlist: &Wishlist, limits: &Limits) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    check_text(&mut errors, "name", &wishlist.name, limits.max_name_length);
+    check_text(
+        &mut errors,
+        "owner",
+        &wishlist.owner,
+        limits.max_owner_length,
+    );
+    if wishlist.items.len() > limits.max_items {
+        errors.push(FieldError::new(
+            "items",
+            "max_items",
+            format!("may hold at most {} items", limits.max_items),
+        ));
+    }
+    let mut ids = HashSet::new();
+    for (position, item) in wishlist.items.iter().enumerate() {
+        let field = format!("items[{}]", position);
+        if !item.id.is_empty() && !ids.insert(item.id.as_str()) {
+            errors.push(FieldError::new(
+                format!("{}.id", field),
+                "unique",
+                format!("duplicates the ID of an earlier item ({})", item.id),
+            ));
+        }
+        errors.extend(validate_item(&field, item, limits));
+    }
+    let size = serde_json::to_vec(wishlist).map_or(0, |bytes| bytes.len());
+    if size > limits.max_wishlist_bytes {
+        errors.push(FieldError::new(
+            ".",
+            "max_size",
+            format!(
+                "wishlist is {} bytes, more than the {} allowed",
+                size, limits.max_wishlist_bytes
+            ),
+        ));
+    }
+    errors
+}
+
+/// Checks a single item. `field` is the item's own path, e.g. `items[3]`, or
+/// empty when the item is the whole body.
+pub fn validate_item(field: &str, item: &WishlistItem, limits: &Limits) -> Vec<FieldError> {
+    let path = |name: &str| {
+        if field.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", field, name)
+        }
+    };
+    let mut errors = Vec::new();
+    check_text(
+        &mut errors,
+        &path("name"),
+        &item.name,
+        limits.max_name_length,
+    );
+    if let Some(url) = &item.url {
+        if url.chars().count() > MAX_URL_LENGTH {
+            errors.push(FieldError::new(
+                path("url"),
+                "max_length",
+                format!("must be at most {} characters", MAX_URL_LENGTH),
+            ));
+        } else if !(url.starts_with("http://") || url.starts_with("https://")) {
+            errors.push(FieldError::new(
+                path("url"),
+                "format",
+                "must be an http or https URL",
+            ));
+        }
+    }
+    if let Some(currency) = &item.currency {
+        check_currency(&mut errors, &path("currency"), currency);
+    }
+    if item.quantity == 0 {
+        errors.push(FieldError::new(
+            path("quantity"),
+            "min",
+            "must be at least 1",
+        ));
+    }
+    let size = serde_json::to_vec(item).map_or(0, |bytes| bytes.len());
+    if size > limits.max_item_bytes {
+        errors.push(FieldError::new(
+            if field.is_empty() { "." } else { field },
+            "max_size",
+            format!(
+                "item is {} bytes, more than the {} allowed",
+                size, limits.max_item_bytes
+            ),
+        ));
+    }
+    errors
+}
+
+/// Checks the body of `POST /api-keys`.
+pub fn validate_api_key_request(request: &ApiKeyRequest, limits: &Limits) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    check_text(&mut errors, "name", &request.name, limits.max_name_length);
+    errors
+}
+
+/// Checks the body of `POST /wishlists/{id}/shares` at Unix time `now`.
+pub fn validate_share_request(
+    request: &ShareRequest,
+    limits: &Limits,
+    now: u64,
+) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    if request.expires_at.is_some_and(|expires| expires <= now) {
+        errors.push(FieldError::new(
+            "expires_at",
+            "in_past",
+            "must be in the future",
+        ));
+    }
+    if let Some(password) = &request.password {
+        check_text(&mut errors, "password", password, limits.max_name_length);
+    }
+    errors
+}
+
+/// Checks the body of a reservation. `name` is only needed when the
+/// reserver can't be told from the request.
+pub fn validate_reservation_request(
+    request: &ReservationRequest,
+    limits: &Limits,
+    needs_name: bool,
+) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    if request.quantity == 0 {
+        errors.push(FieldError::new("quantity", "min", "must be at least 1"));
+    }
+    if needs_name {
+        let name = request.name.as_deref().unwrap_or_default();
+        check_text(&mut errors, "name", name, limits.max_owner_length);
+    }
+    errors
+}
+
+/// Checks the body of `POST /exchanges`. Whether each wishlist exists and
+/// belongs to its participant is left to the handler.
+pub fn validate_exchange_request(request: &ExchangeRequest, limits: &Limits) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    check_text(&mut errors, "name", &request.name, limits.max_name_length);
+    if request.participants.len() < 2 {
+        errors.push(FieldError::new(
+            "participants",
+            "min",
+            "must have at least 2 participants",
+        ));
+    } else if request.participants.len() > limits.max_items {
+        errors.push(FieldError::new(
+            "participants",
+            "max_items",
+            format!("may have at most {} participants", limits.max_items),
+        ));
+    }
+    let subjects: HashSet<&str> = request
+        .participants
+        .iter()
+        .map(|participant| participant.subject.as_str())
+        .collect();
+    let mut seen = HashSet::new();
+    for (position, participant) in request.participants.iter().enumerate() {
+        let field = |name: &str| format!("participants[{}].{}", position, name);
+        check_text(
+            &mut errors,
+            &field("subject"),
+            &participant.subject,
+            limits.max_owner_length,
+        );
+        if !seen.insert(participant.subject.as_str()) {
+            errors.push(FieldError::new(
+                field("subject"),
+                "unique",
+                format!(
+                    "duplicates an earlier participant ({})",
+                    participant.subject
+                ),
+            ));
+        }
+        if participant.wishlist_id.trim().is_empty() {
+            errors.push(FieldError::new(
+                field("wishlist_id"),
+                "required",
+                "must not be empty",
+            ));
+        }
+        match participant.spouse.as_deref() {
+            Some(spouse) if spouse == participant.subject => {
+                errors.push(FieldError::new(
+                    field("spouse"),
+                    "invalid",
+                    "must be someone else",
+                ));
+            }
+            Some(spouse) if !subjects.contains(spouse) => {
+                errors.push(FieldError::new(
+                    field("spouse"),
+                    "unknown",
+                    "must be another participant",
+                ));
+            }
+            _ => {}
+        }
+    }
+    errors
+}
+
+/// Checks the body of `POST /wishlists/{id}/items/{item_id}/pledges` toward
+/// `item`. Without authentication the body must name the contributor.
+pub fn validate_pledge_request(
+    request: &PledgeRequest,
+    item: &WishlistItem,
+    limits: &Limits,
+    needs_contributor: bool,
+) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    if request.amount_cents == 0 {
+        errors.push(FieldError::new("amount_cents", "min", "must be at least 1"));
+    }
+    if let Some(currency) = &request.currency {
+        let before = errors.len();
+        check_currency(&mut errors, "currency", currency);
+        match &item.currency {
+            Some(expected) if errors.len() == before && currency != expected => {
+                errors.push(FieldError::new(
+                    "currency",
+                    "mismatch",
+                    format!("must be the item's currency, {}", expected),
+                ));
+            }
+            _ => {}
+        }
+    }
+    if let Some(message) = &request.message {
+        if message.chars().count() > MAX_MESSAGE_LENGTH {
+            errors.push(FieldError::new(
+                "message",
+                "max_length",
+                format!("must be at most {} characters", MAX_MESSAGE_LENGTH),
+            ));
+        }
+    }
+    if needs_contributor {
+        let contributor = request.contributor.as_deref().unwrap_or_default();
+        check_text(
+            &mut errors,
+            "contributor",
+            contributor,
+            limits.max_owner_length,
+        );
+    }
+    errors
+}
+
+/// Requires `currency` to look like an ISO 4217 code.
+fn check_currency(errors: &mut Vec<FieldError>, field: &str, currency: &str) {
+    if currency.len() != 3 || !currency.bytes().all(|b| b.is_ascii_uppercase()) {
+        errors.push(FieldError::new(
+            field,
+            "format",
+            "must be a three-letter ISO 4217 code such as USD",
+        ));
+    }
+}
+
+/// Requires `value` to have some non-whitespace text and at most `max`
+/// characters.
+fn check_text(errors: &mut Vec<FieldError>, field: &str, value: &str, max: usize) {
+    if value.trim().is_empty() {
+        errors.push(FieldError::new(field, "required", "must not be empty"));
+    } else if value.chars().count() > max {
+        errors.push(FieldError::new(
+            field,
+            "max_length",
+            format!("must be at most {} characters", max),
+        ));
+    }
+}
@@ -0,0 +1,231 @@
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use lambda_http::http::Method;
+use lambda_http::{Body, Request, Response};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use wishlist_api::auth::{Authenticator, HmacSecret};
+use wishlist_api::db::{MemoryStore, WishlistStore};
+use wishlist_api::handlers::{handle_request, Wishlist};
+use wishlist_api::state::AppState;
+
+const SECRET: &[u8] = b"test-secret";
+
+fn token(sub: &str) -> String {
+    let exp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 300;
+    encode(
+        &Header::new(Algorithm::HS256),
+        &json!({"sub": sub, "exp": exp}),
+        &EncodingKey::from_secret(SECRET),
+    )
+    .unwrap()
+}
+
+fn request(method: Method, uri: &str, body: impl Into<Body>, authorization: &str) -> Request {
+    let mut req = Request::new(body.into());
+    *req.method_mut() = method;
+    *req.uri_mut() = uri.parse().unwrap();
+    req.headers_mut()
+        .insert("Authorization", authorization.parse().unwrap());
+    req
+}
+
+fn with_key(method: Method, uri: &str, body: impl Into<Body>, key: &str) -> Request {
+    let mut req = Request::new(body.into());
+    *req.method_mut() = method;
+    *req.uri_mut() = uri.parse().unwrap();
+    req.headers_mut().insert("X-Api-Key", key.parse().unwrap());
+    req
+}
+
+fn state() -> (AppState, Arc<MemoryStore>) {
+    let store = Arc::new(MemoryStore::new());
+    let state = AppState::new(store.clone())
+        .with_auth(Authenticator::new(vec![Arc::new(HmacSecret::new(SECRET))]));
+    (state, store)
+}
+
+fn json_body(res: &Response<Body>) -> Value {
+    serde_json::from_slice(res.body()).unwrap()
+}
+
+/// Creates a key as `owner` and returns the created key's JSON.
+async fn create_key(state: &AppState, owner: &str, scope: &str) -> Value {
+    let body = json!({"name": "script", "scope": scope});
+    let res = handle_request(
+        request(
+            Method::POST,
+            "/api-keys",
+            body.to_string(),
+            &format!("Bearer {}", token(owner)),
+        ),
+        state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 201);
+    assert_eq!(res.headers()["Cache-Control"], "no-store");
+    let created = json_body(&res);
+    assert_eq!(
+        res.headers()["Location"],
+        format!("/api-keys/{}", created["id"].as_str().unwrap()).as_str()
+    );
+    created
+}
+
+#[tokio::test]
+async fn test_created_key_acts_as_its_owner() {
+    let (state, store) = state();
+    let created = create_key(&state, "alice", "read-write").await;
+    let key = created["key"].as_str().unwrap();
+    assert!(key.starts_with("wl_"));
+    assert_eq!(created["scope"], "read-write");
+    assert!(created.get("secret_hash").is_none());
+
+    let body = json!({"name": "Birthday", "items": []});
+    let res = handle_request(
+        request(
+            Method::POST,
+            "/wishlists",
+            body.to_string(),
+            &format!("ApiKey {}", key),
+        ),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 201);
+    let wishlist: Wishlist = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(wishlist.owner, "alice");
+
+    // Only the hash is stored, and use is recorded.
+    let id = created["id"].as_str().unwrap().to_string();
+    let stored = store.get_api_key(id).await.unwrap().unwrap();
+    assert!(!stored.secret_hash.contains(&key[key.len() - 16..]));
+    assert!(stored.last_used_at.is_some());
+}
+
+#[tokio::test]
+async fn test_read_only_key_cannot_write() {
+    let (state, _) = state();
+    let alice = format!("Bearer {}", token("alice"));
+    let body = json!({"name": "Birthday", "items": []});
+    let res = handle_request(
+        request(Method::POST, "/wishlists", body.to_string(), &alice),
+        &state,
+    )
+    .await
+    .unwrap();
+    let wishlist: Wishlist = serde_json::from_slice(res.body()).unwrap();
+    let uri = format!("/wishlists/{}", wishlist.id);
+
+    let created = create_key(&state, "alice", "read-only").await;
+    let key = created["key"].as_str().unwrap();
+    let res = handle_request(with_key(Method::GET, &uri, "", key), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let res = handle_request(with_key(Method::DELETE, &uri, "", key), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 403);
+    assert_eq!(json_body(&res)["code"], "forbidden");
+
+    // Keys can't manage keys, whatever their scope.
+    let writer = create_key(&state, "alice", "read-write").await;
+    let res = handle_request(
+        with_key(
+            Method::GET,
+            "/api-keys",
+            "",
+            writer["key"].as_str().unwrap(),
+        ),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 403);
+}
+
+#[tokio::test]
+async fn test_list_and_revoke_keys() {
+    let (state, _) = state();
+    let alice = format!("Bearer {}", token("alice"));
+    let bob = format!("Bearer {}", token("bob"));
+    let created = create_key(&state, "alice", "read-only").await;
+    create_key(&state, "bob", "read-only").await;
+    let id = created["id"].as_str().unwrap();
+    let key = created["key"].as_str().unwrap();
+
+    let res = handle_request(request(Method::GET, "/api-keys", "", &alice), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let listed = json_body(&res);
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+    assert_eq!(listed[0]["id"], id);
+    assert!(listed[0].get("key").is_none());
+
+    let uri = format!("/api-keys/{}", id);
+    let res = handle_request(request(Method::DELETE, &uri, "", &bob), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 404);
+    let res = handle_request(request(Method::DELETE, &uri, "", &alice), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 204);
+
+    let res = handle_request(with_key(Method::GET, "/wishlists", "", key), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 401);
+    let res = handle_request(request(Method::GET, "/api-keys", "", &alice), &state)
+        .await
+        .unwrap();
+    assert!(json_body(&res)[0]["revoked_at"].is_u64());
+}
+
+#[tokio::test]
+async fn test_unknown_or_forged_keys_are_rejected() {
+    let (state, _) = state();
+    let created = create_key(&state, "alice", "read-only").await;
+    let id = created["id"].as_str().unwrap();
+    for key in [
+        "not-a-key".to_string(),
+        format!("wl_{}.{}", id, "0".repeat(64)),
+        format!("wl_{}.secret", uuid::Uuid::new_v4().simple()),
+    ] {
+        let res = handle_request(with_key(Method::GET, "/wishlists", "", &key), &state)
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 401, "{}", key);
+    }
+}
+
+#[tokio::test]
+async fn test_key_name_is_validated() {
+    let (state, _) = state();
+    let alice = format!("Bearer {}", token("alice"));
+    let body = json!({"name": " ", "scope": "admin"});
+    let res = handle_request(
+        request(Method::POST, "/api-keys", body.to_string(), &alice),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 422);
+    let body = json!({"name": " "});
+    let res = handle_request(
+        request(Method::POST, "/api-keys", body.to_string(), &alice),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 422);
+    assert_eq!(json_body(&res)["errors"][0]["field"], "name");
+}
@@ -0,0 +1,224 @@
+use httpmock::prelude::*;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use lambda_http::http::Method;
+use lambda_http::{Body, Request};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use wishlist_api::auth::{Authenticator, HmacSecret, KeySource, RemoteJwks, StaticJwks};
+use wishlist_api::db::MemoryStore;
+use wishlist_api::handlers::{handle_request, Wishlist};
+use wishlist_api::state::AppState;
+
+const SECRET: &[u8] = b"test-secret";
+const JWKS: &str = include_str!("fixtures/jwks.json");
+const RSA_KEY: &[u8] = include_bytes!("fixtures/jwt_rsa_private.pem");
+const EC_KEY: &[u8] = include_bytes!("fixtures/jwt_ec_private.pem");
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn claims(sub: &str) -> Value {
+    json!({"sub": sub, "exp": now() + 300, "roles": ["member"]})
+}
+
+fn hs256(claims: &Value) -> String {
+    encode(
+        &Header::new(Algorithm::HS256),
+        claims,
+        &EncodingKey::from_secret(SECRET),
+    )
+    .unwrap()
+}
+
+fn signed(alg: Algorithm, kid: Option<&str>, claims: &Value) -> String {
+    let key = match alg {
+        Algorithm::RS256 => EncodingKey::from_rsa_pem(RSA_KEY).unwrap(),
+        Algorithm::ES256 => EncodingKey::from_ec_pem(EC_KEY).unwrap(),
+        _ => unreachable!(),
+    };
+    let mut header = Header::new(alg);
+    header.kid = kid.map(str::to_string);
+    encode(&header, claims, &key).unwrap()
+}
+
+fn request(method: Method, uri: &str, body: impl Into<Body>, token: Option<&str>) -> Request {
+    let mut req = Request::new(body.into());
+    *req.method_mut() = method;
+    *req.uri_mut() = uri.parse().unwrap();
+    if let Some(token) = token {
+        req.headers_mut().insert(
+            "Authorization",
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+    }
+    req
+}
+
+fn state(sources: Vec<Arc<dyn KeySource>>) -> AppState {
+    AppState::new(Arc::new(MemoryStore::new())).with_auth(Authenticator::new(sources))
+}
+
+#[tokio::test]
+async fn test_owner_comes_from_the_token() {
+    let state = state(vec![Arc::new(HmacSecret::new(SECRET))]);
+    let alice = hs256(&claims("alice"));
+
+    let body = json!({"name": "Birthday", "owner": "mallory", "items": []});
+    let res = handle_request(
+        request(Method::POST, "/wishlists", body.to_string(), Some(&alice)),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 201);
+    let created: Wishlist = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(created.owner, "alice");
+
+    // A replacement keeps the stored owner whatever the body says.
+    let body = json!({"name": "Renamed", "owner": "mallory", "items": []});
+    let res = handle_request(
+        request(
+            Method::PUT,
+            &format!("/wishlists/{}", created.id),
+            body.to_string(),
+            Some(&alice),
+        ),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 200);
+    let stored = state.store.get_item(created.id).await.unwrap().unwrap();
+    assert_eq!(stored.name, "Renamed");
+    assert_eq!(stored.owner, "alice");
+}
+
+#[tokio::test]
+async fn test_requests_without_a_valid_token_are_rejected() {
+    let state = state(vec![Arc::new(HmacSecret::new(SECRET))]);
+
+    let res = handle_request(
+        request(Method::GET, "/wishlists", Body::Empty, None),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 401);
+    assert_eq!(res.headers().get("WWW-Authenticate").unwrap(), "Bearer");
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["code"], "unauthorized");
+
+    let forged = encode(
+        &Header::new(Algorithm::HS256),
+        &claims("alice"),
+        &EncodingKey::from_secret(b"wrong-secret"),
+    )
+    .unwrap();
+    let expired = hs256(&json!({"sub": "alice", "exp": now() - 3600}));
+    let no_key = signed(Algorithm::RS256, Some("test-rsa"), &claims("alice"));
+    for token in [
+        forged.as_str(),
+        expired.as_str(),
+        no_key.as_str(),
+        "garbage",
+    ] {
+        let res = handle_request(
+            request(Method::GET, "/wishlists", Body::Empty, Some(token)),
+            &state,
+        )
+        .await
+        .unwrap();
+        assert_eq!(res.status(), 401, "token {}", token);
+    }
+
+    let res = handle_request(request(Method::GET, "/health", Body::Empty, None), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let res = handle_request(
+        request(Method::OPTIONS, "/wishlists", Body::Empty, None),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 204);
+}
+
+#[tokio::test]
+async fn test_jwks_keys_verify_rs256_and_es256() {
+    let auth = Authenticator::new(vec![Arc::new(StaticJwks::from_json(JWKS).unwrap())])
+        .with_issuer("https://id.example.test")
+        .with_audience("wishlists");
+    let mut claims = claims("bob");
+    claims["iss"] = json!("https://id.example.test");
+    claims["aud"] = json!("wishlists");
+
+    let principal = auth
+        .verify(&signed(Algorithm::RS256, Some("test-rsa"), &claims))
+        .await
+        .unwrap();
+    assert_eq!(principal.subject, "bob");
+    assert!(principal.has_role("member"));
+    let principal = auth
+        .verify(&signed(Algorithm::ES256, None, &claims))
+        .await
+        .unwrap();
+    assert_eq!(principal.subject, "bob");
+
+    // Right key, wrong kid, audience or issuer.
+    assert!(auth
+        .verify(&signed(Algorithm::RS256, Some("test-ec"), &claims))
+        .await
+        .is_err());
+    let mut other_audience = claims.clone();
+    other_audience["aud"] = json!("billing");
+    assert!(auth
+        .verify(&signed(Algorithm::RS256, Some("test-rsa"), &other_audience))
+        .await
+        .is_err());
+    let mut other_issuer = claims;
+    other_issuer["iss"] = json!("https://evil.example.test");
+    assert!(auth
+        .verify(&signed(Algorithm::ES256, Some("test-ec"), &other_issuer))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_remote_jwks_is_fetched_once_and_cached() {
+    let server = MockServer::start_async().await;
+    let jwks = server
+        .mock_async(|when, then| {
+            when.method(GET).path("/.well-known/jwks.json");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .body(JWKS);
+        })
+        .await;
+    let auth = Authenticator::new(vec![Arc::new(RemoteJwks::new(
+        server.url("/.well-known/jwks.json"),
+    ))]);
+
+    for _ in 0..3 {
+        let principal = auth
+            .verify(&signed(
+                Algorithm::RS256,
+                Some("test-rsa"),
+                &claims("carol"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(principal.subject, "carol");
+    }
+    // An unknown kid right after a fetch doesn't trigger another one.
+    assert!(auth
+        .verify(&signed(Algorithm::RS256, Some("rotated"), &claims("carol")))
+        .await
+        .is_err());
+    jwks.assert_hits_async(1).await;
+}
@@ -0,0 +1,230 @@
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use lambda_http::http::Method;
+use lambda_http::{Body, Request, Response};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use wishlist_api::auth::{Authenticator, HmacSecret};
+use wishlist_api::db::MemoryStore;
+use wishlist_api::handlers::{handle_request, Wishlist};
+use wishlist_api::state::AppState;
+
+const SECRET: &[u8] = b"test-secret";
+
+fn token(sub: &str) -> String {
+    let exp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 300;
+    encode(
+        &Header::new(Algorithm::HS256),
+        &json!({"sub": sub, "exp": exp}),
+        &EncodingKey::from_secret(SECRET),
+    )
+    .unwrap()
+}
+
+fn state() -> AppState {
+    AppState::new(Arc::new(MemoryStore::new()))
+        .with_auth(Authenticator::new(vec![Arc::new(HmacSecret::new(SECRET))]))
+}
+
+async fn send(
+    state: &AppState,
+    method: Method,
+    uri: &str,
+    body: Value,
+    caller: &str,
+) -> Response<Body> {
+    let body = if body.is_null() {
+        String::new()
+    } else {
+        body.to_string()
+    };
+    let mut req = Request::new(body.into());
+    *req.method_mut() = method;
+    *req.uri_mut() = uri.parse().unwrap();
+    req.headers_mut().insert(
+        "Authorization",
+        format!("Bearer {}", token(caller)).parse().unwrap(),
+    );
+    if req.method() == Method::PATCH {
+        req.headers_mut().insert(
+            "Content-Type",
+            "application/merge-patch+json".parse().unwrap(),
+        );
+    }
+    handle_request(req, state).await.unwrap()
+}
+
+async fn create(state: &AppState, owner: &str, name: &str) -> String {
+    let body = json!({"name": name, "items": [{"name": "Book"}]});
+    let res = send(state, Method::POST, "/wishlists", body, owner).await;
+    assert_eq!(res.status(), 201);
+    serde_json::from_slice::<Wishlist>(res.body()).unwrap().id
+}
+
+async fn share(state: &AppState, id: &str, subject: &str, role: &str) {
+    let uri = format!("/wishlists/{}/collaborators/{}", id, subject);
+    let res = send(state, Method::PUT, &uri, json!({"role": role}), "alice").await;
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn test_roles_grant_increasing_access() {
+    let state = state();
+    let id = create(&state, "alice", "Family").await;
+    let uri = format!("/wishlists/{}", id);
+    share(&state, &id, "vera", "viewer").await;
+    share(&state, &id, "ed", "editor").await;
+    share(&state, &id, "cora", "co-owner").await;
+
+    for who in ["vera", "ed", "cora"] {
+        let res = send(&state, Method::GET, &uri, Value::Null, who).await;
+        assert_eq!(res.status(), 200, "{}", who);
+    }
+    let rename = json!({"name": "Renamed"});
+    let res = send(&state, Method::PATCH, &uri, rename.clone(), "vera").await;
+    assert_eq!(res.status(), 403);
+    let res = send(&state, Method::PATCH, &uri, rename, "ed").await;
+    assert_eq!(res.status(), 200);
+    let stored: Wishlist = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(stored.owner, "alice");
+
+    // Only co-owners manage collaborators and delete the list.
+    let invite = format!("{}/collaborators/zed", uri);
+    let res = send(
+        &state,
+        Method::PUT,
+        &invite,
+        json!({"role": "viewer"}),
+        "ed",
+    )
+    .await;
+    assert_eq!(res.status(), 403);
+    let res = send(
+        &state,
+        Method::PUT,
+        &invite,
+        json!({"role": "viewer"}),
+        "cora",
+    )
+    .await;
+    assert_eq!(res.status(), 200);
+    let res = send(&state, Method::DELETE, &uri, Value::Null, "ed").await;
+    assert_eq!(res.status(), 403);
+    let res = send(&state, Method::DELETE, &uri, Value::Null, "cora").await;
+    assert_eq!(res.status(), 204);
+}
+
+#[tokio::test]
+async fn test_list_change_and_remove_collaborators() {
+    let state = state();
+    let id = create(&state, "alice", "Family").await;
+    let collaborators = format!("/wishlists/{}/collaborators", id);
+    share(&state, &id, "bob", "viewer").await;
+    share(&state, &id, "bob", "editor").await;
+    share(&state, &id, "carol", "viewer").await;
+
+    let res = send(&state, Method::GET, &collaborators, Value::Null, "bob").await;
+    assert_eq!(res.status(), 200);
+    let listed: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(
+        listed,
+        json!([
+            {"subject": "bob", "role": "editor"},
+            {"subject": "carol", "role": "viewer"}
+        ])
+    );
+
+    // A collaborator may leave, but not remove others.
+    let carol = format!("{}/carol", collaborators);
+    let res = send(&state, Method::DELETE, &carol, Value::Null, "bob").await;
+    assert_eq!(res.status(), 403);
+    let res = send(&state, Method::DELETE, &carol, Value::Null, "carol").await;
+    assert_eq!(res.status(), 204);
+    let res = send(&state, Method::GET, &collaborators, Value::Null, "carol").await;
+    assert_eq!(res.status(), 403);
+    let res = send(&state, Method::DELETE, &carol, Value::Null, "alice").await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn test_invalid_invitations() {
+    let state = state();
+    let id = create(&state, "alice", "Family").await;
+    let uri = format!("/wishlists/{}/collaborators/bob", id);
+    let res = send(&state, Method::PUT, &uri, json!({"role": "owner"}), "alice").await;
+    assert_eq!(res.status(), 422);
+    let problem: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(problem["errors"][0]["field"], "role");
+
+    let uri = format!("/wishlists/{}/collaborators/alice", id);
+    let res = send(
+        &state,
+        Method::PUT,
+        &uri,
+        json!({"role": "viewer"}),
+        "alice",
+    )
+    .await;
+    assert_eq!(res.status(), 422);
+
+    let res = send(
+        &state,
+        Method::PUT,
+        "/wishlists/missing/collaborators/bob",
+        json!({"role": "viewer"}),
+        "alice",
+    )
+    .await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn test_lists_shared_with_me() {
+    let state = state();
+    let family = create(&state, "alice", "Family").await;
+    let work = create(&state, "dave", "Work").await;
+    create(&state, "alice", "Private").await;
+    share(&state, &family, "bob", "viewer").await;
+    let uri = format!("/wishlists/{}/collaborators/bob", work);
+    let res = send(&state, Method::PUT, &uri, json!({"role": "editor"}), "dave").await;
+    assert_eq!(res.status(), 200);
+
+    let res = send(
+        &state,
+        Method::GET,
+        "/collaborators/bob/wishlists?limit=1",
+        Value::Null,
+        "bob",
+    )
+    .await;
+    assert_eq!(res.status(), 200);
+    let first: Vec<Wishlist> = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(first.len(), 1);
+    let cursor = res.headers()["X-Next-Cursor"].to_str().unwrap().to_string();
+    let res = send(
+        &state,
+        Method::GET,
+        &format!("/collaborators/bob/wishlists?limit=1&cursor={}", cursor),
+        Value::Null,
+        "bob",
+    )
+    .await;
+    let second: Vec<Wishlist> = serde_json::from_slice(res.body()).unwrap();
+    let mut names: Vec<String> = first.into_iter().chain(second).map(|w| w.name).collect();
+    names.sort();
+    assert_eq!(names, vec!["Family", "Work"]);
+
+    let res = send(
+        &state,
+        Method::GET,
+        "/collaborators/bob/wishlists",
+        Value::Null,
+        "alice",
+    )
+    .await;
+    assert_eq!(res.status(), 403);
+}
@@ -0,0 +1,143 @@
+use lambda_http::{Body, Request};
+use serde_json::json;
+use std::sync::Arc;
+use wishlist_api::config::Config;
+use wishlist_api::db::MemoryStore;
+use wishlist_api::handlers::{handle_delete, handle_get, handle_post, handle_put, Wishlist};
+use wishlist_api::state::AppState;
+
+fn request(uri: &str, body: Body, if_match: Option<&str>) -> Request {
+    let mut req = Request::new(body);
+    *req.uri_mut() = uri.parse().unwrap();
+    if let Some(tag) = if_match {
+        req.headers_mut().insert("If-Match", tag.parse().unwrap());
+    }
+    req
+}
+
+fn wishlist_body(name: &str) -> Body {
+    Body::from(
+        json!({
+            "id": "list-1",
+            "name": name,
+            "owner": "alice",
+            "items": ["Socks"]
+        })
+        .to_string(),
+    )
+}
+
+async fn created_state() -> AppState {
+    let config = Config {
+        allow_client_ids: true,
+        ..Config::default()
+    };
+    let state = AppState::with_config(Arc::new(MemoryStore::new()), config);
+    let res = handle_post(request("/wishlists", wishlist_body("First"), None), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 201);
+    assert_eq!(res.headers().get("ETag").unwrap(), "\"1\"");
+    state
+}
+
+#[tokio::test]
+async fn test_get_returns_etag_and_put_bumps_version() {
+    let state = created_state().await;
+
+    let res = handle_get(request("/wishlists/list-1", Body::Empty, None), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.headers().get("ETag").unwrap(), "\"1\"");
+
+    let res = handle_put(
+        request("/wishlists", wishlist_body("Second"), Some("\"1\"")),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers().get("ETag").unwrap(), "\"2\"");
+    let updated: Wishlist = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(updated.version, 2);
+}
+
+#[tokio::test]
+async fn test_stale_if_match_is_rejected() {
+    let state = created_state().await;
+    let res = handle_put(request("/wishlists", wishlist_body("Second"), None), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+
+    // A second editor still holding version 1 must not overwrite version 2.
+    let res = handle_put(
+        request("/wishlists", wishlist_body("Stale"), Some("\"1\"")),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 412);
+
+    let delete_body = Body::from(json!({"id": "list-1"}).to_string());
+    let res = handle_delete(request("/wishlists", delete_body, Some("\"1\"")), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 412);
+
+    let res = handle_get(request("/wishlists/list-1", Body::Empty, None), &state)
+        .await
+        .unwrap();
+    let current: Wishlist = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(current.name, "Second");
+}
+
+#[tokio::test]
+async fn test_matching_if_match_allows_delete() {
+    let state = created_state().await;
+    let delete_body = Body::from(json!({"id": "list-1"}).to_string());
+    let res = handle_delete(request("/wishlists", delete_body, Some("*")), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 204);
+}
+
+#[tokio::test]
+async fn test_post_refuses_to_clobber_existing_id() {
+    let state = created_state().await;
+    let res = handle_post(
+        request("/wishlists", wishlist_body("Clobber"), None),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 409);
+
+    let res = handle_get(request("/wishlists/list-1", Body::Empty, None), &state)
+        .await
+        .unwrap();
+    let current: Wishlist = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(current.name, "First");
+}
+
+#[tokio::test]
+async fn test_concurrent_conditional_puts_have_one_winner() {
+    let state = created_state().await;
+    let mut tasks = Vec::new();
+    for i in 0..10 {
+        let state = state.clone();
+        tasks.push(tokio::spawn(async move {
+            let body = wishlist_body(&format!("Editor {}", i));
+            handle_put(request("/wishlists", body, Some("\"1\"")), &state)
+                .await
+                .unwrap()
+                .status()
+        }));
+    }
+    let mut statuses = Vec::new();
+    for task in tasks {
+        statuses.push(task.await.unwrap().as_u16());
+    }
+    assert_eq!(statuses.iter().filter(|s| **s == 200).count(), 1);
+    assert_eq!(statuses.iter().filter(|s| **s == 412).count(), 9);
+}
@@ -0,0 +1,72 @@
+use lambda_http::{Body, Request};
+use serde_json::json;
+use std::sync::Arc;
+use wishlist_api::config::Config;
+use wishlist_api::db::MemoryStore;
+use wishlist_api::handlers::{handle_get, handle_post, Wishlist};
+use wishlist_api::state::AppState;
+
+fn post(body: serde_json::Value) -> Request {
+    let mut req = Request::new(Body::from(body.to_string()));
+    *req.uri_mut() = "/wishlists".parse().unwrap();
+    req
+}
+
+#[tokio::test]
+async fn test_post_generates_id_and_location() {
+    let state = AppState::new(Arc::new(MemoryStore::new()));
+    let res = handle_post(
+        post(json!({"name": "Birthday", "owner": "alice", "items": []})),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 201);
+    let created: Wishlist = serde_json::from_slice(res.body()).unwrap();
+    assert!(uuid::Uuid::parse_str(&created.id).is_ok());
+    let location = res.headers().get("Location").unwrap().to_str().unwrap();
+    assert_eq!(location, format!("/wishlists/{}", created.id));
+
+    let mut get = Request::new(Body::Empty);
+    *get.uri_mut() = location.parse().unwrap();
+    let res = handle_get(get, &state).await.unwrap();
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn test_post_rejects_client_id_by_default() {
+    let state = AppState::new(Arc::new(MemoryStore::new()));
+    let res = handle_post(
+        post(json!({"id": "mine", "name": "Birthday", "owner": "alice", "items": []})),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 400);
+    assert!(state
+        .store
+        .get_item("mine".to_string())
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_post_keeps_client_id_when_allowed() {
+    let config = Config {
+        allow_client_ids: true,
+        ..Config::default()
+    };
+    let state = AppState::with_config(Arc::new(MemoryStore::new()), config);
+    let res = handle_post(
+        post(json!({"id": "my list", "name": "Birthday", "owner": "alice", "items": []})),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 201);
+    assert_eq!(
+        res.headers().get("Location").unwrap(),
+        "/wishlists/my%20list"
+    );
+}
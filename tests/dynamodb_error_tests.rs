@@ -0,0 +1,73 @@
+use aws_sdk_dynamodb::error::{ErrorMetadata, SdkError};
+use aws_sdk_dynamodb::operation::get_item::GetItemError;
+use serial_test::serial;
+use std::time::Duration;
+use wishlist_api::db::RetrySettings;
+use wishlist_api::error::AppError;
+
+fn service_error(code: &str, message: &str) -> AppError {
+    let err = GetItemError::generic(ErrorMetadata::builder().code(code).message(message).build());
+    SdkError::<GetItemError, ()>::service_error(err, ()).into()
+}
+
+#[test]
+fn test_sdk_errors_are_classified() {
+    assert!(matches!(
+        service_error("ProvisionedThroughputExceededException", "slow down"),
+        AppError::Throttled(_)
+    ));
+    assert!(matches!(
+        service_error("ThrottlingException", "slow down"),
+        AppError::Throttled(_)
+    ));
+    assert!(matches!(
+        service_error("InternalServerError", "oops"),
+        AppError::Unavailable(_)
+    ));
+    assert!(matches!(
+        service_error(
+            "ValidationException",
+            "Item size has exceeded the maximum allowed size"
+        ),
+        AppError::Validation(_)
+    ));
+    assert!(matches!(
+        service_error(
+            "ValidationException",
+            "One or more parameter values were invalid"
+        ),
+        AppError::DynamoDb(_)
+    ));
+    assert!(matches!(
+        service_error("ResourceNotFoundException", "no such table"),
+        AppError::DynamoDb(_)
+    ));
+
+    let timeout: AppError = SdkError::<GetItemError, ()>::timeout_error("attempt timed out").into();
+    assert!(matches!(timeout, AppError::Unavailable(_)));
+    assert_eq!(timeout.status(), 503);
+}
+
+#[test]
+#[serial]
+fn test_retry_settings_from_env() {
+    std::env::set_var("DYNAMODB_MAX_ATTEMPTS", "5");
+    std::env::set_var("DYNAMODB_MAX_BACKOFF_MS", "500");
+    std::env::set_var("DYNAMODB_ATTEMPT_TIMEOUT_MS", "-1");
+    let settings = RetrySettings::from_env();
+    std::env::remove_var("DYNAMODB_MAX_ATTEMPTS");
+    std::env::remove_var("DYNAMODB_MAX_BACKOFF_MS");
+    std::env::remove_var("DYNAMODB_ATTEMPT_TIMEOUT_MS");
+
+    assert_eq!(settings.max_attempts, 5);
+    assert_eq!(settings.max_backoff, Duration::from_millis(500));
+    assert_eq!(
+        settings.initial_backoff,
+        RetrySettings::default().initial_backoff
+    );
+    assert_eq!(
+        settings.attempt_timeout,
+        RetrySettings::default().attempt_timeout
+    );
+    assert_eq!(settings.retry_config().max_attempts(), 5);
+}
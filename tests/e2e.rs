@@ -1,11 +1,14 @@
 use aws_sdk_dynamodb::Client as DynamoDbClient;
 use lambda_http::{http, Body};
 use serial_test::serial;
+use std::sync::Arc;
+use wishlist_api::db::DynamoDbStore;
 use wishlist_api::handlers::handle_request;
+use wishlist_api::state::AppState;
 
-async fn setup_db_client() -> DynamoDbClient {
+async fn setup_state() -> AppState {
     let config = aws_config::load_from_env().await;
-    DynamoDbClient::new(&config)
+    AppState::new(Arc::new(DynamoDbStore::new(DynamoDbClient::new(&config))))
 }
 
 #[tokio::test]
@@ -18,8 +21,8 @@ async fn test_health_check() {
         .body(Body::Empty)
         .unwrap();
 
-    let db_client = setup_db_client().await;
-    let response = handle_request(request, &db_client)
+    let state = setup_state().await;
+    let response = handle_request(request, &state)
         .await
         .expect("Health check failed");
 
@@ -40,8 +43,8 @@ async fn test_wishlist_endpoint() {
         .body(Body::Empty)
         .unwrap();
 
-    let db_client = setup_db_client().await;
-    let response = handle_request(request, &db_client)
+    let state = setup_state().await;
+    let response = handle_request(request, &state)
         .await
         .expect("API request failed");
 
@@ -0,0 +1,286 @@
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use lambda_http::http::Method;
+use lambda_http::{Body, Request, Response};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use wishlist_api::auth::{Authenticator, HmacSecret};
+use wishlist_api::db::MemoryStore;
+use wishlist_api::exchange::{draw, Exchange, Participant};
+use wishlist_api::handlers::{handle_request, Wishlist};
+use wishlist_api::state::AppState;
+
+const SECRET: &[u8] = b"test-secret";
+
+fn token(sub: &str) -> String {
+    let exp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 300;
+    encode(
+        &Header::new(Algorithm::HS256),
+        &json!({"sub": sub, "exp": exp}),
+        &EncodingKey::from_secret(SECRET),
+    )
+    .unwrap()
+}
+
+fn state() -> AppState {
+    AppState::new(Arc::new(MemoryStore::new()))
+        .with_auth(Authenticator::new(vec![Arc::new(HmacSecret::new(SECRET))]))
+}
+
+async fn send(
+    state: &AppState,
+    method: Method,
+    uri: &str,
+    body: Value,
+    caller: &str,
+) -> Response<Body> {
+    let body = if body.is_null() {
+        String::new()
+    } else {
+        body.to_string()
+    };
+    let mut req = Request::new(body.into());
+    *req.method_mut() = method;
+    *req.uri_mut() = uri.parse().unwrap();
+    req.headers_mut().insert(
+        "Authorization",
+        format!("Bearer {}", token(caller)).parse().unwrap(),
+    );
+    handle_request(req, state).await.unwrap()
+}
+
+fn json_body(res: &Response<Body>) -> Value {
+    serde_json::from_slice(res.body()).unwrap()
+}
+
+fn participant(subject: &str, spouse: Option<&str>) -> Participant {
+    Participant {
+        subject: subject.to_string(),
+        wishlist_id: format!("{}-list", subject),
+        spouse: spouse.map(str::to_string),
+        receiver: None,
+    }
+}
+
+/// Creates a wishlist for each of `subjects` and returns the exchange's
+/// participants, each linked to their own list.
+async fn participants(state: &AppState, subjects: &[&str]) -> Vec<Value> {
+    let mut participants = Vec::new();
+    for subject in subjects {
+        let body = json!({"name": format!("{}'s list", subject), "items": [{"name": "Socks"}]});
+        let res = send(state, Method::POST, "/wishlists", body, subject).await;
+        assert_eq!(res.status(), 201);
+        let wishlist: Wishlist = serde_json::from_slice(res.body()).unwrap();
+        participants.push(json!({"subject": subject, "wishlist_id": wishlist.id}));
+    }
+    participants
+}
+
+#[test]
+fn test_draw_honours_exclusions() {
+    let participants = vec![
+        participant("ann", Some("ben")),
+        participant("ben", Some("ann")),
+        participant("cat", Some("dan")),
+        participant("dan", None),
+        participant("eve", None),
+        participant("fay", None),
+    ];
+    let mut previous = Exchange {
+        id: "last-year".to_string(),
+        name: "Last year".to_string(),
+        organizer: "ann".to_string(),
+        participants: participants.clone(),
+        previous_exchange_id: None,
+        created_at: 0,
+        drawn_at: Some(0),
+    };
+    for (giver, receiver) in previous
+        .participants
+        .iter_mut()
+        .zip(["cat", "dan", "eve", "fay", "ann", "ben"])
+    {
+        giver.receiver = Some(receiver.to_string());
+    }
+
+    for seed in 0..200 {
+        let drawn = draw(&participants, Some(&previous), seed).unwrap();
+        let receivers: HashSet<&str> = drawn
+            .iter()
+            .map(|p| p.receiver.as_deref().unwrap())
+            .collect();
+        assert_eq!(receivers.len(), participants.len());
+        for (giver, last) in drawn.iter().zip(&previous.participants) {
+            let receiver = giver.receiver.as_deref().unwrap();
+            assert_ne!(receiver, giver.subject);
+            assert_ne!(Some(receiver), giver.spouse.as_deref());
+            assert_ne!(receiver, last.receiver.as_deref().unwrap());
+            let spouse_of_receiver = participants
+                .iter()
+                .find(|p| p.subject == receiver)
+                .and_then(|p| p.spouse.as_deref());
+            assert_ne!(spouse_of_receiver, Some(giver.subject.as_str()));
+        }
+    }
+}
+
+#[test]
+fn test_draw_is_deterministic_per_seed() {
+    let participants: Vec<Participant> = ["a", "b", "c", "d", "e"]
+        .into_iter()
+        .map(|subject| participant(subject, None))
+        .collect();
+    assert_eq!(draw(&participants, None, 42), draw(&participants, None, 42));
+    let distinct: HashSet<Vec<Option<String>>> = (0..20)
+        .map(|seed| {
+            draw(&participants, None, seed)
+                .unwrap()
+                .into_iter()
+                .map(|p| p.receiver)
+                .collect()
+        })
+        .collect();
+    assert!(distinct.len() > 1);
+
+    let couple = vec![participant("a", Some("b")), participant("b", None)];
+    assert_eq!(draw(&couple, None, 0), None);
+}
+
+#[tokio::test]
+async fn test_participants_see_only_their_own_assignment() {
+    let state = state();
+    let participants = participants(&state, &["bob", "carol", "dave"]).await;
+    let body = json!({"name": "Office party", "participants": participants});
+    let res = send(&state, Method::POST, "/exchanges", body, "alice").await;
+    assert_eq!(res.status(), 201);
+    let id = json_body(&res)["id"].as_str().unwrap().to_string();
+    let exchange = format!("/exchanges/{}", id);
+    let assignment = format!("{}/assignment", exchange);
+    let draw = format!("{}/draw", exchange);
+
+    let res = send(&state, Method::GET, &assignment, Value::Null, "bob").await;
+    assert_eq!(res.status(), 409);
+    let res = send(&state, Method::POST, &draw, Value::Null, "bob").await;
+    assert_eq!(res.status(), 403);
+    let res = send(&state, Method::POST, &draw, Value::Null, "alice").await;
+    assert_eq!(res.status(), 200);
+    assert!(!String::from_utf8_lossy(res.body()).contains("receiver"));
+    let res = send(&state, Method::POST, &draw, Value::Null, "alice").await;
+    assert_eq!(res.status(), 409);
+
+    let mut receivers = HashSet::new();
+    for giver in ["bob", "carol", "dave"] {
+        let res = send(&state, Method::GET, &assignment, Value::Null, giver).await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers()["Cache-Control"], "private, no-store");
+        let assigned = json_body(&res);
+        let receiver = assigned["receiver"].as_str().unwrap().to_string();
+        assert_ne!(receiver, giver);
+        assert_eq!(assigned["wishlist"]["name"], format!("{}'s list", receiver));
+        assert!(assigned["wishlist"].get("owner").is_none());
+        receivers.insert(receiver);
+
+        let res = send(&state, Method::GET, &exchange, Value::Null, giver).await;
+        assert_eq!(res.status(), 200);
+        assert!(!String::from_utf8_lossy(res.body()).contains("receiver"));
+    }
+    assert_eq!(receivers.len(), 3);
+
+    let res = send(&state, Method::GET, &assignment, Value::Null, "alice").await;
+    assert_eq!(res.status(), 403);
+    let res = send(&state, Method::GET, &exchange, Value::Null, "mallory").await;
+    assert_eq!(res.status(), 403);
+    let res = send(&state, Method::DELETE, &exchange, Value::Null, "bob").await;
+    assert_eq!(res.status(), 403);
+    let res = send(&state, Method::DELETE, &exchange, Value::Null, "alice").await;
+    assert_eq!(res.status(), 204);
+    let res = send(&state, Method::GET, &exchange, Value::Null, "alice").await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn test_no_repeat_from_last_year() {
+    let state = state();
+    let participants = participants(&state, &["bob", "carol", "dave"]).await;
+    let body = json!({"name": "2025", "participants": participants});
+    let res = send(&state, Method::POST, "/exchanges", body, "alice").await;
+    let last_year = json_body(&res)["id"].as_str().unwrap().to_string();
+    let uri = format!("/exchanges/{}/draw", last_year);
+    let res = send(&state, Method::POST, &uri, Value::Null, "alice").await;
+    assert_eq!(res.status(), 200);
+    let uri = format!("/exchanges/{}/assignment", last_year);
+    let res = send(&state, Method::GET, &uri, Value::Null, "bob").await;
+    let bob_gave_to = json_body(&res)["receiver"].as_str().unwrap().to_string();
+
+    // Among three people, ruling out last year's pairs leaves exactly one
+    // draw: last year's cycle reversed.
+    let body = json!({
+        "name": "2026",
+        "participants": participants,
+        "previous_exchange_id": last_year,
+    });
+    let res = send(&state, Method::POST, "/exchanges", body, "alice").await;
+    assert_eq!(res.status(), 201);
+    let id = json_body(&res)["id"].as_str().unwrap().to_string();
+    let uri = format!("/exchanges/{}/draw", id);
+    let res = send(&state, Method::POST, &uri, Value::Null, "alice").await;
+    assert_eq!(res.status(), 200);
+    let uri = format!("/exchanges/{}/assignment", id);
+    let res = send(&state, Method::GET, &uri, Value::Null, bob_gave_to.as_str()).await;
+    assert_eq!(json_body(&res)["receiver"], "bob");
+}
+
+#[tokio::test]
+async fn test_exchanges_are_validated() {
+    let state = state();
+    let mut people = participants(&state, &["bob", "carol"]).await;
+
+    let body = json!({"name": "Solo", "participants": [people[0]]});
+    let res = send(&state, Method::POST, "/exchanges", body, "alice").await;
+    assert_eq!(res.status(), 422);
+    assert_eq!(json_body(&res)["errors"][0]["field"], "participants");
+
+    people[1]["spouse"] = json!("zed");
+    let body = json!({"name": "Party", "participants": people});
+    let res = send(&state, Method::POST, "/exchanges", body, "alice").await;
+    assert_eq!(res.status(), 422);
+    assert_eq!(json_body(&res)["errors"][0]["rule"], "unknown");
+
+    people[1]["spouse"] = json!("bob");
+    people[1]["wishlist_id"] = people[0]["wishlist_id"].clone();
+    let body = json!({"name": "Party", "participants": people});
+    let res = send(&state, Method::POST, "/exchanges", body, "alice").await;
+    assert_eq!(res.status(), 422);
+    let error = &json_body(&res)["errors"][0];
+    assert_eq!(error["field"], "participants[1].wishlist_id");
+    assert_eq!(error["rule"], "not_owned");
+
+    // Two spouses can only draw each other, which the exclusions forbid.
+    let people = participants(&state, &["erin", "fred"]).await;
+    let mut body = json!({"name": "Couple", "participants": people});
+    body["participants"][0]["spouse"] = json!("fred");
+    let res = send(&state, Method::POST, "/exchanges", body, "alice").await;
+    assert_eq!(res.status(), 201);
+    let uri = format!(
+        "/exchanges/{}/draw",
+        json_body(&res)["id"].as_str().unwrap()
+    );
+    let res = send(&state, Method::POST, &uri, Value::Null, "alice").await;
+    assert_eq!(res.status(), 409);
+
+    let unauthenticated = AppState::new(Arc::new(MemoryStore::new()));
+    let res = send(
+        &unauthenticated,
+        Method::GET,
+        "/exchanges/x",
+        Value::Null,
+        "alice",
+    )
+    .await;
+    assert_eq!(res.status(), 401);
+}
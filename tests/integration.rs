@@ -5,8 +5,16 @@ use aws_sdk_dynamodb::error::SdkError;
 use aws_sdk_dynamodb::Client as DynamoDbClient;
 use lambda_http::{Body, Request};
 use serde_json::json;
+use std::sync::Arc;
+use wishlist_api::config::Config;
+#[cfg(feature = "postgres")]
+use wishlist_api::db::PostgresStore;
+#[cfg(feature = "sqlite")]
+use wishlist_api::db::SqliteStore;
+use wishlist_api::db::{DynamoDbStore, MemoryStore, WishlistStore};
 use wishlist_api::handlers::Wishlist;
 use wishlist_api::handlers::{handle_delete, handle_get, handle_post, handle_put};
+use wishlist_api::state::AppState;
 
 use tokio::time::{sleep, Duration};
 
@@ -56,52 +64,10 @@ async fn wait_for_table_status(
 
 async fn create_table(client: &DynamoDbClient) {
     let table_name = "wishlist_table";
-    let key_schema = aws_sdk_dynamodb::types::KeySchemaElement::builder()
-        .attribute_name("id")
-        .key_type(aws_sdk_dynamodb::types::KeyType::Hash)
-        .build()
-        .expect("Failed to build KeySchemaElement");
-    let attribute_definition = aws_sdk_dynamodb::types::AttributeDefinition::builder()
-        .attribute_name("id")
-        .attribute_type(aws_sdk_dynamodb::types::ScalarAttributeType::S)
-        .build()
-        .expect("Failed to build AttributeDefinition");
-
     println!("Attempting to create table '{}'...", table_name);
-    let create_table_result = client
-        .create_table()
-        .table_name(table_name)
-        .key_schema(key_schema)
-        .attribute_definitions(attribute_definition)
-        .provisioned_throughput(
-            aws_sdk_dynamodb::types::ProvisionedThroughput::builder()
-                .read_capacity_units(1)
-                .write_capacity_units(1)
-                .build()
-                .expect("Failed to build ProvisionedThroughput"),
-        )
-        .send()
-        .await;
-
-    match create_table_result {
-        Ok(_) => {
-            println!("Table '{}' created successfully.", table_name);
-        }
-        Err(e) => {
-            if let SdkError::ServiceError(service_error) = &e {
-                if service_error.err().is_resource_in_use_exception() {
-                    println!(
-                        "Table '{}' already exists. Proceeding with existing table.",
-                        table_name
-                    );
-                } else {
-                    panic!("Failed to create table: {:?}", e);
-                }
-            } else {
-                panic!("Failed to create table: {:?}", e);
-            }
-        }
-    }
+    wishlist_api::db::dynamodb::create_table(client)
+        .await
+        .expect("Failed to create table");
     println!("Table '{}' creation initiated.", table_name);
     println!("Waiting for table '{}' to become active...", table_name);
     wait_for_table_status(
@@ -156,9 +122,62 @@ async fn delete_table(client: &DynamoDbClient) {
     }
 }
 
-async fn setup_db_client() -> DynamoDbClient {
-    let endpoint = std::env::var("DYNAMODB_ENDPOINT")
-        .unwrap_or_else(|_| "http://host.containers.internal:8000".to_string());
+async fn setup_local_store() -> Arc<dyn WishlistStore> {
+    match std::env::var("STORAGE_BACKEND").as_deref() {
+        #[cfg(feature = "sqlite")]
+        Ok("sqlite") => {
+            let path =
+                std::env::temp_dir().join(format!("wishlist-it-{}.sqlite", uuid::Uuid::new_v4()));
+            println!("Using SQLite store at {}.", path.display());
+            let store = SqliteStore::connect(&path.to_string_lossy()).await.unwrap();
+            Arc::new(store)
+        }
+        #[cfg(feature = "postgres")]
+        Ok("postgres") => Arc::new(setup_postgres_store().await),
+        _ => {
+            println!("DYNAMODB_ENDPOINT not set, using in-memory store.");
+            Arc::new(MemoryStore::new())
+        }
+    }
+}
+
+/// Gives each test its own schema so parallel tests see an empty database.
+#[cfg(feature = "postgres")]
+async fn setup_postgres_store() -> PostgresStore {
+    use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+    use std::str::FromStr;
+
+    let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set for postgres tests");
+    let schema = format!("test_{}", uuid::Uuid::new_v4().simple());
+    println!("Using PostgreSQL schema '{}'.", schema);
+    let admin = PgPoolOptions::new()
+        .max_connections(1)
+        .connect(&url)
+        .await
+        .unwrap();
+    sqlx::query(&format!("CREATE SCHEMA {}", schema))
+        .execute(&admin)
+        .await
+        .unwrap();
+    let options = PgConnectOptions::from_str(&url)
+        .unwrap()
+        .options([("search_path", schema.as_str())]);
+    let pool = PgPoolOptions::new()
+        .max_connections(4)
+        .connect_with(options)
+        .await
+        .unwrap();
+    PostgresStore::from_pool(pool).await.unwrap()
+}
+
+/// Runs against DynamoDB Local when `DYNAMODB_ENDPOINT` is set, otherwise
+/// against a fresh local store (in-memory by default, or SQLite/PostgreSQL via
+/// `STORAGE_BACKEND`) so the suite needs no external services.
+async fn setup_store() -> Arc<dyn WishlistStore> {
+    let endpoint = match std::env::var("DYNAMODB_ENDPOINT") {
+        Ok(endpoint) => endpoint,
+        Err(_) => return setup_local_store().await,
+    };
     let config = SdkConfig::builder()
         .endpoint_url(endpoint) // Use DynamoDB Local endpoint from env or default
         .region(aws_sdk_dynamodb::config::Region::new("eu-west-1")) // Specify a region
@@ -171,19 +190,37 @@ async fn setup_db_client() -> DynamoDbClient {
     println!("Creating fresh table...");
     create_table(&client).await; // Create fresh table
     println!("DynamoDB client setup complete.");
-    client
+    Arc::new(DynamoDbStore::new(client))
+}
+
+/// These flows pick their own wishlist IDs, so they run with client IDs
+/// allowed.
+async fn setup_state() -> AppState {
+    let config = Config {
+        allow_client_ids: true,
+        ..Config::default()
+    };
+    AppState::with_config(setup_store().await, config)
+}
+
+fn item_names(wishlist: &Wishlist) -> Vec<&str> {
+    wishlist
+        .items
+        .iter()
+        .map(|item| item.name.as_str())
+        .collect()
 }
 
 #[tokio::test]
 async fn test_health_check() {
     println!("Running test_health_check...");
-    let db_client = setup_db_client().await;
+    let state = setup_state().await;
     let mut event = Request::new(Body::Empty);
     *event.uri_mut() = "/health".parse().unwrap();
 
     println!("Request URI: {}", event.uri());
 
-    let response = handle_get(event, &db_client)
+    let response = handle_get(event, &state)
         .await
         .expect("expected Ok(_) value");
 
@@ -197,7 +234,7 @@ async fn test_health_check() {
 #[tokio::test]
 async fn test_full_wishlist_lifecycle() {
     println!("Running test_full_wishlist_lifecycle...");
-    let db_client = setup_db_client().await;
+    let state = setup_state().await;
 
     // Test uses unique IDs so no initial cleanup needed
     let _check_req = Request::new(Body::Empty);
@@ -211,7 +248,7 @@ async fn test_full_wishlist_lifecycle() {
         tokio::time::sleep(std::time::Duration::from_millis(500)).await;
         let mut empty_req = Request::new(Body::Empty);
         *empty_req.uri_mut() = "/wishlists".parse().unwrap();
-        let empty_res = handle_get(empty_req, &db_client).await.unwrap();
+        let empty_res = handle_get(empty_req, &state).await.unwrap();
         println!(
             "Empty verification attempt {}: {:?}",
             retries + 1,
@@ -229,7 +266,7 @@ async fn test_full_wishlist_lifecycle() {
     for wishlist in &empty_wishlists {
         if wishlist.id.starts_with("test-") {
             let delete_req = Request::new(Body::from(json!({"id": wishlist.id}).to_string()));
-            let _ = handle_delete(delete_req, &db_client).await;
+            let _ = handle_delete(delete_req, &state).await;
         }
     }
 
@@ -248,13 +285,13 @@ async fn test_full_wishlist_lifecycle() {
         "items": ["Initial"]
     });
     let create_req = Request::new(Body::from(test_wishlist.to_string()));
-    let create_res = handle_post(create_req, &db_client).await.unwrap();
+    let create_res = handle_post(create_req, &state).await.unwrap();
     assert_eq!(create_res.status(), 201, "Failed to create test wishlist");
 
     // Actively clean up any existing test wishlists
     let mut check_req = Request::new(Body::Empty);
     *check_req.uri_mut() = "/wishlists".parse().unwrap();
-    let check_res = handle_get(check_req, &db_client).await.unwrap();
+    let check_res = handle_get(check_req, &state).await.unwrap();
     let wishlists = if check_res.status() == 200 {
         serde_json::from_slice::<Vec<Wishlist>>(check_res.body()).unwrap_or_default()
     } else {
@@ -265,14 +302,14 @@ async fn test_full_wishlist_lifecycle() {
             let delete_body = serde_json::json!({ "id": wishlist.id }).to_string();
             let mut delete_req = Request::new(Body::Text(delete_body));
             *delete_req.uri_mut() = "/wishlists".parse().unwrap(); // The handler expects /wishlists for DELETE
-            let _ = handle_delete(delete_req, &db_client).await.unwrap();
+            let _ = handle_delete(delete_req, &state).await.unwrap();
         }
     }
 
     // 1. Thorough cleanup of any existing test wishlists
     let mut cleanup_req = Request::new(Body::Empty);
     *cleanup_req.uri_mut() = "/wishlists".parse().unwrap();
-    let cleanup_res = handle_get(cleanup_req, &db_client).await.unwrap();
+    let cleanup_res = handle_get(cleanup_req, &state).await.unwrap();
     assert_eq!(cleanup_res.status(), 200);
     let existing_wishlists: Vec<Wishlist> = serde_json::from_slice(cleanup_res.body()).unwrap();
 
@@ -289,7 +326,7 @@ async fn test_full_wishlist_lifecycle() {
                 let mut delete_req =
                     Request::new(Body::from(json!({"id": wishlist.id}).to_string()));
                 *delete_req.uri_mut() = format!("/wishlists/{}", wishlist.id).parse().unwrap();
-                match handle_delete(delete_req, &db_client).await {
+                match handle_delete(delete_req, &state).await {
                     Ok(res) if res.status() == 200 => break,
                     _ => tokio::time::sleep(std::time::Duration::from_millis(100)).await,
                 }
@@ -309,16 +346,16 @@ async fn test_full_wishlist_lifecycle() {
         })
         .to_string(),
     ));
-    let create_res = handle_post(create_req, &db_client).await.unwrap();
+    let create_res = handle_post(create_req, &state).await.unwrap();
     assert_eq!(create_res.status(), 201);
     let created: Wishlist = serde_json::from_slice(create_res.body()).unwrap();
     assert_eq!(created.owner, "Christmas Owner");
-    assert_eq!(created.items, vec!["Socks", "Chocolate"]);
+    assert_eq!(item_names(&created), vec!["Socks", "Chocolate"]);
 
     // 3. Verify wishlist appears in GET
     let mut get_req = Request::new(Body::Empty);
     *get_req.uri_mut() = "/wishlists".parse().unwrap();
-    let get_res = handle_get(get_req, &db_client).await.unwrap();
+    let get_res = handle_get(get_req, &state).await.unwrap();
     println!("[DEBUG] GET response body: {:?}", get_res.body());
     let body = get_res.body();
     let wishlists: Vec<Wishlist> = serde_json::from_slice(body)
@@ -340,7 +377,7 @@ async fn test_full_wishlist_lifecycle() {
         "[DEBUG] Update request payload: {}",
         String::from_utf8_lossy(update_req.body().as_ref())
     );
-    let update_res = handle_put(update_req, &db_client).await.unwrap();
+    let update_res = handle_put(update_req, &state).await.unwrap();
     assert_eq!(update_res.status(), 200);
     println!("Update response body: {:?}", update_res.body().as_ref());
 
@@ -358,7 +395,7 @@ async fn test_full_wishlist_lifecycle() {
     println!("GET request URI: {}", uri);
     let mut get_req = Request::new(Body::Empty);
     *get_req.uri_mut() = uri.parse().unwrap();
-    let get_updated = handle_get(get_req, &db_client).await.unwrap();
+    let get_updated = handle_get(get_req, &state).await.unwrap();
     println!("GET response status: {}", get_updated.status());
     println!("GET response body: {:?}", get_updated.body().as_ref());
 
@@ -380,20 +417,18 @@ async fn test_full_wishlist_lifecycle() {
         "Should have exactly one wishlist"
     );
     assert_eq!(
-        updated_wishlist.items,
+        item_names(&updated_wishlist),
         vec!["Socks", "Chocolate", "Book"],
         "Items should match expected set"
     );
 
     // 6. Delete the wishlist
     let delete_req = Request::new(Body::from(json!({"id": created.id}).to_string()));
-    let delete_res = handle_delete(delete_req, &db_client).await.unwrap();
+    let delete_res = handle_delete(delete_req, &state).await.unwrap();
     assert_eq!(delete_res.status(), 204);
 
     // 7. Verify deletion
-    let final_get = handle_get(Request::new(Body::Empty), &db_client)
-        .await
-        .unwrap();
+    let final_get = handle_get(Request::new(Body::Empty), &state).await.unwrap();
     match final_get.status().as_u16() {
         200 => {
             let body = final_get.body();
@@ -414,7 +449,7 @@ async fn test_full_wishlist_lifecycle() {
 #[tokio::test]
 async fn test_item_operations() {
     println!("Running test_item_operations...");
-    let db_client = setup_db_client().await;
+    let state = setup_state().await;
 
     // Create wishlist
     let create_req = Request::new(Body::from(
@@ -426,7 +461,7 @@ async fn test_item_operations() {
         })
         .to_string(),
     ));
-    let create_res = handle_post(create_req, &db_client).await.unwrap();
+    let create_res = handle_post(create_req, &state).await.unwrap();
     println!("[DEBUG] Create response: {:?}", create_res.body());
     let wishlist: Wishlist = if create_res.status() == 201 {
         serde_json::from_slice(create_res.body()).expect("Failed to parse wishlist")
@@ -444,10 +479,10 @@ async fn test_item_operations() {
         })
         .to_string(),
     ));
-    let add_item_res = handle_put(add_item_req, &db_client).await.unwrap();
+    let add_item_res = handle_put(add_item_req, &state).await.unwrap();
     assert_eq!(add_item_res.status(), 200);
     let updated: Wishlist = serde_json::from_slice(add_item_res.body()).unwrap();
-    assert_eq!(updated.items, vec!["Initial", "Added"]);
+    assert_eq!(item_names(&updated), vec!["Initial", "Added"]);
 
     // Test removing item via PUT
     let remove_item_req = Request::new(Body::from(
@@ -459,20 +494,20 @@ async fn test_item_operations() {
         })
         .to_string(),
     ));
-    let remove_item_res = handle_put(remove_item_req, &db_client).await.unwrap();
+    let remove_item_res = handle_put(remove_item_req, &state).await.unwrap();
     assert_eq!(remove_item_res.status(), 200);
     let final_state: Wishlist = serde_json::from_slice(remove_item_res.body()).unwrap();
     assert_eq!(final_state.items.len(), 1);
-    assert!(final_state.items.contains(&"Added".to_string()));
+    assert_eq!(item_names(&final_state), vec!["Added"]);
 }
 
 #[tokio::test]
 async fn test_error_handling() {
     // Invalid JSON
     let invalid_json = Request::new(Body::from("invalid json"));
-    let db_client = setup_db_client().await;
-    let res = handle_post(invalid_json, &db_client).await;
-    assert!(res.is_err());
+    let state = setup_state().await;
+    let res = handle_post(invalid_json, &state).await.unwrap();
+    assert_eq!(res.status(), 400);
 
     // Nonexistent wishlist operations
     let fake_id = "nonexistent-id";
@@ -485,10 +520,10 @@ async fn test_error_handling() {
         })
         .to_string(),
     ));
-    let update_res = handle_put(update_req, &db_client).await.unwrap();
+    let update_res = handle_put(update_req, &state).await.unwrap();
     assert_eq!(update_res.status(), 404);
 
     let delete_req = Request::new(Body::from(json!({"id": fake_id}).to_string()));
-    let delete_res = handle_delete(delete_req, &db_client).await.unwrap();
+    let delete_res = handle_delete(delete_req, &state).await.unwrap();
     assert_eq!(delete_res.status(), 404);
 }
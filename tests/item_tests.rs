@@ -0,0 +1,176 @@
+use lambda_http::http::Method;
+use lambda_http::{Body, Request};
+use serde_json::json;
+use std::sync::Arc;
+use wishlist_api::db::{MemoryStore, WishlistStore};
+use wishlist_api::handlers::{handle_request, Wishlist, WishlistItem};
+use wishlist_api::state::AppState;
+
+fn request(method: Method, uri: &str, body: Body, if_match: Option<&str>) -> Request {
+    let mut req = Request::new(body);
+    *req.method_mut() = method;
+    *req.uri_mut() = uri.parse().unwrap();
+    if let Some(tag) = if_match {
+        req.headers_mut().insert("If-Match", tag.parse().unwrap());
+    }
+    req
+}
+
+async fn state_with_list() -> AppState {
+    let store = MemoryStore::new();
+    store
+        .create_item(Wishlist {
+            id: "list-1".to_string(),
+            name: "Birthday".to_string(),
+            owner: "alice".to_string(),
+            items: vec![WishlistItem::new("socks", "Socks")],
+            version: 1,
+        })
+        .await
+        .unwrap();
+    AppState::new(Arc::new(store))
+}
+
+#[tokio::test]
+async fn test_item_lifecycle() {
+    let state = state_with_list().await;
+
+    let body = Body::from(json!({"name": "Book", "price_cents": 1500}).to_string());
+    let res = handle_request(
+        request(Method::POST, "/wishlists/list-1/items", body, None),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 201);
+    assert_eq!(res.headers().get("ETag").unwrap(), "\"2\"");
+    let added: WishlistItem = serde_json::from_slice(res.body()).unwrap();
+    let location = res.headers().get("Location").unwrap().to_str().unwrap();
+    assert_eq!(location, format!("/wishlists/list-1/items/{}", added.id));
+
+    let res = handle_request(request(Method::GET, location, Body::Empty, None), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let fetched: WishlistItem = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(fetched, added);
+
+    let patch = Body::from(json!({"quantity": 2, "price_cents": null}).to_string());
+    let res = handle_request(
+        request(Method::PATCH, location, patch, Some("\"2\"")),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers().get("ETag").unwrap(), "\"3\"");
+    let patched: WishlistItem = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(patched.quantity, 2);
+    assert_eq!(patched.price_cents, None);
+    assert_eq!(patched.name, "Book");
+
+    let res = handle_request(
+        request(
+            Method::DELETE,
+            "/wishlists/list-1/items/socks",
+            Body::Empty,
+            None,
+        ),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 204);
+
+    let stored = state
+        .store
+        .get_item("list-1".to_string())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.items, vec![patched]);
+}
+
+#[tokio::test]
+async fn test_item_requests_report_missing_and_stale() {
+    let state = state_with_list().await;
+
+    let res = handle_request(
+        request(
+            Method::GET,
+            "/wishlists/list-1/items/nope",
+            Body::Empty,
+            None,
+        ),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 404);
+
+    let body = Body::from(json!({"name": "Book"}).to_string());
+    let res = handle_request(
+        request(Method::POST, "/wishlists/missing/items", body, None),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 404);
+
+    let patch = Body::from(json!({"quantity": 3}).to_string());
+    let res = handle_request(
+        request(
+            Method::PATCH,
+            "/wishlists/list-1/items/socks",
+            patch,
+            Some("\"7\""),
+        ),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 412);
+
+    let res = handle_request(
+        request(
+            Method::PATCH,
+            "/wishlists/list-1/items/socks",
+            Body::from("[]"),
+            None,
+        ),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 400);
+}
+
+#[tokio::test]
+async fn test_concurrent_item_adds_are_all_kept() {
+    let state = state_with_list().await;
+    let mut tasks = Vec::new();
+    for i in 0..10 {
+        let state = state.clone();
+        tasks.push(tokio::spawn(async move {
+            let body = Body::from(json!({ "name": format!("Gift {}", i) }).to_string());
+            handle_request(
+                request(Method::POST, "/wishlists/list-1/items", body, None),
+                &state,
+            )
+            .await
+            .unwrap()
+            .status()
+        }));
+    }
+    for task in tasks {
+        assert_eq!(task.await.unwrap(), 201);
+    }
+    let stored = state
+        .store
+        .get_item("list-1".to_string())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.items.len(), 11);
+    assert_eq!(stored.version, 11);
+}
@@ -0,0 +1,147 @@
+use lambda_http::{Body, Request};
+use std::sync::Arc;
+use wishlist_api::db::{MemoryStore, WishlistStore};
+use wishlist_api::handlers::{handle_get, Wishlist};
+use wishlist_api::pagination::{decode_cursor, encode_cursor, PageRequest, DEFAULT_LIMIT};
+use wishlist_api::state::AppState;
+
+async fn state_with_lists(count: usize) -> AppState {
+    let store = MemoryStore::new();
+    for i in 0..count {
+        store
+            .create_item(Wishlist {
+                id: format!("list-{:02}", i),
+                name: format!("List {}", i),
+                owner: "alice".to_string(),
+                items: Vec::new(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+    }
+    AppState::new(Arc::new(store))
+}
+
+fn list_request(uri: &str) -> Request {
+    let mut req = Request::new(Body::Empty);
+    *req.uri_mut() = uri.parse().unwrap();
+    req
+}
+
+#[test]
+fn test_cursor_round_trip_and_tampering() {
+    let secret = b"secret";
+    let cursor = encode_cursor(secret, "list-07");
+    assert_eq!(decode_cursor(secret, &cursor).unwrap(), "list-07");
+
+    assert!(decode_cursor(b"other-secret", &cursor).is_err());
+    let forged = encode_cursor(b"other-secret", "list-99");
+    assert!(decode_cursor(secret, &forged).is_err());
+    assert!(decode_cursor(secret, "not-a-cursor").is_err());
+}
+
+#[test]
+fn test_page_request_parsing() {
+    let page = PageRequest::from_query(None, b"secret").unwrap();
+    assert_eq!(page.limit, DEFAULT_LIMIT);
+    assert_eq!(page.start_key, None);
+
+    let cursor = encode_cursor(b"secret", "abc");
+    let query = format!("limit=10&cursor={}", cursor);
+    let page = PageRequest::from_query(Some(&query), b"secret").unwrap();
+    assert_eq!(page.limit, 10);
+    assert_eq!(page.start_key.as_deref(), Some("abc"));
+
+    assert!(PageRequest::from_query(Some("limit=0"), b"secret").is_err());
+    assert!(PageRequest::from_query(Some("limit=1000"), b"secret").is_err());
+    assert!(PageRequest::from_query(Some("limit=ten"), b"secret").is_err());
+}
+
+#[tokio::test]
+async fn test_list_pages_through_every_wishlist() {
+    let state = state_with_lists(5).await;
+    let mut seen = Vec::new();
+    let mut uri = "/wishlists?limit=2".to_string();
+    loop {
+        let res = handle_get(list_request(&uri), &state).await.unwrap();
+        assert_eq!(res.status(), 200);
+        let page: Vec<Wishlist> = serde_json::from_slice(res.body()).unwrap();
+        assert!(page.len() <= 2);
+        seen.extend(page.into_iter().map(|w| w.id));
+        match res.headers().get("X-Next-Cursor") {
+            Some(cursor) => {
+                let link = res.headers().get("Link").unwrap().to_str().unwrap();
+                assert!(link.ends_with("rel=\"next\""));
+                uri = format!("/wishlists?limit=2&cursor={}", cursor.to_str().unwrap());
+            }
+            None => break,
+        }
+    }
+    assert_eq!(
+        seen,
+        vec!["list-00", "list-01", "list-02", "list-03", "list-04"]
+    );
+}
+
+#[tokio::test]
+async fn test_list_rejects_bad_parameters() {
+    let state = state_with_lists(1).await;
+    let res = handle_get(list_request("/wishlists?limit=0"), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 400);
+
+    let res = handle_get(list_request("/wishlists?cursor=garbage"), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 400);
+
+    let res = handle_get(list_request("/wishlists"), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    assert!(res.headers().get("X-Next-Cursor").is_none());
+}
+
+#[tokio::test]
+async fn test_list_filters_by_owner() {
+    let state = state_with_lists(3).await;
+    state
+        .store
+        .create_item(Wishlist {
+            id: "list-bob".to_string(),
+            name: "Bob's list".to_string(),
+            owner: "bob smith".to_string(),
+            items: Vec::new(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    let res = handle_get(list_request("/wishlists?owner=bob+smith"), &state)
+        .await
+        .unwrap();
+    let lists: Vec<Wishlist> = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(lists.len(), 1);
+    assert_eq!(lists[0].id, "list-bob");
+
+    let res = handle_get(list_request("/owners/bob%20smith/wishlists"), &state)
+        .await
+        .unwrap();
+    let lists: Vec<Wishlist> = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(lists.len(), 1);
+
+    let res = handle_get(list_request("/wishlists?owner=alice&limit=2"), &state)
+        .await
+        .unwrap();
+    let lists: Vec<Wishlist> = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(lists.len(), 2);
+    let link = res.headers().get("Link").unwrap().to_str().unwrap();
+    assert!(link.starts_with("</wishlists?owner=alice&limit=2&cursor="));
+
+    let res = handle_get(list_request("/owners/nobody/wishlists"), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.body().as_ref(), b"[]");
+}
@@ -0,0 +1,193 @@
+use lambda_http::http::Method;
+use lambda_http::{Body, Request};
+use serde_json::json;
+use std::sync::Arc;
+use wishlist_api::db::{MemoryStore, WishlistStore};
+use wishlist_api::handlers::{handle_request, Wishlist, WishlistItem};
+use wishlist_api::state::AppState;
+
+const MERGE: &str = "application/merge-patch+json";
+const JSON_PATCH: &str = "application/json-patch+json";
+
+fn patch(
+    uri: &str,
+    content_type: &str,
+    body: serde_json::Value,
+    if_match: Option<&str>,
+) -> Request {
+    let mut req = Request::new(Body::from(body.to_string()));
+    *req.method_mut() = Method::PATCH;
+    *req.uri_mut() = uri.parse().unwrap();
+    req.headers_mut()
+        .insert("Content-Type", content_type.parse().unwrap());
+    if let Some(tag) = if_match {
+        req.headers_mut().insert("If-Match", tag.parse().unwrap());
+    }
+    req
+}
+
+async fn state_with_list() -> AppState {
+    let store = MemoryStore::new();
+    store
+        .create_item(Wishlist {
+            id: "list-1".to_string(),
+            name: "Birthday".to_string(),
+            owner: "alice".to_string(),
+            items: vec![
+                WishlistItem::new("socks", "Socks"),
+                WishlistItem::new("book", "Book"),
+            ],
+            version: 1,
+        })
+        .await
+        .unwrap();
+    AppState::new(Arc::new(store))
+}
+
+#[tokio::test]
+async fn test_merge_patch_renames_without_resending_items() {
+    let state = state_with_list().await;
+    let res = handle_request(
+        patch(
+            "/wishlists/list-1",
+            "application/merge-patch+json; charset=utf-8",
+            json!({"name": "Christmas"}),
+            Some("\"1\""),
+        ),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers().get("ETag").unwrap(), "\"2\"");
+    let patched: Wishlist = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(patched.name, "Christmas");
+    assert_eq!(patched.owner, "alice");
+    assert_eq!(patched.items.len(), 2);
+}
+
+#[tokio::test]
+async fn test_json_patch_with_test_ops() {
+    let state = state_with_list().await;
+    let ops = json!([
+        {"op": "test", "path": "/items/0/id", "value": "socks"},
+        {"op": "remove", "path": "/items/0"},
+        {"op": "add", "path": "/items/-", "value": {"name": "Pen"}},
+        {"op": "replace", "path": "/owner", "value": "bob"}
+    ]);
+    let res = handle_request(patch("/wishlists/list-1", JSON_PATCH, ops, None), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let patched: Wishlist = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(patched.owner, "bob");
+    let names: Vec<&str> = patched.items.iter().map(|i| i.name.as_str()).collect();
+    assert_eq!(names, vec!["Book", "Pen"]);
+    assert!(!patched.items[1].id.is_empty());
+
+    // The same test now fails and nothing is written.
+    let ops = json!([
+        {"op": "test", "path": "/items/0/id", "value": "socks"},
+        {"op": "replace", "path": "/name", "value": "Never"}
+    ]);
+    let res = handle_request(patch("/wishlists/list-1", JSON_PATCH, ops, None), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 409);
+    let stored = state
+        .store
+        .get_item("list-1".to_string())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.name, "Birthday");
+    assert_eq!(stored.version, 2);
+}
+
+#[tokio::test]
+async fn test_patch_rejections() {
+    let state = state_with_list().await;
+
+    let res = handle_request(
+        patch(
+            "/wishlists/list-1",
+            "application/json",
+            json!({"name": "X"}),
+            None,
+        ),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 415);
+    assert!(res.headers().get("Accept-Patch").is_some());
+
+    let res = handle_request(
+        patch("/wishlists/list-1", MERGE, json!({"id": "other"}), None),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 400);
+
+    let res = handle_request(
+        patch("/wishlists/list-1", MERGE, json!({"items": 5}), None),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 422);
+
+    let ops = json!([{"op": "remove", "path": "/items/9"}]);
+    let res = handle_request(patch("/wishlists/list-1", JSON_PATCH, ops, None), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 400);
+
+    let res = handle_request(
+        patch(
+            "/wishlists/list-1",
+            MERGE,
+            json!({"name": "X"}),
+            Some("\"9\""),
+        ),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 412);
+
+    let res = handle_request(
+        patch("/wishlists/missing", MERGE, json!({"name": "X"}), None),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn test_concurrent_merge_patches_all_apply() {
+    let state = state_with_list().await;
+    let mut tasks = Vec::new();
+    for i in 0..5 {
+        let state = state.clone();
+        tasks.push(tokio::spawn(async move {
+            let ops = json!([{"op": "add", "path": "/items/-", "value": {"name": format!("Gift {}", i)}}]);
+            handle_request(patch("/wishlists/list-1", JSON_PATCH, ops, None), &state)
+                .await
+                .unwrap()
+                .status()
+        }));
+    }
+    for task in tasks {
+        assert_eq!(task.await.unwrap(), 200);
+    }
+    let stored = state
+        .store
+        .get_item("list-1".to_string())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.items.len(), 7);
+}
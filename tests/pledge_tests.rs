@@ -0,0 +1,226 @@
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use lambda_http::http::Method;
+use lambda_http::{Body, Request, Response};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use wishlist_api::auth::{Authenticator, HmacSecret};
+use wishlist_api::db::MemoryStore;
+use wishlist_api::handlers::{handle_request, Wishlist};
+use wishlist_api::state::AppState;
+
+const SECRET: &[u8] = b"test-secret";
+
+fn token(sub: &str) -> String {
+    let exp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 300;
+    encode(
+        &Header::new(Algorithm::HS256),
+        &json!({"sub": sub, "exp": exp}),
+        &EncodingKey::from_secret(SECRET),
+    )
+    .unwrap()
+}
+
+fn state() -> AppState {
+    AppState::new(Arc::new(MemoryStore::new()))
+        .with_auth(Authenticator::new(vec![Arc::new(HmacSecret::new(SECRET))]))
+}
+
+async fn send(
+    state: &AppState,
+    method: Method,
+    uri: &str,
+    body: Value,
+    caller: &str,
+) -> Response<Body> {
+    let body = if body.is_null() {
+        String::new()
+    } else {
+        body.to_string()
+    };
+    let mut req = Request::new(body.into());
+    *req.method_mut() = method;
+    *req.uri_mut() = uri.parse().unwrap();
+    req.headers_mut().insert(
+        "Authorization",
+        format!("Bearer {}", token(caller)).parse().unwrap(),
+    );
+    handle_request(req, state).await.unwrap()
+}
+
+fn json_body(res: &Response<Body>) -> Value {
+    serde_json::from_slice(res.body()).unwrap()
+}
+
+/// Creates Alice's list with a 50 USD bike and an unpriced card, shared with
+/// `viewers`, and returns the pledge URIs of the bike and the card.
+async fn create(state: &AppState, viewers: &[&str]) -> (String, String) {
+    let body = json!({"name": "Birthday", "items": [
+        {"name": "Bike", "price_cents": 5000, "currency": "USD"},
+        {"name": "Card"},
+    ]});
+    let res = send(state, Method::POST, "/wishlists", body, "alice").await;
+    assert_eq!(res.status(), 201);
+    let wishlist: Wishlist = serde_json::from_slice(res.body()).unwrap();
+    for viewer in viewers {
+        let uri = format!("/wishlists/{}/collaborators/{}", wishlist.id, viewer);
+        let res = send(state, Method::PUT, &uri, json!({"role": "viewer"}), "alice").await;
+        assert_eq!(res.status(), 200);
+    }
+    let pledges = |i: usize| {
+        format!(
+            "/wishlists/{}/items/{}/pledges",
+            wishlist.id, wishlist.items[i].id
+        )
+    };
+    (pledges(0), pledges(1))
+}
+
+#[tokio::test]
+async fn test_pledges_fund_item_until_closed() {
+    let state = state();
+    let (bike, _) = create(&state, &["vera", "walt"]).await;
+
+    let body = json!({"amount_cents": 3000, "message": "From all of us"});
+    let res = send(&state, Method::POST, &bike, body, "vera").await;
+    assert_eq!(res.status(), 201);
+    let receipt = json_body(&res);
+    assert_eq!(receipt["pledge"]["contributor"], "vera");
+    assert_eq!(receipt["pledge"]["currency"], "USD");
+    assert_eq!(receipt["pledge"]["message"], "From all of us");
+    assert_eq!(receipt["funding"]["remaining_cents"], 2000);
+    assert_eq!(receipt["funding"]["funded_percent"], 60);
+    assert_eq!(receipt["funding"]["closed"], false);
+
+    let res = send(
+        &state,
+        Method::POST,
+        &bike,
+        json!({"amount_cents": 2500}),
+        "walt",
+    )
+    .await;
+    assert_eq!(res.status(), 409);
+    let res = send(
+        &state,
+        Method::POST,
+        &bike,
+        json!({"amount_cents": 2000, "currency": "USD"}),
+        "walt",
+    )
+    .await;
+    assert_eq!(res.status(), 201);
+    assert_eq!(json_body(&res)["funding"]["closed"], true);
+    let res = send(
+        &state,
+        Method::POST,
+        &bike,
+        json!({"amount_cents": 1}),
+        "alice",
+    )
+    .await;
+    assert_eq!(res.status(), 409);
+
+    let res = send(&state, Method::GET, &bike, Value::Null, "alice").await;
+    assert_eq!(res.status(), 200);
+    let funding = json_body(&res);
+    assert_eq!(funding["target_cents"], 5000);
+    assert_eq!(funding["pledged_cents"], 5000);
+    assert_eq!(funding["remaining_cents"], 0);
+    assert_eq!(funding["funded_percent"], 100);
+    assert_eq!(funding["closed"], true);
+    let contributors: Vec<&Value> = funding["pledges"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|pledge| &pledge["contributor"])
+        .collect();
+    assert_eq!(contributors, [&json!("vera"), &json!("walt")]);
+}
+
+#[tokio::test]
+async fn test_pledges_are_validated() {
+    let state = state();
+    let (bike, card) = create(&state, &["vera"]).await;
+
+    for (body, field, rule) in [
+        (json!({"amount_cents": 0}), "amount_cents", "min"),
+        (
+            json!({"amount_cents": 100, "currency": "EUR"}),
+            "currency",
+            "mismatch",
+        ),
+        (
+            json!({"amount_cents": 100, "currency": "usd"}),
+            "currency",
+            "format",
+        ),
+        (
+            json!({"amount_cents": 100, "message": "x".repeat(501)}),
+            "message",
+            "max_length",
+        ),
+    ] {
+        let res = send(&state, Method::POST, &bike, body, "vera").await;
+        assert_eq!(res.status(), 422);
+        let error = &json_body(&res)["errors"][0];
+        assert_eq!(error["field"], field);
+        assert_eq!(error["rule"], rule);
+    }
+
+    let res = send(
+        &state,
+        Method::POST,
+        &card,
+        json!({"amount_cents": 100}),
+        "vera",
+    )
+    .await;
+    assert_eq!(res.status(), 409);
+    let res = send(&state, Method::GET, &card, Value::Null, "vera").await;
+    assert_eq!(json_body(&res)["closed"], false);
+    assert!(json_body(&res).get("funded_percent").is_none());
+
+    let res = send(&state, Method::GET, &bike, Value::Null, "mallory").await;
+    assert_eq!(res.status(), 403);
+    let missing = bike.replace("/items/", "/items/missing-");
+    let res = send(
+        &state,
+        Method::POST,
+        &missing,
+        json!({"amount_cents": 1}),
+        "vera",
+    )
+    .await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_pledges_never_overfund() {
+    let state = state();
+    let (bike, _) = create(&state, &[]).await;
+    let mut tasks = Vec::new();
+    for _ in 0..10 {
+        let state = state.clone();
+        let bike = bike.clone();
+        tasks.push(tokio::spawn(async move {
+            let body = json!({"amount_cents": 1000});
+            send(&state, Method::POST, &bike, body, "alice")
+                .await
+                .status()
+                .as_u16()
+        }));
+    }
+    let mut statuses = Vec::new();
+    for task in tasks {
+        statuses.push(task.await.unwrap());
+    }
+    assert_eq!(statuses.iter().filter(|s| **s == 201).count(), 5);
+    assert_eq!(statuses.iter().filter(|s| **s == 409).count(), 5);
+    let res = send(&state, Method::GET, &bike, Value::Null, "alice").await;
+    assert_eq!(json_body(&res)["pledged_cents"], 5000);
+}
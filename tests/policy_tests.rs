@@ -0,0 +1,245 @@
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use lambda_http::http::Method;
+use lambda_http::{Body, Request};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use wishlist_api::auth::{Authenticator, HmacSecret};
+use wishlist_api::db::MemoryStore;
+use wishlist_api::handlers::{handle_request, Wishlist};
+use wishlist_api::state::AppState;
+
+const SECRET: &[u8] = b"test-secret";
+
+fn token(sub: &str, roles: &[&str]) -> String {
+    let exp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 300;
+    encode(
+        &Header::new(Algorithm::HS256),
+        &json!({"sub": sub, "exp": exp, "roles": roles}),
+        &EncodingKey::from_secret(SECRET),
+    )
+    .unwrap()
+}
+
+fn request(method: Method, uri: &str, body: impl Into<Body>, token: &str) -> Request {
+    let mut req = Request::new(body.into());
+    *req.method_mut() = method;
+    *req.uri_mut() = uri.parse().unwrap();
+    req.headers_mut().insert(
+        "Authorization",
+        format!("Bearer {}", token).parse().unwrap(),
+    );
+    req
+}
+
+fn state() -> AppState {
+    AppState::new(Arc::new(MemoryStore::new()))
+        .with_auth(Authenticator::new(vec![Arc::new(HmacSecret::new(SECRET))]))
+}
+
+async fn create(state: &AppState, token: &str, name: &str) -> Wishlist {
+    let body = json!({"name": name, "items": [{"name": "Book"}]});
+    let res = handle_request(
+        request(Method::POST, "/wishlists", body.to_string(), token),
+        state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 201);
+    serde_json::from_slice(res.body()).unwrap()
+}
+
+async fn status(state: &AppState, method: Method, uri: &str, body: Value, token: &str) -> u16 {
+    let body = if body.is_null() {
+        String::new()
+    } else {
+        body.to_string()
+    };
+    let mut req = request(method, uri, body, token);
+    if req.method() == Method::PATCH {
+        req.headers_mut().insert(
+            "Content-Type",
+            "application/merge-patch+json".parse().unwrap(),
+        );
+    }
+    handle_request(req, state).await.unwrap().status().as_u16()
+}
+
+#[tokio::test]
+async fn test_non_owner_is_forbidden() {
+    let state = state();
+    let alice = token("alice", &[]);
+    let bob = token("bob", &[]);
+    let wishlist = create(&state, &alice, "Birthday").await;
+    let uri = format!("/wishlists/{}", wishlist.id);
+    let item = format!("{}/items/{}", uri, wishlist.items[0].id);
+    let replacement = json!({"name": "Mine now", "items": []});
+
+    let res = handle_request(request(Method::GET, &uri, "", &bob), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 403);
+    let problem: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(problem["code"], "forbidden");
+
+    assert_eq!(
+        status(&state, Method::PUT, &uri, replacement.clone(), &bob).await,
+        403
+    );
+    assert_eq!(
+        status(&state, Method::PATCH, &uri, json!({"name": "x"}), &bob).await,
+        403
+    );
+    assert_eq!(
+        status(&state, Method::DELETE, &uri, Value::Null, &bob).await,
+        403
+    );
+    assert_eq!(
+        status(
+            &state,
+            Method::POST,
+            &format!("{}/items", uri),
+            json!({"name": "Pen"}),
+            &bob
+        )
+        .await,
+        403
+    );
+    assert_eq!(
+        status(&state, Method::PATCH, &item, json!({"quantity": 2}), &bob).await,
+        403
+    );
+    assert_eq!(
+        status(&state, Method::DELETE, &item, Value::Null, &bob).await,
+        403
+    );
+
+    // The deprecated body-addressed forms are guarded too.
+    let body = json!({"id": wishlist.id, "name": "Mine now", "items": []});
+    assert_eq!(
+        status(&state, Method::PUT, "/wishlists", body, &bob).await,
+        403
+    );
+    let body = json!({"id": wishlist.id});
+    assert_eq!(
+        status(&state, Method::DELETE, "/wishlists", body, &bob).await,
+        403
+    );
+
+    // Nothing changed.
+    assert_eq!(
+        status(&state, Method::GET, &item, Value::Null, &alice).await,
+        200
+    );
+}
+
+#[tokio::test]
+async fn test_owner_and_admin_have_access() {
+    let state = state();
+    let alice = token("alice", &[]);
+    let admin = token("carol", &["admin"]);
+    let wishlist = create(&state, &alice, "Birthday").await;
+    let uri = format!("/wishlists/{}", wishlist.id);
+
+    assert_eq!(
+        status(&state, Method::GET, &uri, Value::Null, &alice).await,
+        200
+    );
+    assert_eq!(
+        status(
+            &state,
+            Method::PATCH,
+            &uri,
+            json!({"name": "Party"}),
+            &alice
+        )
+        .await,
+        200
+    );
+    assert_eq!(
+        status(&state, Method::GET, &uri, Value::Null, &admin).await,
+        200
+    );
+    let replacement = json!({"name": "Cleaned up", "items": []});
+    assert_eq!(
+        status(&state, Method::PUT, &uri, replacement, &admin).await,
+        200
+    );
+
+    let res = handle_request(request(Method::GET, &uri, "", &admin), &state)
+        .await
+        .unwrap();
+    let stored: Wishlist = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(stored.owner, "alice");
+
+    assert_eq!(
+        status(&state, Method::DELETE, &uri, Value::Null, &admin).await,
+        204
+    );
+}
+
+#[tokio::test]
+async fn test_missing_wishlist_is_not_found() {
+    let state = state();
+    let bob = token("bob", &[]);
+    assert_eq!(
+        status(&state, Method::GET, "/wishlists/nope", Value::Null, &bob).await,
+        404
+    );
+}
+
+#[tokio::test]
+async fn test_listing_is_limited_to_own_wishlists() {
+    let state = state();
+    let alice = token("alice", &[]);
+    let bob = token("bob", &[]);
+    let admin = token("carol", &["admin"]);
+    create(&state, &alice, "Alice's").await;
+    create(&state, &bob, "Bob's").await;
+
+    let res = handle_request(request(Method::GET, "/wishlists", "", &alice), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let listed: Vec<Wishlist> = serde_json::from_slice(res.body()).unwrap();
+    let owners: Vec<&str> = listed.iter().map(|w| w.owner.as_str()).collect();
+    assert_eq!(owners, vec!["alice"]);
+
+    assert_eq!(
+        status(
+            &state,
+            Method::GET,
+            "/wishlists?owner=bob",
+            Value::Null,
+            &alice
+        )
+        .await,
+        403
+    );
+    assert_eq!(
+        status(
+            &state,
+            Method::GET,
+            "/owners/bob/wishlists",
+            Value::Null,
+            &alice
+        )
+        .await,
+        403
+    );
+    assert_eq!(
+        status(
+            &state,
+            Method::GET,
+            "/owners/bob/wishlists",
+            Value::Null,
+            &admin
+        )
+        .await,
+        200
+    );
+}
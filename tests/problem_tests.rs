@@ -0,0 +1,131 @@
+use lambda_http::http::{Method, StatusCode};
+use lambda_http::{Body, Request, Response};
+use serde_json::json;
+use std::sync::Arc;
+use wishlist_api::db::MemoryStore;
+use wishlist_api::error::AppError;
+use wishlist_api::handlers::handle_request;
+use wishlist_api::problem::{Problem, PROBLEM_CONTENT_TYPE};
+use wishlist_api::state::AppState;
+
+fn request(method: Method, uri: &str, body: impl Into<Body>) -> Request {
+    let mut req = Request::new(body.into());
+    *req.method_mut() = method;
+    *req.uri_mut() = uri.parse().unwrap();
+    req
+}
+
+fn problem(res: &Response<Body>) -> Problem {
+    assert_eq!(
+        res.headers().get("Content-Type").unwrap(),
+        PROBLEM_CONTENT_TYPE
+    );
+    serde_json::from_slice(res.body()).unwrap()
+}
+
+#[tokio::test]
+async fn test_errors_are_problem_documents() {
+    let state = AppState::new(Arc::new(MemoryStore::new()));
+
+    let mut req = request(Method::GET, "/wishlists/missing", Body::Empty);
+    req.headers_mut()
+        .insert("X-Request-Id", "req-123".parse().unwrap());
+    let res = handle_request(req, &state).await.unwrap();
+    assert_eq!(res.status(), 404);
+    assert_eq!(res.headers().get("X-Request-Id").unwrap(), "req-123");
+    let body = problem(&res);
+    assert_eq!(body.status, 404);
+    assert_eq!(body.code, "not_found");
+    assert_eq!(body.title, "Not Found");
+    assert_eq!(body.instance.as_deref(), Some("/wishlists/missing"));
+    assert_eq!(body.request_id.as_deref(), Some("req-123"));
+
+    let res = handle_request(request(Method::POST, "/health", Body::Empty), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 405);
+    let body = problem(&res);
+    assert_eq!(body.code, "method_not_allowed");
+    let generated = res.headers().get("X-Request-Id").unwrap().to_str().unwrap();
+    assert_eq!(body.request_id.as_deref(), Some(generated));
+
+    let res = handle_request(request(Method::POST, "/wishlists", "not json"), &state)
+        .await
+        .unwrap();
+    assert_eq!(problem(&res).code, "bad_request");
+
+    let body = json!({"name": "", "owner": "alice", "items": []});
+    let res = handle_request(
+        request(Method::POST, "/wishlists", body.to_string()),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 422);
+    let body = problem(&res);
+    assert_eq!(body.code, "validation_failed");
+    assert_eq!(body.errors.unwrap()[0].field, "name");
+
+    let res = handle_request(request(Method::PUT, "/wishlists", "{}"), &state)
+        .await
+        .unwrap();
+    assert_eq!(problem(&res).code, "validation_failed");
+
+    let res = handle_request(
+        request(Method::HEAD, "/wishlists/missing", Body::Empty),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 404);
+    assert!(res.body().is_empty());
+}
+
+#[test]
+fn test_app_errors_map_to_status_and_code() {
+    let cases = [
+        (AppError::NotFound, StatusCode::NOT_FOUND, "not_found"),
+        (
+            AppError::Conflict("taken".to_string()),
+            StatusCode::CONFLICT,
+            "conflict",
+        ),
+        (
+            AppError::Validation(vec![]),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "validation_failed",
+        ),
+        (
+            AppError::Unauthorized("no token".to_string()),
+            StatusCode::UNAUTHORIZED,
+            "unauthorized",
+        ),
+        (
+            AppError::Forbidden("not yours".to_string()),
+            StatusCode::FORBIDDEN,
+            "forbidden",
+        ),
+        (
+            AppError::Throttled("slow down".to_string()),
+            StatusCode::SERVICE_UNAVAILABLE,
+            "throttled",
+        ),
+        (AppError::MissingId, StatusCode::BAD_REQUEST, "missing_id"),
+        (
+            AppError::DynamoDb("table wishlists-prod".to_string()),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "storage_error",
+        ),
+    ];
+    for (error, status, code) in cases {
+        let res: Response<Body> = error.into();
+        assert_eq!(res.status(), status);
+        let body = problem(&res);
+        assert_eq!(body.status, status.as_u16());
+        assert_eq!(body.code, code);
+        assert_eq!(body.type_uri, "about:blank");
+        if status.is_server_error() && code != "throttled" {
+            assert!(!body.detail.unwrap().contains("wishlists-prod"));
+        }
+    }
+}
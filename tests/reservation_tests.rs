@@ -0,0 +1,291 @@
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use lambda_http::http::Method;
+use lambda_http::{Body, Request, Response};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use wishlist_api::auth::{Authenticator, HmacSecret};
+use wishlist_api::db::MemoryStore;
+use wishlist_api::handlers::{handle_request, Wishlist};
+use wishlist_api::state::AppState;
+
+const SECRET: &[u8] = b"test-secret";
+
+fn token(sub: &str) -> String {
+    let exp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 300;
+    encode(
+        &Header::new(Algorithm::HS256),
+        &json!({"sub": sub, "exp": exp}),
+        &EncodingKey::from_secret(SECRET),
+    )
+    .unwrap()
+}
+
+fn state() -> AppState {
+    AppState::new(Arc::new(MemoryStore::new()))
+        .with_auth(Authenticator::new(vec![Arc::new(HmacSecret::new(SECRET))]))
+}
+
+fn request(method: Method, uri: &str, body: Value, caller: Option<&str>) -> Request {
+    let body = if body.is_null() {
+        String::new()
+    } else {
+        body.to_string()
+    };
+    let mut req = Request::new(body.into());
+    *req.method_mut() = method;
+    *req.uri_mut() = uri.parse().unwrap();
+    if let Some(caller) = caller {
+        req.headers_mut().insert(
+            "Authorization",
+            format!("Bearer {}", token(caller)).parse().unwrap(),
+        );
+    }
+    req
+}
+
+async fn send(
+    state: &AppState,
+    method: Method,
+    uri: &str,
+    body: Value,
+    caller: Option<&str>,
+) -> Response<Body> {
+    handle_request(request(method, uri, body, caller), state)
+        .await
+        .unwrap()
+}
+
+fn json_body(res: &Response<Body>) -> Value {
+    serde_json::from_slice(res.body()).unwrap()
+}
+
+/// Creates Alice's list with one item of the given quantity, shared with
+/// Vera as a viewer, and returns the list and item IDs.
+async fn create(state: &AppState, quantity: u32) -> (String, String) {
+    let body = json!({"name": "Birthday", "items": [{"name": "Mug", "quantity": quantity}]});
+    let res = send(state, Method::POST, "/wishlists", body, Some("alice")).await;
+    assert_eq!(res.status(), 201);
+    let wishlist: Wishlist = serde_json::from_slice(res.body()).unwrap();
+    let uri = format!("/wishlists/{}/collaborators/vera", wishlist.id);
+    let res = send(
+        state,
+        Method::PUT,
+        &uri,
+        json!({"role": "viewer"}),
+        Some("alice"),
+    )
+    .await;
+    assert_eq!(res.status(), 200);
+    (wishlist.id, wishlist.items[0].id.clone())
+}
+
+#[tokio::test]
+async fn test_viewer_reserves_and_owner_cannot_see() {
+    let state = state();
+    let (id, item_id) = create(&state, 2).await;
+    let uri = format!("/wishlists/{}/items/{}/reservation", id, item_id);
+
+    let res = send(&state, Method::PUT, &uri, Value::Null, Some("vera")).await;
+    assert_eq!(res.status(), 200);
+    let reservation = json_body(&res);
+    assert_eq!(reservation["reserver"], "vera");
+    assert_eq!(reservation["quantity"], 1);
+    assert_eq!(reservation["purchased"], false);
+
+    let body = json!({"quantity": 1, "purchased": true});
+    let res = send(&state, Method::PUT, &uri, body, Some("vera")).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(json_body(&res)["purchased"], true);
+
+    let list = format!("/wishlists/{}/reservations", id);
+    let res = send(&state, Method::GET, &list, Value::Null, Some("vera")).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(json_body(&res).as_array().unwrap().len(), 1);
+
+    for (method, uri) in [(Method::GET, &list), (Method::PUT, &uri)] {
+        let res = send(&state, method, uri, Value::Null, Some("alice")).await;
+        assert_eq!(res.status(), 403);
+    }
+    let res = send(
+        &state,
+        Method::GET,
+        &format!("/wishlists/{}", id),
+        Value::Null,
+        Some("alice"),
+    )
+    .await;
+    assert!(!String::from_utf8_lossy(res.body()).contains("vera"));
+
+    let res = send(&state, Method::GET, &list, Value::Null, Some("mallory")).await;
+    assert_eq!(res.status(), 403);
+}
+
+#[tokio::test]
+async fn test_reservations_cannot_exceed_quantity() {
+    let state = state();
+    let (id, item_id) = create(&state, 2).await;
+    let uri = format!("/wishlists/{}/items/{}/reservation", id, item_id);
+    let res = send(
+        &state,
+        Method::PUT,
+        &uri,
+        json!({"quantity": 3}),
+        Some("vera"),
+    )
+    .await;
+    assert_eq!(res.status(), 409);
+    let res = send(
+        &state,
+        Method::PUT,
+        &uri,
+        json!({"quantity": 0}),
+        Some("vera"),
+    )
+    .await;
+    assert_eq!(res.status(), 422);
+
+    let res = send(
+        &state,
+        Method::PUT,
+        &uri,
+        json!({"quantity": 2}),
+        Some("vera"),
+    )
+    .await;
+    assert_eq!(res.status(), 200);
+    let res = send(&state, Method::PUT, &uri, Value::Null, Some("mallory")).await;
+    assert_eq!(res.status(), 403);
+
+    let res = send(&state, Method::DELETE, &uri, Value::Null, Some("vera")).await;
+    assert_eq!(res.status(), 204);
+    let res = send(&state, Method::DELETE, &uri, Value::Null, Some("vera")).await;
+    assert_eq!(res.status(), 404);
+    let missing = format!("/wishlists/{}/items/missing/reservation", id);
+    let res = send(&state, Method::PUT, &missing, Value::Null, Some("vera")).await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_claims_on_last_unit_have_one_winner() {
+    let state = state();
+    let (id, item_id) = create(&state, 1).await;
+    let mut tasks = Vec::new();
+    for i in 0..10 {
+        let state = state.clone();
+        let uri = format!("/wishlists/{}/items/{}/reservation", id, item_id);
+        let collaborator = format!("/wishlists/{}/collaborators/giver-{}", id, i);
+        tasks.push(tokio::spawn(async move {
+            let body = json!({"role": "viewer"});
+            send(&state, Method::PUT, &collaborator, body, Some("alice")).await;
+            let caller = format!("giver-{}", i);
+            send(&state, Method::PUT, &uri, Value::Null, Some(&caller))
+                .await
+                .status()
+                .as_u16()
+        }));
+    }
+    let mut statuses = Vec::new();
+    for task in tasks {
+        statuses.push(task.await.unwrap());
+    }
+    assert_eq!(statuses.iter().filter(|s| **s == 200).count(), 1);
+    assert_eq!(statuses.iter().filter(|s| **s == 409).count(), 9);
+}
+
+#[tokio::test]
+async fn test_guests_reserve_through_share_link() {
+    let state = state();
+    let (id, item_id) = create(&state, 3).await;
+    let shares = format!("/wishlists/{}/shares", id);
+    let res = send(&state, Method::POST, &shares, Value::Null, Some("alice")).await;
+    assert_eq!(res.status(), 201);
+    let url = json_body(&res)["url"].as_str().unwrap().to_string();
+    let uri = format!("{}/items/{}/reservation", url, item_id);
+
+    let res = send(&state, Method::PUT, &uri, json!({"quantity": 1}), None).await;
+    assert_eq!(res.status(), 422);
+    let body = json!({"name": "Aunt May", "quantity": 2, "purchased": true});
+    let res = send(&state, Method::PUT, &uri, body, None).await;
+    assert_eq!(res.status(), 200);
+    let reserved = json_body(&res);
+    assert_eq!(reserved["reserver"], "guest:Aunt May");
+    assert!(reserved.get("claim_hash").is_none());
+    let claim = reserved["claim_token"].as_str().unwrap().to_string();
+
+    let res = send(&state, Method::GET, &url, Value::Null, None).await;
+    let shared = json_body(&res);
+    assert_eq!(
+        shared["items"][0]["reservations"],
+        json!({"reserved": 2, "purchased": 2})
+    );
+    assert!(!String::from_utf8_lossy(res.body()).contains("Aunt May"));
+
+    let list = format!("/wishlists/{}/reservations", id);
+    let res = send(&state, Method::GET, &list, Value::Null, Some("vera")).await;
+    assert_eq!(json_body(&res)[0]["reserver"], "guest:Aunt May");
+    assert!(json_body(&res)[0].get("claim_hash").is_none());
+
+    let mut req = request(
+        Method::DELETE,
+        &format!("{}?name=Aunt%20May", uri),
+        Value::Null,
+        None,
+    );
+    req.headers_mut()
+        .insert("X-Claim-Token", claim.parse().unwrap());
+    let res = handle_request(req, &state).await.unwrap();
+    assert_eq!(res.status(), 204);
+    let res = send(&state, Method::GET, &url, Value::Null, None).await;
+    assert_eq!(
+        json_body(&res)["items"][0]["reservations"],
+        json!({"reserved": 0, "purchased": 0})
+    );
+    let res = send(
+        &state,
+        Method::PUT,
+        "/shared/unknown/items/x/reservation",
+        json!({"name": "Bob"}),
+        None,
+    )
+    .await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn test_guest_claim_needs_its_token() {
+    let state = state();
+    let (id, item_id) = create(&state, 3).await;
+    let shares = format!("/wishlists/{}/shares", id);
+    let res = send(&state, Method::POST, &shares, Value::Null, Some("alice")).await;
+    let url = json_body(&res)["url"].as_str().unwrap().to_string();
+    let uri = format!("{}/items/{}/reservation", url, item_id);
+    let res = send(&state, Method::PUT, &uri, json!({"name": "May"}), None).await;
+    assert_eq!(res.status(), 200);
+    let claim = json_body(&res)["claim_token"].as_str().unwrap().to_string();
+
+    // Someone else typing the same name can neither release nor take over
+    // May's claim.
+    let release = format!("{}?name=May", uri);
+    let res = send(&state, Method::DELETE, &release, Value::Null, None).await;
+    assert_eq!(res.status(), 403);
+    let mut req = request(Method::DELETE, &release, Value::Null, None);
+    req.headers_mut()
+        .insert("X-Claim-Token", "not-the-token".parse().unwrap());
+    assert_eq!(handle_request(req, &state).await.unwrap().status(), 403);
+    let body = json!({"name": "May", "quantity": 3});
+    let res = send(&state, Method::PUT, &uri, body.clone(), None).await;
+    assert_eq!(res.status(), 403);
+
+    let mut req = request(Method::PUT, &uri, body, None);
+    req.headers_mut()
+        .insert("X-Claim-Token", claim.parse().unwrap());
+    let res = handle_request(req, &state).await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(json_body(&res)["quantity"], 3);
+    assert!(json_body(&res).get("claim_token").is_none());
+}
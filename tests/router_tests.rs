@@ -0,0 +1,189 @@
+use lambda_http::http::Method;
+use lambda_http::{Body, Request};
+use std::sync::Arc;
+use wishlist_api::db::{MemoryStore, WishlistStore};
+use wishlist_api::handlers::{handle_request, Wishlist};
+use wishlist_api::router::Route;
+use wishlist_api::state::AppState;
+
+fn request(method: Method, uri: &str) -> Request {
+    let mut req = Request::new(Body::Empty);
+    *req.method_mut() = method;
+    *req.uri_mut() = uri.parse().unwrap();
+    req
+}
+
+#[test]
+fn test_recognize_routes() {
+    assert_eq!(Route::recognize("/health"), Some(Route::Health));
+    assert_eq!(Route::recognize("/prod/wishlists/"), Some(Route::Wishlists));
+    assert_eq!(
+        Route::recognize("/owners/bob%20smith/wishlists"),
+        Some(Route::OwnerWishlists {
+            owner: "bob smith".to_string()
+        })
+    );
+    assert_eq!(
+        Route::recognize("/wishlists/a%2Fb"),
+        Some(Route::Wishlist {
+            id: "a/b".to_string()
+        })
+    );
+    assert_eq!(
+        Route::recognize("/wishlists/w/items/i"),
+        Some(Route::WishlistItem {
+            wishlist_id: "w".to_string(),
+            item_id: "i".to_string()
+        })
+    );
+
+    assert_eq!(Route::recognize("/wishlist"), None);
+    assert_eq!(Route::recognize("/wishlists/a/b"), None);
+    assert_eq!(Route::recognize("/wishlists//items"), None);
+    assert_eq!(Route::recognize("/products/wishlists"), None);
+    assert_eq!(Route::recognize("/"), None);
+}
+
+#[tokio::test]
+async fn test_wrong_method_is_405_with_allow() {
+    let state = AppState::new(Arc::new(MemoryStore::new()));
+    let res = handle_request(request(Method::POST, "/health"), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 405);
+    assert_eq!(res.headers().get("Allow").unwrap(), "GET, HEAD, OPTIONS");
+
+    let res = handle_request(request(Method::PATCH, "/wishlists"), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 405);
+    assert_eq!(
+        res.headers().get("Allow").unwrap(),
+        "GET, POST, PUT, DELETE, HEAD, OPTIONS"
+    );
+
+    let res = handle_request(request(Method::GET, "/nowhere"), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn test_head_and_options() {
+    let store = MemoryStore::new();
+    store
+        .create_item(Wishlist {
+            id: "list-1".to_string(),
+            name: "Birthday".to_string(),
+            owner: "alice".to_string(),
+            version: 4,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let state = AppState::new(Arc::new(store));
+
+    let res = handle_request(request(Method::HEAD, "/wishlists/list-1"), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers().get("ETag").unwrap(), "\"4\"");
+    assert!(res.body().is_empty());
+
+    let res = handle_request(request(Method::HEAD, "/wishlists/missing"), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 404);
+    assert!(res.body().is_empty());
+
+    let res = handle_request(request(Method::OPTIONS, "/wishlists/list-1/items"), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 204);
+    assert_eq!(res.headers().get("Allow").unwrap(), "POST, OPTIONS");
+}
+
+fn request_with_body(method: Method, uri: &str, body: serde_json::Value) -> Request {
+    let mut req = request(method, uri);
+    *req.body_mut() = Body::from(body.to_string());
+    req
+}
+
+#[tokio::test]
+async fn test_put_and_delete_by_path() {
+    let store = MemoryStore::new();
+    for id in ["list-1", "list-2"] {
+        store
+            .create_item(Wishlist {
+                id: id.to_string(),
+                name: "Birthday".to_string(),
+                owner: "alice".to_string(),
+                version: 1,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+    }
+    let state = AppState::new(Arc::new(store));
+
+    let body = serde_json::json!({"name": "Renamed", "owner": "alice", "items": []});
+    let res = handle_request(
+        request_with_body(Method::PUT, "/wishlists/list-1", body),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 200);
+    assert!(res.headers().get("Deprecation").is_none());
+    let updated: Wishlist = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(updated.id, "list-1");
+    assert_eq!(updated.name, "Renamed");
+
+    let body = serde_json::json!({"id": "list-2", "name": "X", "owner": "alice", "items": []});
+    let res = handle_request(
+        request_with_body(Method::PUT, "/wishlists/list-1", body),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 400);
+
+    let res = handle_request(
+        request_with_body(
+            Method::DELETE,
+            "/wishlists/list-1",
+            serde_json::json!({"id": "list-2"}),
+        ),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 400);
+
+    let res = handle_request(request(Method::DELETE, "/wishlists/list-1"), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 204);
+    let res = handle_request(request(Method::DELETE, "/wishlists/list-1"), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 404);
+
+    // The body-based forms still work but are flagged as deprecated.
+    let res = handle_request(
+        request_with_body(
+            Method::DELETE,
+            "/wishlists",
+            serde_json::json!({"id": "list-2"}),
+        ),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 204);
+    assert_eq!(res.headers().get("Deprecation").unwrap(), "true");
+    assert_eq!(
+        res.headers().get("Link").unwrap(),
+        "</wishlists/list-2>; rel=\"successor-version\""
+    );
+}
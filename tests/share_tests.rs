@@ -0,0 +1,237 @@
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use lambda_http::http::Method;
+use lambda_http::{Body, Request, Response};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use wishlist_api::auth::{Authenticator, HmacSecret};
+use wishlist_api::db::{MemoryStore, WishlistStore};
+use wishlist_api::handlers::{handle_request, Wishlist};
+use wishlist_api::secret::{hash_password, verify_password};
+use wishlist_api::share::{ShareLink, ShareRequest};
+use wishlist_api::state::AppState;
+
+const SECRET: &[u8] = b"test-secret";
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn token(sub: &str) -> String {
+    encode(
+        &Header::new(Algorithm::HS256),
+        &json!({"sub": sub, "exp": now() + 300}),
+        &EncodingKey::from_secret(SECRET),
+    )
+    .unwrap()
+}
+
+fn state() -> (AppState, Arc<MemoryStore>) {
+    let store = Arc::new(MemoryStore::new());
+    let state = AppState::new(store.clone())
+        .with_auth(Authenticator::new(vec![Arc::new(HmacSecret::new(SECRET))]));
+    (state, store)
+}
+
+async fn send(
+    state: &AppState,
+    method: Method,
+    uri: &str,
+    body: Value,
+    caller: &str,
+) -> Response<Body> {
+    let body = if body.is_null() {
+        String::new()
+    } else {
+        body.to_string()
+    };
+    let mut req = Request::new(body.into());
+    *req.method_mut() = method;
+    *req.uri_mut() = uri.parse().unwrap();
+    req.headers_mut().insert(
+        "Authorization",
+        format!("Bearer {}", token(caller)).parse().unwrap(),
+    );
+    handle_request(req, state).await.unwrap()
+}
+
+/// Opens a share link without credentials, as a relative would.
+async fn open(state: &AppState, url: &str, password: Option<&str>) -> Response<Body> {
+    let mut req = Request::new(Body::Empty);
+    *req.uri_mut() = url.parse().unwrap();
+    if let Some(password) = password {
+        req.headers_mut()
+            .insert("X-Share-Password", password.parse().unwrap());
+    }
+    handle_request(req, state).await.unwrap()
+}
+
+fn json_body(res: &Response<Body>) -> Value {
+    serde_json::from_slice(res.body()).unwrap()
+}
+
+async fn create(state: &AppState, owner: &str) -> String {
+    let body = json!({"name": "Birthday", "items": [{"name": "Book"}]});
+    let res = send(state, Method::POST, "/wishlists", body, owner).await;
+    assert_eq!(res.status(), 201);
+    serde_json::from_slice::<Wishlist>(res.body()).unwrap().id
+}
+
+/// Makes a share link as `owner` and returns the created link's JSON.
+async fn create_share(state: &AppState, id: &str, body: Value) -> Value {
+    let uri = format!("/wishlists/{}/shares", id);
+    let res = send(state, Method::POST, &uri, body, "alice").await;
+    assert_eq!(res.status(), 201);
+    assert_eq!(res.headers()["Cache-Control"], "no-store");
+    json_body(&res)
+}
+
+#[tokio::test]
+async fn test_share_link_shows_wishlist_without_account() {
+    let (state, _) = state();
+    let id = create(&state, "alice").await;
+    let created = create_share(&state, &id, Value::Null).await;
+    let token = created["token"].as_str().unwrap();
+    assert_eq!(created["url"], format!("/shared/{}", token));
+    assert_eq!(created["has_password"], false);
+    assert_eq!(created["created_by"], "alice");
+    assert_ne!(created["id"], created["token"]);
+
+    let res = open(&state, created["url"].as_str().unwrap(), None).await;
+    assert_eq!(res.status(), 200);
+    let shared = json_body(&res);
+    assert_eq!(shared["name"], "Birthday");
+    assert_eq!(shared["items"][0]["name"], "Book");
+    for hidden in ["owner", "id", "version"] {
+        assert!(shared.get(hidden).is_none(), "{} is exposed", hidden);
+    }
+
+    let res = send(&state, Method::PUT, "/shared/x", json!({}), "alice").await;
+    assert_eq!(res.status(), 405);
+    assert_eq!(
+        open(&state, "/shared/not-a-token", None).await.status(),
+        404
+    );
+}
+
+#[tokio::test]
+async fn test_password_protected_link() {
+    let (state, _) = state();
+    let id = create(&state, "alice").await;
+    let created = create_share(&state, &id, json!({"password": "hunter2"})).await;
+    assert_eq!(created["has_password"], true);
+    assert!(created.get("password_hash").is_none());
+    let url = created["url"].as_str().unwrap();
+
+    assert_eq!(open(&state, url, None).await.status(), 403);
+    assert_eq!(open(&state, url, Some("hunter3")).await.status(), 403);
+    assert_eq!(open(&state, url, Some("hunter2")).await.status(), 200);
+}
+
+#[tokio::test]
+async fn test_expired_and_revoked_links_stop_working() {
+    let (state, store) = state();
+    let id = create(&state, "alice").await;
+
+    let res = send(
+        &state,
+        Method::POST,
+        &format!("/wishlists/{}/shares", id),
+        json!({"expires_at": now() - 1}),
+        "alice",
+    )
+    .await;
+    assert_eq!(res.status(), 422);
+    assert_eq!(json_body(&res)["errors"][0]["field"], "expires_at");
+
+    let request = ShareRequest {
+        expires_at: Some(now() - 1),
+        password: None,
+    };
+    let (expired, expired_token) = ShareLink::generate(&id, None, &request);
+    store.create_share(expired).await.unwrap();
+    let url = format!("/shared/{}", expired_token);
+    assert_eq!(open(&state, &url, None).await.status(), 404);
+
+    let created = create_share(&state, &id, json!({"expires_at": now() + 3600})).await;
+    let url = created["url"].as_str().unwrap();
+    assert_eq!(open(&state, url, None).await.status(), 200);
+    let link = format!(
+        "/wishlists/{}/shares/{}",
+        id,
+        created["id"].as_str().unwrap()
+    );
+    let res = send(&state, Method::DELETE, &link, Value::Null, "alice").await;
+    assert_eq!(res.status(), 204);
+    assert_eq!(open(&state, url, None).await.status(), 404);
+
+    let res = send(
+        &state,
+        Method::GET,
+        &format!("/wishlists/{}/shares", id),
+        Value::Null,
+        "alice",
+    )
+    .await;
+    let links = json_body(&res);
+    let links = links.as_array().unwrap();
+    assert_eq!(links.len(), 2);
+    let revoked = links
+        .iter()
+        .find(|link| link["id"] == created["id"])
+        .unwrap();
+    assert!(revoked["revoked_at"].is_u64());
+    assert!(links.iter().all(|link| link.get("token").is_none()));
+}
+
+#[tokio::test]
+async fn test_only_admins_manage_share_links() {
+    let (state, _) = state();
+    let id = create(&state, "alice").await;
+    let uri = format!("/wishlists/{}/collaborators/ed", id);
+    let res = send(
+        &state,
+        Method::PUT,
+        &uri,
+        json!({"role": "editor"}),
+        "alice",
+    )
+    .await;
+    assert_eq!(res.status(), 200);
+
+    let shares = format!("/wishlists/{}/shares", id);
+    let res = send(&state, Method::POST, &shares, Value::Null, "ed").await;
+    assert_eq!(res.status(), 403);
+    let res = send(&state, Method::GET, &shares, Value::Null, "mallory").await;
+    assert_eq!(res.status(), 403);
+    let res = send(
+        &state,
+        Method::POST,
+        "/wishlists/missing/shares",
+        Value::Null,
+        "alice",
+    )
+    .await;
+    assert_eq!(res.status(), 404);
+}
+
+#[test]
+fn test_password_hashes_follow_pbkdf2() {
+    // Published PBKDF2-HMAC-SHA256 test vectors (RFC 6070's inputs).
+    assert!(verify_password(
+        "password",
+        "pbkdf2-sha256$1$salt$120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"
+    ));
+    assert!(verify_password(
+        "password",
+        "pbkdf2-sha256$4096$salt$c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a"
+    ));
+    let hash = hash_password("hunter2");
+    assert!(verify_password("hunter2", &hash));
+    assert!(!verify_password("hunter3", &hash));
+    assert_ne!(hash, hash_password("hunter2"));
+    assert!(!verify_password("hunter2", "plain-text"));
+}
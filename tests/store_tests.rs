@@ -0,0 +1,1270 @@
+use async_trait::async_trait;
+use aws_config::{BehaviorVersion, SdkConfig};
+use aws_credential_types::provider::SharedCredentialsProvider;
+use aws_credential_types::Credentials;
+use aws_sdk_dynamodb::config::Region;
+use aws_sdk_dynamodb::error::SdkError;
+use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::Client as DynamoDbClient;
+use lambda_http::{Body, Request};
+use serial_test::serial;
+use std::sync::Arc;
+use wishlist_api::api_key::{ApiKey, ApiKeyScope};
+#[cfg(feature = "postgres")]
+use wishlist_api::db::PostgresStore;
+#[cfg(feature = "sqlite")]
+use wishlist_api::db::SqliteStore;
+use wishlist_api::db::{dynamodb, DynamoDbStore, MemoryStore, Page, WishlistStore};
+use wishlist_api::error::AppError;
+use wishlist_api::exchange::{Exchange, Participant};
+use wishlist_api::handlers::{
+    handle_get, Collaborator, CollaboratorRole, Priority, Wishlist, WishlistItem,
+};
+use wishlist_api::pledge::Pledge;
+use wishlist_api::reservation::Reservation;
+use wishlist_api::share::{ShareLink, ShareRequest};
+use wishlist_api::state::AppState;
+
+/// Backend that fails every call with the error `self.0` makes, for checking
+/// how handlers surface storage errors.
+struct FailingStore(fn() -> AppError);
+
+fn internal_error() -> AppError {
+    AppError::Generic("unavailable".to_string())
+}
+
+#[async_trait]
+impl WishlistStore for FailingStore {
+    async fn get_item(&self, _id: String) -> Result<Option<Wishlist>, AppError> {
+        Err((self.0)())
+    }
+
+    async fn create_item(&self, _wishlist: Wishlist) -> Result<(), AppError> {
+        Err((self.0)())
+    }
+
+    async fn update_item(
+        &self,
+        _wishlist: Wishlist,
+        _expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError> {
+        Err((self.0)())
+    }
+
+    async fn delete_item(
+        &self,
+        _id: String,
+        _expected_version: Option<u64>,
+    ) -> Result<(), AppError> {
+        Err((self.0)())
+    }
+
+    async fn add_wishlist_item(
+        &self,
+        _wishlist_id: String,
+        _item: WishlistItem,
+        _expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError> {
+        Err((self.0)())
+    }
+
+    async fn update_wishlist_item(
+        &self,
+        _wishlist_id: String,
+        _item_id: String,
+        _patch: serde_json::Value,
+        _expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError> {
+        Err((self.0)())
+    }
+
+    async fn delete_wishlist_item(
+        &self,
+        _wishlist_id: String,
+        _item_id: String,
+        _expected_version: Option<u64>,
+    ) -> Result<Wishlist, AppError> {
+        Err((self.0)())
+    }
+
+    async fn scan_page(
+        &self,
+        _limit: usize,
+        _start_key: Option<String>,
+    ) -> Result<Page<Wishlist>, AppError> {
+        Err((self.0)())
+    }
+
+    async fn query_by_owner(
+        &self,
+        _owner: String,
+        _limit: usize,
+        _start_key: Option<String>,
+    ) -> Result<Page<Wishlist>, AppError> {
+        Err((self.0)())
+    }
+
+    async fn query_shared_with(
+        &self,
+        _subject: String,
+        _limit: usize,
+        _start_key: Option<String>,
+    ) -> Result<Page<Wishlist>, AppError> {
+        Err((self.0)())
+    }
+
+    async fn put_collaborator(
+        &self,
+        _wishlist_id: String,
+        _collaborator: Collaborator,
+    ) -> Result<(), AppError> {
+        Err((self.0)())
+    }
+
+    async fn get_collaborator(
+        &self,
+        _wishlist_id: String,
+        _subject: String,
+    ) -> Result<Option<Collaborator>, AppError> {
+        Err((self.0)())
+    }
+
+    async fn list_collaborators(
+        &self,
+        _wishlist_id: String,
+    ) -> Result<Vec<Collaborator>, AppError> {
+        Err((self.0)())
+    }
+
+    async fn delete_collaborator(
+        &self,
+        _wishlist_id: String,
+        _subject: String,
+    ) -> Result<(), AppError> {
+        Err((self.0)())
+    }
+
+    async fn create_share(&self, _link: ShareLink) -> Result<(), AppError> {
+        Err((self.0)())
+    }
+
+    async fn get_share(&self, _id: String) -> Result<Option<ShareLink>, AppError> {
+        Err((self.0)())
+    }
+
+    async fn list_shares(&self, _wishlist_id: String) -> Result<Vec<ShareLink>, AppError> {
+        Err((self.0)())
+    }
+
+    async fn revoke_share(
+        &self,
+        _wishlist_id: String,
+        _id: String,
+        _revoked_at: u64,
+    ) -> Result<ShareLink, AppError> {
+        Err((self.0)())
+    }
+
+    async fn reserve_item(
+        &self,
+        _wishlist_id: String,
+        _reservation: Reservation,
+    ) -> Result<Reservation, AppError> {
+        Err((self.0)())
+    }
+
+    async fn list_reservations(&self, _wishlist_id: String) -> Result<Vec<Reservation>, AppError> {
+        Err((self.0)())
+    }
+
+    async fn delete_reservation(
+        &self,
+        _wishlist_id: String,
+        _item_id: String,
+        _reserver: String,
+        _claim_hash: Option<String>,
+    ) -> Result<(), AppError> {
+        Err((self.0)())
+    }
+
+    async fn add_pledge(&self, _wishlist_id: String, _pledge: Pledge) -> Result<(), AppError> {
+        Err((self.0)())
+    }
+
+    async fn list_pledges(
+        &self,
+        _wishlist_id: String,
+        _item_id: String,
+    ) -> Result<Vec<Pledge>, AppError> {
+        Err((self.0)())
+    }
+
+    async fn create_exchange(&self, _exchange: Exchange) -> Result<(), AppError> {
+        Err((self.0)())
+    }
+
+    async fn get_exchange(&self, _id: String) -> Result<Option<Exchange>, AppError> {
+        Err((self.0)())
+    }
+
+    async fn record_draw(
+        &self,
+        _id: String,
+        _participants: Vec<Participant>,
+        _drawn_at: u64,
+    ) -> Result<(), AppError> {
+        Err((self.0)())
+    }
+
+    async fn delete_exchange(&self, _id: String) -> Result<(), AppError> {
+        Err((self.0)())
+    }
+
+    async fn create_api_key(&self, _key: ApiKey) -> Result<(), AppError> {
+        Err((self.0)())
+    }
+
+    async fn get_api_key(&self, _id: String) -> Result<Option<ApiKey>, AppError> {
+        Err((self.0)())
+    }
+
+    async fn list_api_keys(&self, _owner: String) -> Result<Vec<ApiKey>, AppError> {
+        Err((self.0)())
+    }
+
+    async fn revoke_api_key(
+        &self,
+        _owner: String,
+        _id: String,
+        _revoked_at: u64,
+    ) -> Result<ApiKey, AppError> {
+        Err((self.0)())
+    }
+
+    async fn touch_api_key(&self, _id: String, _used_at: u64) -> Result<(), AppError> {
+        Err((self.0)())
+    }
+}
+
+#[tokio::test]
+async fn test_store_errors_become_internal_server_error() {
+    let state = AppState::new(Arc::new(FailingStore(internal_error)));
+
+    let mut list_req = Request::new(Body::Empty);
+    *list_req.uri_mut() = "/wishlists".parse().unwrap();
+    let list_res = handle_get(list_req, &state).await.unwrap();
+    assert_eq!(list_res.status(), 500);
+
+    let mut get_req = Request::new(Body::Empty);
+    *get_req.uri_mut() = "/wishlists/some-id".parse().unwrap();
+    let get_res = handle_get(get_req, &state).await.unwrap();
+    assert_eq!(get_res.status(), 500);
+}
+
+#[tokio::test]
+async fn test_throttling_is_reported_as_retryable() {
+    let state = AppState::new(Arc::new(FailingStore(|| {
+        AppError::Throttled("ProvisionedThroughputExceededException".to_string())
+    })));
+
+    let mut get_req = Request::new(Body::Empty);
+    *get_req.uri_mut() = "/wishlists/some-id".parse().unwrap();
+    let get_res = handle_get(get_req, &state).await.unwrap();
+    assert_eq!(get_res.status(), 503);
+    assert_eq!(get_res.headers().get("Retry-After").unwrap(), "1");
+    let body: serde_json::Value = serde_json::from_slice(get_res.body()).unwrap();
+    assert_eq!(body["code"], "throttled");
+    assert!(!body["detail"]
+        .as_str()
+        .unwrap()
+        .contains("ProvisionedThroughputExceededException"));
+}
+
+#[tokio::test]
+async fn test_health_check_does_not_touch_store() {
+    let state = AppState::new(Arc::new(FailingStore(internal_error)));
+    let mut event = Request::new(Body::Empty);
+    *event.uri_mut() = "/health".parse().unwrap();
+
+    let response = handle_get(event, &state).await.unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+fn sample(id: &str) -> Wishlist {
+    Wishlist {
+        id: id.to_string(),
+        name: "Birthday".to_string(),
+        owner: "alice".to_string(),
+        items: vec![WishlistItem {
+            url: Some("https://example.com/book".to_string()),
+            price_cents: Some(1999),
+            currency: Some("EUR".to_string()),
+            quantity: 2,
+            priority: Some(Priority::High),
+            notes: Some("Hardback".to_string()),
+            ..WishlistItem::new("item-book", "Book")
+        }],
+        ..Default::default()
+    }
+}
+
+/// Shared contract every backend must satisfy.
+async fn assert_store_semantics(store: &dyn WishlistStore) {
+    assert!(store
+        .get_item("missing".to_string())
+        .await
+        .unwrap()
+        .is_none());
+
+    store.create_item(sample("a")).await.unwrap();
+    assert!(matches!(
+        store.create_item(sample("a")).await,
+        Err(AppError::Conflict(_))
+    ));
+    let fetched = store.get_item("a".to_string()).await.unwrap().unwrap();
+    assert_eq!(fetched.items, sample("a").items);
+    assert_eq!(store.scan_items().await.unwrap().len(), 1);
+
+    let mut renamed = sample("a");
+    renamed.name = "Renamed".to_string();
+    renamed.items = vec![
+        WishlistItem::new("i1", "Pen"),
+        WishlistItem::new("i2", "Ink"),
+    ];
+    let updated = store.update_item(renamed, None).await.unwrap();
+    assert_eq!(updated.version, 1);
+    let fetched = store.get_item("a".to_string()).await.unwrap().unwrap();
+    assert_eq!(fetched.name, "Renamed");
+    assert_eq!(fetched.version, 1);
+    let names: Vec<_> = fetched.items.iter().map(|i| i.name.as_str()).collect();
+    assert_eq!(names, vec!["Pen", "Ink"]);
+    assert_eq!(fetched.items[1].id, "i2");
+    assert_eq!(store.scan_items().await.unwrap().len(), 1);
+
+    store.create_item(sample("b")).await.unwrap();
+    store.create_item(sample("c")).await.unwrap();
+    let first = store.scan_page(2, None).await.unwrap();
+    assert_eq!(first.items.len(), 2);
+    let start_key = first.last_key.expect("a second page should follow");
+    let second = store.scan_page(2, Some(start_key)).await.unwrap();
+    let mut ids: Vec<String> = first
+        .items
+        .into_iter()
+        .chain(second.items)
+        .map(|w| w.id)
+        .collect();
+    ids.sort();
+    assert_eq!(ids, vec!["a", "b", "c"]);
+    assert!(second.last_key.is_none());
+
+    let mut bobs = sample("d");
+    bobs.owner = "bob".to_string();
+    store.create_item(bobs).await.unwrap();
+    let owned = store
+        .query_by_owner("bob".to_string(), 10, None)
+        .await
+        .unwrap();
+    assert_eq!(owned.items.len(), 1);
+    assert_eq!(owned.items[0].id, "d");
+    assert!(owned.last_key.is_none());
+    let first = store
+        .query_by_owner("alice".to_string(), 2, None)
+        .await
+        .unwrap();
+    assert_eq!(first.items.len(), 2);
+    let second = store
+        .query_by_owner("alice".to_string(), 2, first.last_key)
+        .await
+        .unwrap();
+    assert_eq!(second.items.len(), 1);
+    assert!(second.items.iter().all(|w| w.owner == "alice"));
+
+    for id in ["b", "c", "d"] {
+        store.delete_item(id.to_string(), None).await.unwrap();
+    }
+
+    assert!(matches!(
+        store.update_item(sample("a"), Some(0)).await,
+        Err(AppError::PreconditionFailed)
+    ));
+    let updated = store.update_item(sample("a"), Some(1)).await.unwrap();
+    assert_eq!(updated.version, 2);
+    assert!(matches!(
+        store.update_item(sample("never-created"), None).await,
+        Err(AppError::NotFound)
+    ));
+    assert!(matches!(
+        store.delete_item("a".to_string(), Some(1)).await,
+        Err(AppError::PreconditionFailed)
+    ));
+    store.delete_item("a".to_string(), Some(2)).await.unwrap();
+    assert!(store.get_item("a".to_string()).await.unwrap().is_none());
+    assert!(matches!(
+        store.delete_item("a".to_string(), None).await,
+        Err(AppError::NotFound)
+    ));
+
+    store.create_item(sample("e")).await.unwrap();
+    let added = store
+        .add_wishlist_item(
+            "e".to_string(),
+            WishlistItem::new("item-pen", "Pen"),
+            Some(0),
+        )
+        .await
+        .unwrap();
+    assert_eq!(added.version, 1);
+    assert_eq!(added.items[1].id, "item-pen");
+    let patched = store
+        .update_wishlist_item(
+            "e".to_string(),
+            "item-book".to_string(),
+            serde_json::json!({"quantity": 5, "notes": null}),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(patched.version, 2);
+    assert_eq!(patched.items[0].quantity, 5);
+    assert_eq!(patched.items[0].notes, None);
+    assert_eq!(patched.items[0].price_cents, Some(1999));
+    assert_eq!(patched.items[1], WishlistItem::new("item-pen", "Pen"));
+    assert!(matches!(
+        store
+            .update_wishlist_item(
+                "e".to_string(),
+                "missing".to_string(),
+                serde_json::json!({"quantity": 1}),
+                None
+            )
+            .await,
+        Err(AppError::NotFound)
+    ));
+    assert!(matches!(
+        store
+            .delete_wishlist_item("e".to_string(), "item-pen".to_string(), Some(1))
+            .await,
+        Err(AppError::PreconditionFailed)
+    ));
+    assert!(matches!(
+        store
+            .add_wishlist_item("a".to_string(), WishlistItem::new("x", "X"), None)
+            .await,
+        Err(AppError::NotFound)
+    ));
+    let remaining = store
+        .delete_wishlist_item("e".to_string(), "item-book".to_string(), Some(2))
+        .await
+        .unwrap();
+    assert_eq!(remaining.version, 3);
+    assert_eq!(remaining.items, vec![WishlistItem::new("item-pen", "Pen")]);
+    let fetched = store.get_item("e".to_string()).await.unwrap().unwrap();
+    assert_eq!(fetched.version, 3);
+    assert_eq!(fetched.items, remaining.items);
+    assert!(matches!(
+        store
+            .delete_wishlist_item("e".to_string(), "item-book".to_string(), None)
+            .await,
+        Err(AppError::NotFound)
+    ));
+
+    assert_collaborator_semantics(store).await;
+    assert_share_semantics(store).await;
+    assert_reservation_semantics(store).await;
+    assert_pledge_semantics(store).await;
+    assert_exchange_semantics(store).await;
+    assert_api_key_semantics(store).await;
+}
+
+async fn assert_reservation_semantics(store: &dyn WishlistStore) {
+    let reservation = |item_id: &str, reserver: &str, quantity, reserved_at| Reservation {
+        item_id: item_id.to_string(),
+        reserver: reserver.to_string(),
+        quantity,
+        purchased: false,
+        reserved_at,
+        claim_hash: None,
+    };
+    let mut gifts = sample("gifts");
+    gifts.items = vec![WishlistItem::new("item-socks", "Socks")];
+    gifts.items[0].quantity = 3;
+    store.create_item(gifts).await.unwrap();
+
+    for (wishlist_id, item_id) in [("missing", "item-socks"), ("gifts", "item-missing")] {
+        assert!(matches!(
+            store
+                .reserve_item(wishlist_id.to_string(), reservation(item_id, "bob", 1, 100))
+                .await,
+            Err(AppError::NotFound)
+        ));
+    }
+    let bob = store
+        .reserve_item(
+            "gifts".to_string(),
+            reservation("item-socks", "bob", 2, 100),
+        )
+        .await
+        .unwrap();
+    assert_eq!(bob, reservation("item-socks", "bob", 2, 100));
+    assert!(matches!(
+        store
+            .reserve_item(
+                "gifts".to_string(),
+                reservation("item-socks", "carol", 2, 200)
+            )
+            .await,
+        Err(AppError::Conflict(_))
+    ));
+    store
+        .reserve_item(
+            "gifts".to_string(),
+            reservation("item-socks", "carol", 1, 200),
+        )
+        .await
+        .unwrap();
+    // Bob may change his own reservation within what Carol left.
+    let mut bought = reservation("item-socks", "bob", 2, 300);
+    bought.purchased = true;
+    let updated = store
+        .reserve_item("gifts".to_string(), bought)
+        .await
+        .unwrap();
+    assert!(updated.purchased);
+    assert_eq!(updated.reserved_at, 100);
+    assert!(matches!(
+        store
+            .reserve_item(
+                "gifts".to_string(),
+                reservation("item-socks", "bob", 3, 300)
+            )
+            .await,
+        Err(AppError::Conflict(_))
+    ));
+    assert_eq!(
+        store.list_reservations("gifts".to_string()).await.unwrap(),
+        vec![updated, reservation("item-socks", "carol", 1, 200)]
+    );
+
+    assert!(matches!(
+        store
+            .delete_reservation(
+                "gifts".to_string(),
+                "item-socks".to_string(),
+                "dave".to_string(),
+                None
+            )
+            .await,
+        Err(AppError::NotFound)
+    ));
+    store
+        .delete_reservation(
+            "gifts".to_string(),
+            "item-socks".to_string(),
+            "carol".to_string(),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        store
+            .list_reservations("gifts".to_string())
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    // A guest's reservation only changes or goes with its claim hash.
+    let claimed = |claim_hash: Option<&str>| Reservation {
+        claim_hash: claim_hash.map(str::to_string),
+        ..reservation("item-socks", "guest:May", 1, 400)
+    };
+    let stored = store
+        .reserve_item("gifts".to_string(), claimed(Some("hash-1")))
+        .await
+        .unwrap();
+    assert_eq!(stored.claim_hash.as_deref(), Some("hash-1"));
+    // The claim is checked before what's left, so a stranger asking for more
+    // than remains is still told the reservation isn't theirs.
+    assert!(matches!(
+        store
+            .reserve_item(
+                "gifts".to_string(),
+                Reservation {
+                    quantity: 3,
+                    ..claimed(Some("hash-2"))
+                }
+            )
+            .await,
+        Err(AppError::Forbidden(_))
+    ));
+    for claim_hash in [Some("hash-2"), None] {
+        assert!(matches!(
+            store
+                .reserve_item("gifts".to_string(), claimed(claim_hash))
+                .await,
+            Err(AppError::Forbidden(_))
+        ));
+        assert!(matches!(
+            store
+                .delete_reservation(
+                    "gifts".to_string(),
+                    "item-socks".to_string(),
+                    "guest:May".to_string(),
+                    claim_hash.map(str::to_string)
+                )
+                .await,
+            Err(AppError::Forbidden(_))
+        ));
+    }
+    store
+        .delete_reservation(
+            "gifts".to_string(),
+            "item-socks".to_string(),
+            "guest:May".to_string(),
+            Some("hash-1".to_string()),
+        )
+        .await
+        .unwrap();
+
+    store.delete_item("gifts".to_string(), None).await.unwrap();
+    assert!(store
+        .list_reservations("gifts".to_string())
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+async fn assert_pledge_semantics(store: &dyn WishlistStore) {
+    let pledge = |item_id: &str, contributor: &str, amount_cents, pledged_at| Pledge {
+        pledged_at,
+        ..Pledge::new(item_id, contributor, amount_cents, "EUR", None)
+    };
+    // The book needs 2 × 1999 cents; the socks have no price.
+    let mut fund = sample("fund");
+    fund.items.push(WishlistItem::new("item-socks", "Socks"));
+    store.create_item(fund).await.unwrap();
+
+    for (wishlist_id, item_id) in [("missing", "item-book"), ("fund", "item-missing")] {
+        assert!(matches!(
+            store
+                .add_pledge(wishlist_id.to_string(), pledge(item_id, "bob", 100, 100))
+                .await,
+            Err(AppError::NotFound)
+        ));
+    }
+    assert!(matches!(
+        store
+            .add_pledge("fund".to_string(), pledge("item-socks", "bob", 100, 100))
+            .await,
+        Err(AppError::Conflict(_))
+    ));
+
+    let mut bob = pledge("item-book", "bob", 2000, 100);
+    bob.message = Some("Happy birthday!".to_string());
+    store
+        .add_pledge("fund".to_string(), bob.clone())
+        .await
+        .unwrap();
+    assert!(matches!(
+        store
+            .add_pledge("fund".to_string(), pledge("item-book", "carol", 1999, 200))
+            .await,
+        Err(AppError::Conflict(message)) if message.contains("1998")
+    ));
+    let carol = pledge("item-book", "carol", 1998, 200);
+    store
+        .add_pledge("fund".to_string(), carol.clone())
+        .await
+        .unwrap();
+    assert!(matches!(
+        store
+            .add_pledge("fund".to_string(), pledge("item-book", "dave", 1, 300))
+            .await,
+        Err(AppError::Conflict(message)) if message.contains("fully funded")
+    ));
+    assert_eq!(
+        store
+            .list_pledges("fund".to_string(), "item-book".to_string())
+            .await
+            .unwrap(),
+        vec![bob, carol]
+    );
+    assert!(store
+        .list_pledges("fund".to_string(), "item-socks".to_string())
+        .await
+        .unwrap()
+        .is_empty());
+
+    store.delete_item("fund".to_string(), None).await.unwrap();
+    assert!(store
+        .list_pledges("fund".to_string(), "item-book".to_string())
+        .await
+        .unwrap()
+        .is_empty());
+
+    // A list created under the same ID starts with nothing funded.
+    store.create_item(sample("fund")).await.unwrap();
+    store
+        .add_pledge("fund".to_string(), pledge("item-book", "dave", 3998, 400))
+        .await
+        .unwrap();
+    store.delete_item("fund".to_string(), None).await.unwrap();
+}
+
+async fn assert_exchange_semantics(store: &dyn WishlistStore) {
+    let participant = |subject: &str, spouse: Option<&str>| Participant {
+        subject: subject.to_string(),
+        wishlist_id: format!("{}-list", subject),
+        spouse: spouse.map(str::to_string),
+        receiver: None,
+    };
+    let exchange = Exchange {
+        id: "office".to_string(),
+        name: "Office party".to_string(),
+        organizer: "alice".to_string(),
+        participants: vec![
+            participant("bob", Some("carol")),
+            participant("carol", None),
+            participant("dave", None),
+        ],
+        previous_exchange_id: Some("office-last-year".to_string()),
+        created_at: 100,
+        drawn_at: None,
+    };
+    store.create_exchange(exchange.clone()).await.unwrap();
+    assert!(matches!(
+        store.create_exchange(exchange.clone()).await,
+        Err(AppError::Conflict(_))
+    ));
+    assert_eq!(
+        store.get_exchange("office".to_string()).await.unwrap(),
+        Some(exchange.clone())
+    );
+    assert_eq!(
+        store.get_exchange("missing".to_string()).await.unwrap(),
+        None
+    );
+
+    let mut drawn = exchange.participants.clone();
+    for (giver, receiver) in drawn.iter_mut().zip(["dave", "bob", "carol"]) {
+        giver.receiver = Some(receiver.to_string());
+    }
+    assert!(matches!(
+        store
+            .record_draw("missing".to_string(), drawn.clone(), 200)
+            .await,
+        Err(AppError::NotFound)
+    ));
+    store
+        .record_draw("office".to_string(), drawn.clone(), 200)
+        .await
+        .unwrap();
+    let stored = store
+        .get_exchange("office".to_string())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.participants, drawn);
+    assert_eq!(stored.drawn_at, Some(200));
+    assert!(matches!(
+        store.record_draw("office".to_string(), drawn, 300).await,
+        Err(AppError::Conflict(_))
+    ));
+
+    store.delete_exchange("office".to_string()).await.unwrap();
+    assert_eq!(
+        store.get_exchange("office".to_string()).await.unwrap(),
+        None
+    );
+    assert!(matches!(
+        store.delete_exchange("office".to_string()).await,
+        Err(AppError::NotFound)
+    ));
+}
+
+async fn assert_share_semantics(store: &dyn WishlistStore) {
+    for id in ["linked-1", "linked-2"] {
+        store.create_item(sample(id)).await.unwrap();
+    }
+    let protected = ShareRequest {
+        expires_at: Some(5_000_000_000),
+        password: Some("hunter2".to_string()),
+    };
+    let (mut first, _) = ShareLink::generate("linked-1", Some("alice".to_string()), &protected);
+    first.created_at = 100;
+    let (second, _) = ShareLink::generate("linked-1", None, &ShareRequest::default());
+    let (other, _) = ShareLink::generate("linked-2", None, &ShareRequest::default());
+    for link in [&second, &first, &other] {
+        store.create_share(link.clone()).await.unwrap();
+    }
+    let (orphan, _) = ShareLink::generate("missing", None, &ShareRequest::default());
+    assert!(matches!(
+        store.create_share(orphan).await,
+        Err(AppError::NotFound)
+    ));
+    assert_eq!(
+        store.get_share(first.id.clone()).await.unwrap(),
+        Some(first.clone())
+    );
+    assert_eq!(store.get_share("missing".to_string()).await.unwrap(), None);
+    assert_eq!(
+        store.list_shares("linked-1".to_string()).await.unwrap(),
+        vec![first.clone(), second.clone()]
+    );
+
+    assert!(matches!(
+        store
+            .revoke_share("linked-2".to_string(), first.id.clone(), 300)
+            .await,
+        Err(AppError::NotFound)
+    ));
+    let revoked = store
+        .revoke_share("linked-1".to_string(), first.id.clone(), 300)
+        .await
+        .unwrap();
+    assert_eq!(revoked.revoked_at, Some(300));
+    assert_eq!(revoked.password_hash, first.password_hash);
+    let again = store
+        .revoke_share("linked-1".to_string(), first.id.clone(), 400)
+        .await
+        .unwrap();
+    assert_eq!(again.revoked_at, Some(300));
+
+    store
+        .delete_item("linked-2".to_string(), None)
+        .await
+        .unwrap();
+    assert_eq!(store.get_share(other.id.clone()).await.unwrap(), None);
+    assert!(store
+        .list_shares("linked-2".to_string())
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+async fn assert_collaborator_semantics(store: &dyn WishlistStore) {
+    let collaborator = |subject: &str, role| Collaborator {
+        subject: subject.to_string(),
+        role,
+    };
+    for id in ["shared-1", "shared-2", "shared-3"] {
+        store.create_item(sample(id)).await.unwrap();
+    }
+    assert!(matches!(
+        store
+            .put_collaborator(
+                "missing".to_string(),
+                collaborator("carol", CollaboratorRole::Viewer)
+            )
+            .await,
+        Err(AppError::NotFound)
+    ));
+    for id in ["shared-3", "shared-1", "shared-2"] {
+        store
+            .put_collaborator(
+                id.to_string(),
+                collaborator("carol", CollaboratorRole::Viewer),
+            )
+            .await
+            .unwrap();
+    }
+    store
+        .put_collaborator(
+            "shared-1".to_string(),
+            collaborator("carol", CollaboratorRole::CoOwner),
+        )
+        .await
+        .unwrap();
+    store
+        .put_collaborator(
+            "shared-1".to_string(),
+            collaborator("bob", CollaboratorRole::Editor),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store
+            .list_collaborators("shared-1".to_string())
+            .await
+            .unwrap(),
+        vec![
+            collaborator("bob", CollaboratorRole::Editor),
+            collaborator("carol", CollaboratorRole::CoOwner),
+        ]
+    );
+    assert_eq!(
+        store
+            .get_collaborator("shared-2".to_string(), "carol".to_string())
+            .await
+            .unwrap(),
+        Some(collaborator("carol", CollaboratorRole::Viewer))
+    );
+    assert_eq!(
+        store
+            .get_collaborator("shared-2".to_string(), "bob".to_string())
+            .await
+            .unwrap(),
+        None
+    );
+
+    let first = store
+        .query_shared_with("carol".to_string(), 2, None)
+        .await
+        .unwrap();
+    let ids: Vec<&str> = first.items.iter().map(|w| w.id.as_str()).collect();
+    assert_eq!(ids, vec!["shared-1", "shared-2"]);
+    let second = store
+        .query_shared_with("carol".to_string(), 2, first.last_key)
+        .await
+        .unwrap();
+    assert_eq!(second.items.len(), 1);
+    assert_eq!(second.items[0].id, "shared-3");
+    assert!(second.last_key.is_none());
+
+    store
+        .delete_collaborator("shared-2".to_string(), "carol".to_string())
+        .await
+        .unwrap();
+    assert!(matches!(
+        store
+            .delete_collaborator("shared-2".to_string(), "carol".to_string())
+            .await,
+        Err(AppError::NotFound)
+    ));
+    store
+        .delete_item("shared-1".to_string(), None)
+        .await
+        .unwrap();
+    let shared = store
+        .query_shared_with("carol".to_string(), 10, None)
+        .await
+        .unwrap();
+    let ids: Vec<&str> = shared.items.iter().map(|w| w.id.as_str()).collect();
+    assert_eq!(ids, vec!["shared-3"]);
+    let bobs = store
+        .query_shared_with("bob".to_string(), 10, None)
+        .await
+        .unwrap();
+    assert!(bobs.items.is_empty());
+
+    // More lists than DynamoDB reads in one `BatchGetItem`.
+    let ids: Vec<String> = (0..150).map(|i| format!("many-{:03}", i)).collect();
+    for id in &ids {
+        store.create_item(sample(id)).await.unwrap();
+        store
+            .put_collaborator(id.clone(), collaborator("dave", CollaboratorRole::Viewer))
+            .await
+            .unwrap();
+    }
+    let shared = store
+        .query_shared_with("dave".to_string(), 200, None)
+        .await
+        .unwrap();
+    let found: Vec<String> = shared.items.into_iter().map(|w| w.id).collect();
+    assert_eq!(found, ids);
+    for id in ids {
+        store.delete_item(id, None).await.unwrap();
+    }
+}
+
+async fn assert_api_key_semantics(store: &dyn WishlistStore) {
+    let (mut first, _) = ApiKey::generate("alice", "backup script", ApiKeyScope::ReadOnly);
+    first.created_at = 100;
+    let (second, _) = ApiKey::generate("alice", "importer", ApiKeyScope::ReadWrite);
+    let (other, _) = ApiKey::generate("bob", "bob's", ApiKeyScope::ReadOnly);
+    for key in [&second, &first, &other] {
+        store.create_api_key(key.clone()).await.unwrap();
+    }
+    assert!(matches!(
+        store.create_api_key(first.clone()).await,
+        Err(AppError::Conflict(_))
+    ));
+    assert_eq!(
+        store.get_api_key(first.id.clone()).await.unwrap(),
+        Some(first.clone())
+    );
+    assert_eq!(
+        store.get_api_key("missing".to_string()).await.unwrap(),
+        None
+    );
+    assert_eq!(
+        store.list_api_keys("alice".to_string()).await.unwrap(),
+        vec![first.clone(), second.clone()]
+    );
+
+    store.touch_api_key(second.id.clone(), 200).await.unwrap();
+    store
+        .touch_api_key("missing".to_string(), 200)
+        .await
+        .unwrap();
+    let touched = store.get_api_key(second.id.clone()).await.unwrap().unwrap();
+    assert_eq!(touched.last_used_at, Some(200));
+
+    assert!(matches!(
+        store
+            .revoke_api_key("bob".to_string(), first.id.clone(), 300)
+            .await,
+        Err(AppError::NotFound)
+    ));
+    assert!(matches!(
+        store
+            .revoke_api_key("alice".to_string(), "missing".to_string(), 300)
+            .await,
+        Err(AppError::NotFound)
+    ));
+    let revoked = store
+        .revoke_api_key("alice".to_string(), first.id.clone(), 300)
+        .await
+        .unwrap();
+    assert_eq!(revoked.revoked_at, Some(300));
+    let again = store
+        .revoke_api_key("alice".to_string(), first.id.clone(), 400)
+        .await
+        .unwrap();
+    assert_eq!(again.revoked_at, Some(300));
+    assert_eq!(
+        store
+            .list_api_keys("alice".to_string())
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn test_memory_store_round_trip() {
+    assert_store_semantics(&MemoryStore::new()).await;
+}
+
+#[tokio::test]
+async fn test_memory_store_is_shared_across_tasks() {
+    let store = MemoryStore::new();
+    let mut tasks = Vec::new();
+    for i in 0..20 {
+        let store = store.clone();
+        tasks.push(tokio::spawn(async move {
+            store
+                .create_item(sample(&format!("list-{}", i)))
+                .await
+                .unwrap();
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+    assert_eq!(store.scan_items().await.unwrap().len(), 20);
+}
+
+#[cfg(feature = "sqlite")]
+fn temp_sqlite_path() -> String {
+    std::env::temp_dir()
+        .join(format!("wishlist-test-{}.sqlite", uuid::Uuid::new_v4()))
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_sqlite_store_round_trip() {
+    let store = SqliteStore::connect(&temp_sqlite_path()).await.unwrap();
+    assert_store_semantics(&store).await;
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_sqlite_store_persists_across_connections() {
+    let path = temp_sqlite_path();
+    let store = SqliteStore::connect(&path).await.unwrap();
+    store.create_item(sample("kept")).await.unwrap();
+    drop(store);
+
+    // Reconnecting re-runs migrations, which must be a no-op on an up-to-date file.
+    let reopened = SqliteStore::connect(&path).await.unwrap();
+    let fetched = reopened
+        .get_item("kept".to_string())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(fetched.owner, "alice");
+    assert_eq!(fetched.items, sample("kept").items);
+}
+
+/// A client for the DynamoDB Local at `DYNAMODB_ENDPOINT`, with every table
+/// dropped and created afresh, or `None` when the variable isn't set.
+async fn fresh_dynamodb() -> Option<DynamoDbClient> {
+    let Ok(endpoint) = std::env::var("DYNAMODB_ENDPOINT") else {
+        println!("DYNAMODB_ENDPOINT not set, skipping.");
+        return None;
+    };
+    let config = SdkConfig::builder()
+        .endpoint_url(endpoint)
+        .region(Region::new("eu-west-1"))
+        .behavior_version(BehaviorVersion::latest())
+        .credentials_provider(SharedCredentialsProvider::new(Credentials::for_tests()))
+        .build();
+    let client = DynamoDbClient::new(&config);
+    for table in [
+        dynamodb::TABLE_NAME,
+        dynamodb::COLLABORATOR_TABLE_NAME,
+        dynamodb::SHARE_TABLE_NAME,
+        dynamodb::RESERVATION_TABLE_NAME,
+        dynamodb::PLEDGE_TABLE_NAME,
+        dynamodb::EXCHANGE_TABLE_NAME,
+        dynamodb::API_KEY_TABLE_NAME,
+    ] {
+        match client.delete_table().table_name(table).send().await {
+            Ok(_) => {}
+            Err(SdkError::ServiceError(e)) if e.err().is_resource_not_found_exception() => {}
+            Err(e) => panic!("could not drop {}: {:?}", table, e),
+        }
+    }
+    dynamodb::create_table(&client).await.unwrap();
+    Some(client)
+}
+
+/// Runs only when `DYNAMODB_ENDPOINT` points at DynamoDB Local, which it
+/// wipes.
+#[tokio::test]
+#[serial]
+async fn test_dynamodb_store_round_trip() {
+    let Some(client) = fresh_dynamodb().await else {
+        return;
+    };
+    assert_store_semantics(&DynamoDbStore::new(client)).await;
+}
+
+/// A `BatchGetItem` that leaves some keys unprocessed is asked again for just
+/// those, here against a stub that throttles the second of two lists once.
+#[tokio::test]
+async fn test_dynamodb_rereads_unprocessed_keys() {
+    use httpmock::prelude::*;
+
+    fn only_second(req: &HttpMockRequest) -> bool {
+        let body = String::from_utf8_lossy(req.body.as_deref().unwrap_or_default());
+        body.contains("list-2") && !body.contains("list-1")
+    }
+    let wishlist = |id: &str| serde_json::json!({"id": {"S": id}, "name": {"S": "Birthday"}, "owner": {"S": "alice"}});
+    let key = |id: &str| serde_json::json!({"id": {"S": id}});
+
+    let server = MockServer::start_async().await;
+    server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .header("x-amz-target", "DynamoDB_20120810.Query");
+            then.status(200)
+                .header("Content-Type", "application/x-amz-json-1.0")
+                .json_body(serde_json::json!({
+                    "Items": [
+                        {"wishlist_id": {"S": "list-1"}, "subject": {"S": "carol"}},
+                        {"wishlist_id": {"S": "list-2"}, "subject": {"S": "carol"}},
+                    ]
+                }));
+        })
+        .await;
+    let first = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .header("x-amz-target", "DynamoDB_20120810.BatchGetItem")
+                .body_contains("list-1");
+            then.status(200)
+                .header("Content-Type", "application/x-amz-json-1.0")
+                .json_body(serde_json::json!({
+                    "Responses": {dynamodb::TABLE_NAME: [wishlist("list-1")]},
+                    "UnprocessedKeys": {dynamodb::TABLE_NAME: {"Keys": [key("list-2")]}},
+                }));
+        })
+        .await;
+    let retry = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .header("x-amz-target", "DynamoDB_20120810.BatchGetItem")
+                .matches(only_second);
+            then.status(200)
+                .header("Content-Type", "application/x-amz-json-1.0")
+                .json_body(serde_json::json!({
+                    "Responses": {dynamodb::TABLE_NAME: [wishlist("list-2")]},
+                    "UnprocessedKeys": {},
+                }));
+        })
+        .await;
+
+    let config = SdkConfig::builder()
+        .endpoint_url(server.base_url())
+        .region(Region::new("eu-west-1"))
+        .behavior_version(BehaviorVersion::latest())
+        .credentials_provider(SharedCredentialsProvider::new(Credentials::for_tests()))
+        .build();
+    let store = DynamoDbStore::new(DynamoDbClient::new(&config));
+    let shared = store
+        .query_shared_with("carol".to_string(), 10, None)
+        .await
+        .unwrap();
+    let ids: Vec<&str> = shared.items.iter().map(|w| w.id.as_str()).collect();
+    assert_eq!(ids, vec!["list-1", "list-2"]);
+    first.assert_async().await;
+    retry.assert_async().await;
+}
+
+/// Deleting the wishlist row alone leaves its pledges and funding total
+/// behind, as if cleanup had failed; a list created under the same ID must
+/// not inherit them.
+#[tokio::test]
+#[serial]
+async fn test_dynamodb_create_sweeps_leftover_pledges() {
+    let Some(client) = fresh_dynamodb().await else {
+        return;
+    };
+    let store = DynamoDbStore::new(client.clone());
+    store.create_item(sample("fund")).await.unwrap();
+    let pledge = Pledge::new("item-book", "bob", 3998, "EUR", None);
+    store.add_pledge("fund".to_string(), pledge).await.unwrap();
+    client
+        .delete_item()
+        .table_name(dynamodb::TABLE_NAME)
+        .key("id", AttributeValue::S("fund".to_string()))
+        .send()
+        .await
+        .unwrap();
+
+    store.create_item(sample("fund")).await.unwrap();
+    assert!(store
+        .list_pledges("fund".to_string(), "item-book".to_string())
+        .await
+        .unwrap()
+        .is_empty());
+    let pledge = Pledge::new("item-book", "carol", 3998, "EUR", None);
+    store.add_pledge("fund".to_string(), pledge).await.unwrap();
+}
+
+/// Runs only when `DATABASE_URL` points at a PostgreSQL server; each run gets
+/// a fresh schema.
+#[cfg(feature = "postgres")]
+#[tokio::test]
+async fn test_postgres_store_round_trip() {
+    use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+    use std::str::FromStr;
+
+    let Ok(url) = std::env::var("DATABASE_URL") else {
+        println!("DATABASE_URL not set, skipping.");
+        return;
+    };
+    let schema = format!("store_{}", uuid::Uuid::new_v4().simple());
+    let admin = PgPoolOptions::new()
+        .max_connections(1)
+        .connect(&url)
+        .await
+        .unwrap();
+    sqlx::query(&format!("CREATE SCHEMA {}", schema))
+        .execute(&admin)
+        .await
+        .unwrap();
+    let options = PgConnectOptions::from_str(&url)
+        .unwrap()
+        .options([("search_path", schema.as_str())]);
+    let pool = PgPoolOptions::new().connect_with(options).await.unwrap();
+    let store = PostgresStore::from_pool(pool).await.unwrap();
+    assert_store_semantics(&store).await;
+}
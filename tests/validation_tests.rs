@@ -0,0 +1,220 @@
+use lambda_http::http::Method;
+use lambda_http::{Body, Request, Response};
+use serde_json::{json, Value};
+use serial_test::serial;
+use std::sync::Arc;
+use wishlist_api::config::Config;
+use wishlist_api::db::MemoryStore;
+use wishlist_api::handlers::handle_request;
+use wishlist_api::state::AppState;
+use wishlist_api::validation::{FieldError, Limits, DYNAMODB_ITEM_LIMIT};
+
+fn request(method: Method, uri: &str, body: impl Into<Body>) -> Request {
+    let mut req = Request::new(body.into());
+    *req.method_mut() = method;
+    *req.uri_mut() = uri.parse().unwrap();
+    req
+}
+
+fn state(limits: Limits) -> AppState {
+    let config = Config {
+        limits,
+        ..Config::default()
+    };
+    AppState::with_config(Arc::new(MemoryStore::new()), config)
+}
+
+fn field_errors(res: &Response<Body>) -> Vec<FieldError> {
+    assert_eq!(res.status(), 422);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    serde_json::from_value(body["errors"].clone()).unwrap()
+}
+
+fn rules(errors: &[FieldError]) -> Vec<(&str, &str)> {
+    errors
+        .iter()
+        .map(|e| (e.field.as_str(), e.rule.as_str()))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_post_reports_every_invalid_field() {
+    let state = state(Limits {
+        max_owner_length: 10,
+        ..Limits::default()
+    });
+    let body = json!({
+        "name": "  ",
+        "owner": "a".repeat(11),
+        "items": [
+            {"name": "Book", "quantity": 0, "currency": "usd"},
+            {"name": "Lamp", "url": "ftp://example.com/lamp"}
+        ]
+    });
+    let res = handle_request(
+        request(Method::POST, "/wishlists", body.to_string()),
+        &state,
+    )
+    .await
+    .unwrap();
+    let errors = field_errors(&res);
+    assert_eq!(
+        rules(&errors),
+        vec![
+            ("name", "required"),
+            ("owner", "max_length"),
+            ("items[0].currency", "format"),
+            ("items[0].quantity", "min"),
+            ("items[1].url", "format"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_size_and_count_limits() {
+    let state = state(Limits {
+        max_items: 2,
+        max_item_bytes: 200,
+        ..Limits::default()
+    });
+    let body = json!({
+        "name": "Big",
+        "owner": "alice",
+        "items": ["One", "Two", {"name": "Three", "notes": "x".repeat(300)}]
+    });
+    let res = handle_request(
+        request(Method::POST, "/wishlists", body.to_string()),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        rules(&field_errors(&res)),
+        vec![("items", "max_items"), ("items[2]", "max_size")]
+    );
+
+    let body = json!({"name": "Small", "owner": "alice", "items": ["One", "Two"]});
+    let res = handle_request(
+        request(Method::POST, "/wishlists", body.to_string()),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 201);
+    let location = res.headers().get("Location").unwrap().to_str().unwrap();
+
+    let res = handle_request(
+        request(
+            Method::POST,
+            &format!("{}/items", location),
+            json!({"name": "Three"}).to_string(),
+        ),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(rules(&field_errors(&res)), vec![("items", "max_items")]);
+}
+
+#[tokio::test]
+async fn test_body_shape_errors() {
+    let state = state(Limits::default());
+
+    let res = handle_request(request(Method::POST, "/wishlists", "{\"name\": "), &state)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 400);
+
+    let body = json!({"name": "Birthday", "items": []});
+    let res = handle_request(
+        request(Method::POST, "/wishlists", body.to_string()),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(rules(&field_errors(&res)), vec![("owner", "required")]);
+
+    let body = json!({"name": 5, "owner": "alice", "items": []});
+    let res = handle_request(
+        request(Method::POST, "/wishlists", body.to_string()),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(rules(&field_errors(&res)), vec![("name", "type")]);
+
+    let body = json!({"name": "Birthday", "owner": "alice", "items": ["Ok", 7]});
+    let res = handle_request(
+        request(Method::POST, "/wishlists", body.to_string()),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(rules(&field_errors(&res)), vec![("items[1]", "type")]);
+}
+
+#[tokio::test]
+async fn test_item_patch_is_validated() {
+    let state = state(Limits::default());
+    let body = json!({"name": "Birthday", "owner": "alice", "items": ["Socks"]});
+    let res = handle_request(
+        request(Method::POST, "/wishlists", body.to_string()),
+        &state,
+    )
+    .await
+    .unwrap();
+    let created: Value = serde_json::from_slice(res.body()).unwrap();
+    let uri = format!(
+        "/wishlists/{}/items/{}",
+        created["id"].as_str().unwrap(),
+        created["items"][0]["id"].as_str().unwrap()
+    );
+
+    let res = handle_request(
+        request(Method::PATCH, &uri, json!({"name": ""}).to_string()),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(rules(&field_errors(&res)), vec![("name", "required")]);
+
+    let res = handle_request(
+        request(Method::PATCH, &uri, json!({"quantity": "two"}).to_string()),
+        &state,
+    )
+    .await
+    .unwrap();
+    assert_eq!(rules(&field_errors(&res)), vec![("quantity", "type")]);
+}
+
+#[test]
+#[serial]
+fn test_limits_from_env_stay_under_dynamodb_limit() {
+    std::env::set_var("MAX_ITEMS", "20");
+    std::env::set_var("MAX_NAME_LENGTH", "not-a-number");
+    std::env::set_var("MAX_WISHLIST_BYTES", "1000000");
+    let limits = Limits::from_env();
+    std::env::remove_var("MAX_ITEMS");
+    std::env::remove_var("MAX_NAME_LENGTH");
+    std::env::remove_var("MAX_WISHLIST_BYTES");
+
+    assert_eq!(limits.max_items, 20);
+    assert_eq!(limits.max_name_length, Limits::default().max_name_length);
+    assert_eq!(limits.max_wishlist_bytes, DYNAMODB_ITEM_LIMIT);
+}
+
+#[test]
+#[serial]
+fn test_config_requires_cursor_secret_on_lambda() {
+    std::env::remove_var("CURSOR_SECRET");
+    std::env::set_var("AWS_LAMBDA_FUNCTION_NAME", "wishlist");
+    let missing = Config::from_env();
+    std::env::set_var("CURSOR_SECRET", "shared");
+    let shared = Config::from_env();
+    std::env::remove_var("AWS_LAMBDA_FUNCTION_NAME");
+    std::env::remove_var("CURSOR_SECRET");
+
+    assert!(missing.is_err());
+    assert_eq!(shared.unwrap().cursor_secret, b"shared");
+    assert!(Config::from_env().is_ok());
+}
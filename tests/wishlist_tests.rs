@@ -1,4 +1,6 @@
-use wishlist_api::handlers::Wishlist;
+use aws_sdk_dynamodb::types::AttributeValue;
+use std::collections::HashMap;
+use wishlist_api::handlers::{Priority, Wishlist, WishlistItem};
 
 #[cfg(test)]
 mod tests {
@@ -11,6 +13,7 @@ mod tests {
             name: "Test Wishlist".to_string(),
             owner: "Test Owner".to_string(),
             items: Vec::new(),
+            ..Default::default()
         };
         assert_eq!(w.owner, "Test Owner");
         assert!(w.items.is_empty());
@@ -23,8 +26,67 @@ mod tests {
             name: "Test Wishlist".to_string(),
             owner: "Test Owner".to_string(),
             items: Vec::new(),
+            ..Default::default()
         };
-        w.items.push("Item 1".to_string());
+        w.items.push(WishlistItem::new("item-1", "Item 1"));
         assert_eq!(w.items.len(), 1);
     }
 }
+
+#[test]
+fn test_plain_string_items_are_accepted() {
+    let mut w: Wishlist = serde_json::from_str(
+        r#"{"name": "Birthday", "owner": "alice",
+            "items": ["Socks", {"name": "Book", "price_cents": 1250, "priority": "high"}]}"#,
+    )
+    .unwrap();
+    w.assign_item_ids();
+    assert_eq!(w.items[0].name, "Socks");
+    assert_eq!(w.items[0].quantity, 1);
+    assert_eq!(w.items[1].price_cents, Some(1250));
+    assert_eq!(w.items[1].priority, Some(Priority::High));
+    assert!(w.items.iter().all(|item| !item.id.is_empty()));
+    assert_ne!(w.items[0].id, w.items[1].id);
+}
+
+#[test]
+fn test_legacy_dynamodb_items_are_upgraded() {
+    let stored = HashMap::from([
+        ("id".to_string(), AttributeValue::S("list".to_string())),
+        (
+            "name".to_string(),
+            AttributeValue::S("Birthday".to_string()),
+        ),
+        ("owner".to_string(), AttributeValue::S("alice".to_string())),
+        (
+            "items".to_string(),
+            AttributeValue::L(vec![
+                AttributeValue::S("Socks".to_string()),
+                AttributeValue::M(HashMap::from([
+                    ("id".to_string(), AttributeValue::S("abc".to_string())),
+                    ("name".to_string(), AttributeValue::S("Book".to_string())),
+                    ("quantity".to_string(), AttributeValue::N("3".to_string())),
+                ])),
+            ]),
+        ),
+    ]);
+    let mut too_many = stored.clone();
+    too_many.insert(
+        "items".to_string(),
+        AttributeValue::L(vec![AttributeValue::M(HashMap::from([
+            ("name".to_string(), AttributeValue::S("Book".to_string())),
+            (
+                "quantity".to_string(),
+                AttributeValue::N("4294967297".to_string()),
+            ),
+        ]))]),
+    );
+    let w = Wishlist::try_from(stored).unwrap();
+    assert_eq!(w.items[0], WishlistItem::new("item-0", "Socks"));
+    assert_eq!(w.items[1].id, "abc");
+    assert_eq!(w.items[1].quantity, 3);
+    assert_eq!(
+        Wishlist::try_from(too_many).unwrap_err(),
+        "Item quantity is out of range"
+    );
+}